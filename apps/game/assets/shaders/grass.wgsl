@@ -0,0 +1,89 @@
+// Places grass blades on the terrain and sways them in the wind.
+// The blade meshes stand on y = 0; their UV's y goes from 0 at the root to 1 at the tip.
+// Lighting is done by the standard material's fragment shader.
+
+#import bevy_pbr::{
+    mesh_functions,
+    forward_io::{Vertex, VertexOutput},
+    mesh_view_bindings::globals,
+    view_transformations::position_world_to_clip,
+}
+
+struct GrassMaterialSettings {
+    base_color: vec4<f32>,
+    tip_color: vec4<f32>,
+    wind_direction: vec2<f32>,
+    wind_strength: f32,
+    wind_speed: f32,
+    terrain_size: f32,
+}
+
+@group(2) @binding(100) var<uniform> grass: GrassMaterialSettings;
+// Red: terrain height, green: how much grass grows there.
+@group(2) @binding(101) var heightmap: texture_2d<f32>;
+
+// Bilinearly samples the heightmap. Float32 textures can't be filtered by the sampler.
+fn sample_terrain(world_xz: vec2<f32>) -> vec2<f32> {
+    let size = textureDimensions(heightmap);
+    let max_texel = vec2<f32>(size - vec2<u32>(1u));
+    let texel = clamp((world_xz / grass.terrain_size + 0.5) * max_texel, vec2<f32>(0.0), max_texel);
+    let base = vec2<u32>(floor(texel));
+    let next = min(base + vec2<u32>(1u), size - vec2<u32>(1u));
+    let f = fract(texel);
+
+    let a = textureLoad(heightmap, base, 0).rg;
+    let b = textureLoad(heightmap, vec2<u32>(next.x, base.y), 0).rg;
+    let c = textureLoad(heightmap, vec2<u32>(base.x, next.y), 0).rg;
+    let d = textureLoad(heightmap, next, 0).rg;
+    return mix(mix(a, b, f.x), mix(c, d, f.x), f.y);
+}
+
+@vertex
+fn vertex(vertex: Vertex) -> VertexOutput {
+    var out: VertexOutput;
+
+    let world_from_local = mesh_functions::get_world_from_local(vertex.instance_index);
+    var world_position = mesh_functions::mesh_position_local_to_world(
+        world_from_local,
+        vec4<f32>(vertex.position, 1.0),
+    );
+    let blade_height = world_position.y - world_from_local[3].y;
+    let along_blade = vertex.uv.y;
+
+    // Blades shrink where the terrain shows rock or snow, and sink below the ground where
+    // no grass grows at all.
+    let terrain = sample_terrain(world_position.xz);
+    let coverage = terrain.y;
+    world_position.y = terrain.x - 0.05 + (blade_height + 0.05) * coverage;
+
+    // Slow gusts roll over the field, with a faster flutter on top.
+    let time = globals.time * grass.wind_speed;
+    let gust_phase = dot(world_position.xz, grass.wind_direction) * 0.15 - time;
+    let gust = 0.6 + 0.4 * sin(gust_phase) * sin(gust_phase * 0.37 + 1.3);
+    let flutter = 0.15 * sin(time * 3.1 + world_position.x * 1.7 + world_position.z * 1.3);
+    // Roots stay put, tips move the most.
+    let bend = grass.wind_strength * (gust + flutter) * along_blade * along_blade * coverage;
+    world_position.x += grass.wind_direction.x * bend;
+    world_position.z += grass.wind_direction.y * bend;
+    world_position.y -= 0.5 * bend * bend;
+
+    out.world_position = world_position;
+    out.position = position_world_to_clip(world_position.xyz);
+    out.world_normal = mesh_functions::mesh_normal_local_to_world(vertex.normal, vertex.instance_index);
+    out.uv = vertex.uv;
+    // The vertex color is a per-blade brightness variation.
+    out.color = mix(grass.base_color, grass.tip_color, along_blade) * vertex.color;
+
+#ifdef VERTEX_OUTPUT_INSTANCE_INDEX
+    out.instance_index = vertex.instance_index;
+#endif
+
+#ifdef VISIBILITY_RANGE_DITHER
+    out.visibility_range_dither = mesh_functions::get_visibility_range_dither_level(
+        vertex.instance_index,
+        world_from_local[3],
+    );
+#endif
+
+    return out;
+}
@@ -0,0 +1,202 @@
+// Stochastic hex-tiling and layer splatting for procedural terrain.
+// Hex-tiling is based on Mikkelsen, "Practical Real-Time Hex-Tiling", JCGT 2022.
+// The layer weights come from the vertex colors in the order (lowland, ground, cliff, peak).
+
+#import bevy_pbr::{
+    pbr_fragment::pbr_input_from_standard_material,
+    pbr_functions::alpha_discard,
+    mesh_view_bindings::view,
+}
+
+#ifdef PREPASS_PIPELINE
+#import bevy_pbr::{
+    prepass_io::{VertexOutput, FragmentOutput},
+    pbr_deferred_functions::deferred_output,
+}
+#else
+#import bevy_pbr::{
+    forward_io::{VertexOutput, FragmentOutput},
+    pbr_functions::{apply_pbr_lighting, main_pass_post_lighting_processing},
+}
+#endif
+
+struct TerrainMaterialSettings {
+    tile_size: f32,
+    rotation_strength: f32,
+    blend_contrast: f32,
+    macro_scale: f32,
+    macro_strength_near: f32,
+    macro_strength_far: f32,
+    far_distance: f32,
+    far_tile_multiplier: f32,
+}
+
+@group(2) @binding(100) var<uniform> terrain: TerrainMaterialSettings;
+@group(2) @binding(101) var ground_texture: texture_2d<f32>;
+@group(2) @binding(102) var terrain_sampler: sampler;
+@group(2) @binding(103) var lowland_texture: texture_2d<f32>;
+@group(2) @binding(104) var cliff_texture: texture_2d<f32>;
+@group(2) @binding(105) var peak_texture: texture_2d<f32>;
+
+const TAU: f32 = 6.28318530718;
+
+fn hash2(p: vec2<f32>) -> vec2<f32> {
+    let q = vec2<f32>(dot(p, vec2<f32>(127.1, 311.7)), dot(p, vec2<f32>(269.5, 183.3)));
+    return fract(sin(q) * 43758.5453);
+}
+
+fn value_noise(p: vec2<f32>) -> f32 {
+    let i = floor(p);
+    let f = fract(p);
+    let u = f * f * (3.0 - 2.0 * f);
+    let a = hash2(i).x;
+    let b = hash2(i + vec2<f32>(1.0, 0.0)).x;
+    let c = hash2(i + vec2<f32>(0.0, 1.0)).x;
+    let d = hash2(i + vec2<f32>(1.0, 1.0)).x;
+    return mix(mix(a, b, u.x), mix(c, d, u.x), u.y);
+}
+
+struct HexGrid {
+    weights: vec3<f32>,
+    vertex1: vec2<f32>,
+    vertex2: vec2<f32>,
+    vertex3: vec2<f32>,
+}
+
+// Finds the three hex cell centers surrounding `uv` and their barycentric weights.
+fn hex_grid(uv: vec2<f32>) -> HexGrid {
+    let st = uv * 2.0 * sqrt(3.0);
+    let skewed = mat2x2<f32>(1.0, 0.0, -0.57735027, 1.15470054) * st;
+    let base_id = floor(skewed);
+    let f = fract(skewed);
+    let z = 1.0 - f.x - f.y;
+    let s = step(0.0, -z);
+    let s2 = 2.0 * s - 1.0;
+
+    var grid: HexGrid;
+    grid.weights = vec3<f32>(-z * s2, s - f.y * s2, s - f.x * s2);
+    grid.vertex1 = base_id + vec2<f32>(s, s);
+    grid.vertex2 = base_id + vec2<f32>(s, 1.0 - s);
+    grid.vertex3 = base_id + vec2<f32>(1.0 - s, s);
+    return grid;
+}
+
+fn cell_rotation(cell: vec2<f32>) -> mat2x2<f32> {
+    let angle = (hash2(cell).x - 0.5) * TAU * terrain.rotation_strength;
+    let c = cos(angle);
+    let s = sin(angle);
+    return mat2x2<f32>(c, s, -s, c);
+}
+
+fn cell_offset(cell: vec2<f32>) -> vec2<f32> {
+    return hash2(cell + vec2<f32>(17.0, 59.0));
+}
+
+// Everything needed to sample any layer with the same hex-tiling pattern.
+struct HexSampling {
+    weights: vec3<f32>,
+    uv1: vec2<f32>,
+    uv2: vec2<f32>,
+    uv3: vec2<f32>,
+    rotation1: mat2x2<f32>,
+    rotation2: mat2x2<f32>,
+    rotation3: mat2x2<f32>,
+    ddx: vec2<f32>,
+    ddy: vec2<f32>,
+}
+
+// Must be called from uniform control flow, as it takes derivatives of `uv`.
+fn hex_sampling(uv: vec2<f32>) -> HexSampling {
+    let grid = hex_grid(uv);
+
+    var sampling: HexSampling;
+    sampling.ddx = dpdx(uv);
+    sampling.ddy = dpdy(uv);
+    sampling.rotation1 = cell_rotation(grid.vertex1);
+    sampling.rotation2 = cell_rotation(grid.vertex2);
+    sampling.rotation3 = cell_rotation(grid.vertex3);
+    sampling.uv1 = sampling.rotation1 * uv + cell_offset(grid.vertex1);
+    sampling.uv2 = sampling.rotation2 * uv + cell_offset(grid.vertex2);
+    sampling.uv3 = sampling.rotation3 * uv + cell_offset(grid.vertex3);
+
+    let w = pow(max(grid.weights, vec3<f32>(0.0)), vec3<f32>(terrain.blend_contrast));
+    sampling.weights = w / max(w.x + w.y + w.z, 1e-5);
+    return sampling;
+}
+
+// Uses explicit gradients, so this is safe to call from non-uniform control flow.
+fn sample_layer(layer: texture_2d<f32>, s: HexSampling) -> vec4<f32> {
+    let c1 = textureSampleGrad(layer, terrain_sampler, s.uv1, s.rotation1 * s.ddx, s.rotation1 * s.ddy);
+    let c2 = textureSampleGrad(layer, terrain_sampler, s.uv2, s.rotation2 * s.ddx, s.rotation2 * s.ddy);
+    let c3 = textureSampleGrad(layer, terrain_sampler, s.uv3, s.rotation3 * s.ddx, s.rotation3 * s.ddy);
+    return c1 * s.weights.x + c2 * s.weights.y + c3 * s.weights.z;
+}
+
+fn sample_layers(s: HexSampling, splat: vec4<f32>) -> vec4<f32> {
+    // Skip layers that don't contribute; most of the terrain only shows one or two of them.
+    var color = vec4<f32>(0.0);
+    if splat.x > 0.001 {
+        color += splat.x * sample_layer(lowland_texture, s);
+    }
+    if splat.y > 0.001 {
+        color += splat.y * sample_layer(ground_texture, s);
+    }
+    if splat.z > 0.001 {
+        color += splat.z * sample_layer(cliff_texture, s);
+    }
+    if splat.w > 0.001 {
+        color += splat.w * sample_layer(peak_texture, s);
+    }
+    return color / max(splat.x + splat.y + splat.z + splat.w, 1e-5);
+}
+
+fn terrain_color(world_position: vec3<f32>, splat: vec4<f32>) -> vec4<f32> {
+    let uv = world_position.xz / terrain.tile_size;
+    let near_sampling = hex_sampling(uv);
+    let far_sampling = hex_sampling(uv / terrain.far_tile_multiplier);
+
+    let camera_distance = length(view.world_position - world_position);
+    let far_blend = smoothstep(terrain.far_distance * 0.25, terrain.far_distance, camera_distance);
+
+    var color = vec4<f32>(0.0);
+    if far_blend < 1.0 {
+        color += (1.0 - far_blend) * sample_layers(near_sampling, splat);
+    }
+    if far_blend > 0.0 {
+        color += far_blend * sample_layers(far_sampling, splat);
+    }
+
+    let macro_noise = value_noise(world_position.xz / terrain.macro_scale)
+        + 0.5 * value_noise(world_position.xz / (terrain.macro_scale * 0.37));
+    let macro_strength = mix(terrain.macro_strength_near, terrain.macro_strength_far, far_blend);
+    let brightness = 1.0 + (macro_noise / 1.5 - 0.5) * 2.0 * macro_strength;
+    return vec4<f32>(color.rgb * brightness, color.a);
+}
+
+@fragment
+fn fragment(
+    in: VertexOutput,
+    @builtin(front_facing) is_front: bool,
+) -> FragmentOutput {
+    var pbr_input = pbr_input_from_standard_material(in, is_front);
+
+#ifdef VERTEX_COLORS
+    // The vertex colors hold layer weights, not colors, so they must not tint the result.
+    let splat = in.color;
+#else
+    let splat = vec4<f32>(0.0, 1.0, 0.0, 0.0);
+#endif
+    let ground = terrain_color(in.world_position.xyz, splat);
+    pbr_input.material.base_color = vec4<f32>(ground.rgb, 1.0);
+    pbr_input.material.base_color = alpha_discard(pbr_input.material, pbr_input.material.base_color);
+
+#ifdef PREPASS_PIPELINE
+    // Lighting for deferred materials happens in a separate fullscreen pass.
+    let out = deferred_output(in, pbr_input);
+#else
+    var out: FragmentOutput;
+    out.color = apply_pbr_lighting(pbr_input);
+    out.color = main_pass_post_lighting_processing(pbr_input, out.color);
+#endif
+    return out;
+}
@@ -0,0 +1,80 @@
+// Water surface for procedural levels.
+// The water depth below each vertex is stored in the second UV channel. Shallow water is clear and
+// light, deep water dark and opaque. Reflections come from the environment map through the
+// regular PBR lighting, with the surface normal perturbed by a few moving waves.
+
+#import bevy_pbr::{
+    pbr_fragment::pbr_input_from_standard_material,
+    mesh_view_bindings::globals,
+    forward_io::{VertexOutput, FragmentOutput},
+    pbr_functions::{apply_pbr_lighting, main_pass_post_lighting_processing},
+}
+
+struct WaterMaterialSettings {
+    shallow_color: vec4<f32>,
+    deep_color: vec4<f32>,
+    deep_depth: f32,
+    wave_length: f32,
+    wave_strength: f32,
+    wave_speed: f32,
+}
+
+@group(2) @binding(100) var<uniform> water: WaterMaterialSettings;
+
+const TAU: f32 = 6.28318530718;
+
+// Slope of the water surface from a sum of directional sine waves.
+fn wave_gradient(position: vec2<f32>, time: f32) -> vec2<f32> {
+    var gradient = vec2<f32>(0.0);
+    var wave_length = water.wave_length;
+    var amplitude = 1.0;
+    var direction = normalize(vec2<f32>(1.0, 0.4));
+    for (var i = 0; i < 4; i++) {
+        let frequency = TAU / wave_length;
+        // Deep water waves travel with a speed proportional to the square root of their length.
+        let phase = frequency * dot(direction, position) - sqrt(wave_length) * water.wave_speed * time;
+        gradient += direction * (amplitude * frequency * cos(phase));
+        wave_length *= 0.57;
+        amplitude *= 0.55;
+        direction = vec2<f32>(direction.x * 0.6 - direction.y * 0.8, direction.x * 0.8 + direction.y * 0.6);
+    }
+    return gradient;
+}
+
+@fragment
+fn fragment(
+    in: VertexOutput,
+    @builtin(front_facing) is_front: bool,
+) -> FragmentOutput {
+#ifdef VERTEX_UVS_B
+    let depth = in.uv_b.x;
+#else
+    let depth = water.deep_depth;
+#endif
+    if depth <= 0.0 {
+        discard;
+    }
+
+    var pbr_input = pbr_input_from_standard_material(in, is_front);
+
+    let depth_factor = smoothstep(0.0, water.deep_depth, depth);
+    var color = mix(water.shallow_color, water.deep_color, depth_factor);
+    // Fade out towards the shore so the water line isn't a hard edge.
+    color.a *= smoothstep(0.0, 0.3, depth);
+    pbr_input.material.base_color = color;
+
+    // Waves calm down in the shallows.
+    let gradient = wave_gradient(in.world_position.xz, globals.time)
+        * water.wave_strength * mix(0.3, 1.0, depth_factor);
+    var normal = normalize(vec3<f32>(-gradient.x, 1.0, -gradient.y));
+    if !is_front {
+        normal = -normal;
+    }
+    pbr_input.N = normal;
+    pbr_input.world_normal = normal;
+
+    var out: FragmentOutput;
+    out.color = apply_pbr_lighting(pbr_input);
+    out.color = main_pass_post_lighting_processing(pbr_input, out.color);
+    return out;
+}
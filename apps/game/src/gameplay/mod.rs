@@ -11,6 +11,14 @@ pub(crate) mod level;
 pub(crate) mod npc;
 pub(crate) mod player;
 pub(crate) mod procedural_level;
+pub(crate) mod procedural_navmesh;
+pub(crate) mod sky_lighting;
+pub(crate) mod terrain_material;
+pub(crate) mod time_of_day;
+pub(crate) mod vegetation;
+pub(crate) mod water;
+pub(crate) mod world_plan;
+pub(crate) mod world_settings;
 
 pub(super) fn plugin(app: &mut App) {
     app.add_plugins((
@@ -18,6 +26,14 @@ pub(super) fn plugin(app: &mut App) {
         crosshair::plugin,
         npc::plugin,
         player::plugin,
+        procedural_navmesh::plugin,
+        sky_lighting::plugin,
+        terrain_material::plugin,
+        time_of_day::plugin,
+        vegetation::plugin,
+        water::plugin,
+        world_plan::plugin,
+        world_settings::plugin,
         // These plugins preload the levels,
         // so make sure to add them last.
         level::plugin,
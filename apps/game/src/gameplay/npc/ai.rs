@@ -19,7 +19,7 @@ use crate::{
     screens::Screen,
 };
 
-use super::{NPC_FLOAT_HEIGHT, NPC_RADIUS, Npc};
+use super::{NPC_FLOAT_HEIGHT, NPC_RADIUS, Npc, behaviour::NpcBehaviour};
 
 pub(crate) const NPC_MAX_SLOPE: f32 = TAU / 6.0;
 
@@ -33,7 +33,7 @@ pub(super) fn plugin(app: &mut App) {
             .chain()
             .in_set(RunFixedMainLoopSystem::BeforeFixedMainLoop)
             .before(LandmassSystemSet::SyncExistence)
-            .run_if(in_state(Screen::Gameplay)),
+            .run_if(in_state(Screen::Gameplay).or(in_state(Screen::ProceduralGameplay))),
     );
     app.add_systems(
         RunFixedMainLoop,
@@ -44,14 +44,16 @@ pub(super) fn plugin(app: &mut App) {
 
 /// Setup the NPC agent. An "agent" is what `bevy_landmass` can move around.
 /// Since we use a floating character controller, we need to offset the agent's position by the character's float height.
+/// NPCs with an [`NpcBehaviour`] pick their own targets, all others follow the player.
 #[cfg_attr(feature = "hot_patch", hot)]
 fn setup_npc_agent(
     trigger: Trigger<OnAdd, Npc>,
     mut commands: Commands,
     archipelago: Single<Entity, With<Archipelago3d>>,
+    behaviours: Query<(), With<NpcBehaviour>>,
 ) {
     let npc = trigger.target();
-    commands.spawn((
+    let mut agent = commands.spawn((
         Name::new("NPC Agent"),
         Transform::from_translation(Vec3::new(0.0, -NPC_FLOAT_HEIGHT, 0.0)),
         Agent3dBundle {
@@ -67,8 +69,10 @@ fn setup_npc_agent(
         ChildOf(npc),
         AgentOf(npc),
         AgentTarget3d::default(),
-        WantsToFollowPlayer,
     ));
+    if !behaviours.contains(npc) {
+        agent.insert(WantsToFollowPlayer);
+    }
 }
 
 #[derive(Component, Debug, Reflect)]
@@ -96,7 +100,7 @@ struct AgentOf(Entity);
 #[derive(Component, Deref, Debug, Reflect)]
 #[reflect(Component)]
 #[relationship_target(relationship = AgentOf)]
-struct Agent(Entity);
+pub(crate) struct Agent(Entity);
 
 /// Use the desired velocity as the agent's velocity.
 #[cfg_attr(feature = "hot_patch", hot)]
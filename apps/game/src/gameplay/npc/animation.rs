@@ -16,7 +16,7 @@ pub(super) fn plugin(app: &mut App) {
     app.add_systems(
         Update,
         play_animations
-            .run_if(in_state(Screen::Gameplay))
+            .run_if(in_state(Screen::Gameplay).or(in_state(Screen::ProceduralGameplay)))
             .in_set(PostPhysicsAppSystems::PlayAnimations),
     );
 }
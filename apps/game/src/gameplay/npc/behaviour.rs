@@ -0,0 +1,264 @@
+//! NPC behaviours for procedural levels.
+//!
+//! Each NPC is configured with an [`NpcBehaviour`], which describes its routine and whether it is
+//! afraid of the player. A small state machine in [`NpcBehaviourState`] turns that into navmesh
+//! targets for the NPC's landmass agent: idling, wandering around its home, walking a patrol route,
+//! following the player or fleeing from them.
+
+use std::ops::Range;
+
+use bevy::prelude::*;
+use bevy_landmass::prelude::*;
+#[cfg(feature = "hot_patch")]
+use bevy_simple_subsecond_system::hot;
+use rand::Rng as _;
+
+use crate::{
+    PrePhysicsAppSystems,
+    gameplay::{
+        player::{Player, navmesh_position::LastValidPlayerNavmeshPosition},
+        procedural_level::{TERRAIN_SCALE, TERRAIN_SIZE, sample_terrain_height},
+    },
+    screens::Screen,
+};
+
+use super::ai::Agent;
+
+/// How close an NPC has to get to a wander or patrol target to count as having arrived.
+const ARRIVAL_DISTANCE: f32 = 1.5;
+/// How long an NPC pauses between wander targets, in seconds.
+const WANDER_PAUSE: Range<f32> = 2.0..6.0;
+/// A fleeing NPC only calms down once the player is this many times its flee distance away.
+const CALM_DOWN_FACTOR: f32 = 2.0;
+/// Agent speeds per state. The fast ones match the NPC in the main level.
+const WALK_SPEED: f32 = 2.5;
+const FOLLOW_SPEED: f32 = 7.0;
+const FLEE_SPEED: f32 = 8.0;
+
+pub(super) fn plugin(app: &mut App) {
+    app.register_type::<NpcBehaviour>();
+    app.register_type::<NpcBehaviourState>();
+    app.add_observer(init_behaviour_state);
+    app.add_systems(
+        RunFixedMainLoop,
+        update_npc_behaviours
+            .in_set(PrePhysicsAppSystems::UpdateNavmeshTargets)
+            .run_if(in_state(Screen::ProceduralGameplay)),
+    );
+}
+
+/// How an NPC behaves. NPCs without this component follow the player.
+#[derive(Component, Reflect, Debug, Clone)]
+#[reflect(Component)]
+pub(crate) struct NpcBehaviour {
+    /// What the NPC does while nothing disturbs it.
+    pub(crate) routine: NpcRoutine,
+    /// If set, the NPC runs away when the player comes closer than this many meters.
+    pub(crate) flee_distance: Option<f32>,
+}
+
+impl NpcBehaviour {
+    pub(crate) fn new(routine: NpcRoutine) -> Self {
+        Self {
+            routine,
+            flee_distance: None,
+        }
+    }
+
+    pub(crate) fn fleeing_within(mut self, distance: f32) -> Self {
+        self.flee_distance = Some(distance);
+        self
+    }
+
+    /// A short description of the behaviour, e.g. for prompting a language model.
+    pub(crate) fn describe(&self) -> String {
+        let routine = match &self.routine {
+            NpcRoutine::Idle => "stays in one place",
+            NpcRoutine::Wander { .. } => "wanders around the area",
+            NpcRoutine::Patrol { .. } => "patrols a fixed route",
+            NpcRoutine::Follow { .. } => "follows the player around",
+        };
+        if self.flee_distance.is_some() {
+            format!("{routine} and is shy, running away when approached")
+        } else {
+            routine.to_string()
+        }
+    }
+}
+
+#[derive(Reflect, Debug, Clone)]
+pub(crate) enum NpcRoutine {
+    /// Stand around at home.
+    Idle,
+    /// Walk to random points within `radius` meters of home, pausing in between.
+    Wander { radius: f32 },
+    /// Walk along the waypoints in a loop. The waypoints are offsets from home.
+    Patrol { waypoints: Vec<Vec3> },
+    /// Stay within `distance` meters of the player.
+    Follow { distance: f32 },
+}
+
+/// The state an NPC with an [`NpcBehaviour`] is in.
+#[derive(Component, Reflect, Debug, Clone)]
+#[reflect(Component)]
+pub(crate) struct NpcBehaviourState {
+    /// Where the NPC spawned. Wandering and patrolling happen relative to it.
+    pub(crate) home: Vec3,
+    pub(crate) state: NpcState,
+}
+
+#[derive(Reflect, Debug, Clone, PartialEq)]
+pub(crate) enum NpcState {
+    Idle { remaining: f32 },
+    Wandering { target: Vec3 },
+    Patrolling { waypoint: usize },
+    Following,
+    Fleeing,
+}
+
+impl NpcState {
+    fn initial(routine: &NpcRoutine) -> Self {
+        match routine {
+            NpcRoutine::Idle => Self::Idle {
+                remaining: f32::INFINITY,
+            },
+            NpcRoutine::Wander { .. } => Self::Idle { remaining: 0.0 },
+            NpcRoutine::Patrol { .. } => Self::Patrolling { waypoint: 0 },
+            NpcRoutine::Follow { .. } => Self::Following,
+        }
+    }
+
+    fn speed(&self) -> f32 {
+        match self {
+            Self::Following => FOLLOW_SPEED,
+            Self::Fleeing => FLEE_SPEED,
+            _ => WALK_SPEED,
+        }
+    }
+}
+
+#[cfg_attr(feature = "hot_patch", hot)]
+fn init_behaviour_state(
+    trigger: Trigger<OnAdd, NpcBehaviour>,
+    mut commands: Commands,
+    npcs: Query<(&NpcBehaviour, &Transform)>,
+) {
+    let Ok((behaviour, transform)) = npcs.get(trigger.target()) else {
+        return;
+    };
+    commands.entity(trigger.target()).insert(NpcBehaviourState {
+        home: transform.translation,
+        state: NpcState::initial(&behaviour.routine),
+    });
+}
+
+#[cfg_attr(feature = "hot_patch", hot)]
+fn update_npc_behaviours(
+    time: Res<Time>,
+    mut npcs: Query<(
+        &NpcBehaviour,
+        &mut NpcBehaviourState,
+        &GlobalTransform,
+        &Agent,
+    )>,
+    mut agents: Query<(&mut AgentTarget3d, &mut AgentSettings)>,
+    player: Option<Single<&GlobalTransform, With<Player>>>,
+    player_navmesh_position: Option<Single<&LastValidPlayerNavmeshPosition>>,
+) {
+    let player_position = player.map(|player| player.translation());
+    let player_navmesh_position = player_navmesh_position.and_then(|position| position.0);
+    let rng = &mut rand::thread_rng();
+    let dt = time.delta_secs();
+
+    for (behaviour, mut behaviour_state, transform, agent) in &mut npcs {
+        let Ok((mut agent_target, mut settings)) = agents.get_mut(**agent) else {
+            continue;
+        };
+        let position = transform.translation();
+        let player_distance = player_position.map(|player| player.distance(position));
+        let home = behaviour_state.home;
+
+        // Fear overrides the routine.
+        if let (Some(flee_distance), Some(player_distance)) =
+            (behaviour.flee_distance, player_distance)
+        {
+            let state = &mut behaviour_state.state;
+            if *state != NpcState::Fleeing && player_distance < flee_distance {
+                *state = NpcState::Fleeing;
+            } else if *state == NpcState::Fleeing
+                && player_distance > flee_distance * CALM_DOWN_FACTOR
+            {
+                *state = NpcState::initial(&behaviour.routine);
+            }
+        }
+
+        let next_target = match (&mut behaviour_state.state, &behaviour.routine) {
+            (NpcState::Fleeing, _) => player_position.map(|player| {
+                let away = (position - player).with_y(0.0).normalize_or(Vec3::X);
+                let distance = behaviour.flee_distance.unwrap_or_default() * CALM_DOWN_FACTOR;
+                ground_point(position + away * (distance + ARRIVAL_DISTANCE))
+            }),
+            (NpcState::Following, NpcRoutine::Follow { distance }) => {
+                match (player_distance, player_navmesh_position) {
+                    (Some(player_distance), Some(player)) if player_distance > *distance => {
+                        Some(player)
+                    }
+                    _ => None,
+                }
+            }
+            (NpcState::Patrolling { waypoint }, NpcRoutine::Patrol { waypoints })
+                if !waypoints.is_empty() =>
+            {
+                let mut point = ground_point(home + waypoints[*waypoint % waypoints.len()]);
+                if horizontal_distance(position, point) < ARRIVAL_DISTANCE {
+                    *waypoint = (*waypoint + 1) % waypoints.len();
+                    point = ground_point(home + waypoints[*waypoint]);
+                }
+                Some(point)
+            }
+            (NpcState::Wandering { target }, NpcRoutine::Wander { .. }) => {
+                let target = *target;
+                if horizontal_distance(position, target) < ARRIVAL_DISTANCE {
+                    behaviour_state.state = NpcState::Idle {
+                        remaining: rng.gen_range(WANDER_PAUSE),
+                    };
+                    None
+                } else {
+                    Some(target)
+                }
+            }
+            (NpcState::Idle { remaining }, NpcRoutine::Wander { radius }) => {
+                *remaining -= dt;
+                if *remaining <= 0.0 {
+                    let angle = rng.gen_range(0.0..std::f32::consts::TAU);
+                    let distance = radius * rng.gen_range(0.0_f32..1.0).sqrt();
+                    let target =
+                        ground_point(home + Vec3::new(angle.cos(), 0.0, angle.sin()) * distance);
+                    behaviour_state.state = NpcState::Wandering { target };
+                    Some(target)
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        };
+
+        *agent_target = match next_target {
+            Some(point) => AgentTarget3d::Point(point),
+            None => AgentTarget3d::None,
+        };
+        settings.desired_speed = behaviour_state.state.speed();
+    }
+}
+
+fn horizontal_distance(a: Vec3, b: Vec3) -> f32 {
+    a.xz().distance(b.xz())
+}
+
+/// The point on the terrain below or above the given position, kept inside the level.
+fn ground_point(position: Vec3) -> Vec3 {
+    let half_size = TERRAIN_SIZE as f32 * TERRAIN_SCALE / 2.0 - ARRIVAL_DISTANCE;
+    let x = position.x.clamp(-half_size, half_size);
+    let z = position.z.clamp(-half_size, half_size);
+    Vec3::new(x, sample_terrain_height(x, z), z)
+}
@@ -0,0 +1,200 @@
+//! Generated Yarn dialogue for the NPCs of procedural levels.
+//!
+//! Every planned NPC talks through its own node in `dialogue/procedural_npc.yarn`. That file only
+//! holds placeholders; once the level spawns, dialogue grounded in the world prompt and the props
+//! around each NPC is generated, validated and swapped into the dialogue runner in their place.
+//! Generated props change what NPCs can talk about, so the dialogue is regenerated after new ones appear.
+
+use std::time::Duration;
+
+use bevy::{
+    prelude::*,
+    tasks::{IoTaskPool, Task},
+};
+#[cfg(feature = "hot_patch")]
+use bevy_simple_subsecond_system::hot;
+use bevy_yarnspinner::prelude::*;
+use futures_lite::future;
+
+use crate::{
+    gameplay::{npc::behaviour::NpcBehaviour, world_plan::NpcPlan},
+    generate::generate_dialogue::{
+        GENERATED_NODE_PREFIX, GeneratedDialogue, NpcDialogueBrief, compile_dialogue,
+        generate_npc_dialogue,
+    },
+    menus::generate::GenerationPrompt,
+    props::generated::spawn::GeneratedProp,
+    screens::Screen,
+    third_party::bevy_yarnspinner::{YarnNode, is_dialogue_running},
+};
+
+/// The Yarn file holding the placeholder nodes that generated dialogue replaces.
+pub(crate) const PROCEDURAL_DIALOGUE_PATH: &str = "dialogue/procedural_npc.yarn";
+/// Props closer than this to an NPC are mentioned in its dialogue.
+const NEARBY_PROP_DISTANCE: f32 = 15.0;
+/// How long to wait after props change before regenerating, so placing several props in a row
+/// only costs one request.
+const REGENERATION_DELAY: Duration = Duration::from_secs(5);
+
+pub(super) fn plugin(app: &mut App) {
+    app.register_type::<ProceduralNpcDialogue>();
+    app.add_systems(OnEnter(Screen::ProceduralGameplay), request_dialogue);
+    app.add_systems(
+        OnExit(Screen::ProceduralGameplay),
+        reset_dialogue_generation,
+    );
+    app.add_systems(
+        Update,
+        (
+            request_dialogue_for_new_props,
+            start_dialogue_generation,
+            apply_generated_dialogue.run_if(not(is_dialogue_running)),
+        )
+            .chain()
+            .run_if(in_state(Screen::ProceduralGameplay)),
+    );
+    app.init_resource::<DialogueGeneration>();
+}
+
+/// Marks an NPC of a procedural level that talks through generated dialogue.
+#[derive(Component, Reflect, Debug, Clone)]
+#[reflect(Component)]
+pub(crate) struct ProceduralNpcDialogue {
+    pub(crate) name: String,
+}
+
+/// The dialogue components of the `index`th planned NPC.
+pub(crate) fn procedural_npc_dialogue(index: usize, plan: &NpcPlan) -> impl Bundle {
+    (
+        ProceduralNpcDialogue {
+            name: plan.name.clone(),
+        },
+        YarnNode {
+            yarn_node: format!("{GENERATED_NODE_PREFIX}{}", index + 1),
+            prompt: format!("Talk to {}", plan.name),
+        },
+    )
+}
+
+#[derive(Resource, Default)]
+struct DialogueGeneration {
+    /// When the dialogue should be (re)generated next.
+    requested_at: Option<Duration>,
+    task: Option<Task<GeneratedDialogue>>,
+    /// Generated dialogue waiting for the current conversation to end.
+    pending: Option<GeneratedDialogue>,
+}
+
+fn request_dialogue(mut generation: ResMut<DialogueGeneration>, time: Res<Time>) {
+    generation.requested_at = Some(time.elapsed());
+}
+
+fn reset_dialogue_generation(mut generation: ResMut<DialogueGeneration>) {
+    *generation = DialogueGeneration::default();
+}
+
+#[cfg_attr(feature = "hot_patch", hot)]
+fn request_dialogue_for_new_props(
+    new_props: Query<(), Added<GeneratedProp>>,
+    mut generation: ResMut<DialogueGeneration>,
+    time: Res<Time>,
+) {
+    if !new_props.is_empty() {
+        generation.requested_at = Some(time.elapsed() + REGENERATION_DELAY);
+    }
+}
+
+#[cfg_attr(feature = "hot_patch", hot)]
+fn start_dialogue_generation(
+    mut generation: ResMut<DialogueGeneration>,
+    time: Res<Time>,
+    prompt: Res<GenerationPrompt>,
+    npcs: Query<(
+        &ProceduralNpcDialogue,
+        &YarnNode,
+        &NpcBehaviour,
+        &GlobalTransform,
+    )>,
+    props: Query<(&GeneratedProp, &GlobalTransform)>,
+) {
+    if let Some(task) = &mut generation.task {
+        if let Some(dialogue) = future::block_on(future::poll_once(task)) {
+            generation.task = None;
+            generation.pending = Some(dialogue);
+        }
+        return;
+    }
+    let Some(requested_at) = generation.requested_at else {
+        return;
+    };
+    if time.elapsed() < requested_at || npcs.is_empty() {
+        return;
+    }
+    generation.requested_at = None;
+
+    let briefs = npcs
+        .iter()
+        .map(|(dialogue, node, behaviour, transform)| {
+            let position = transform.translation();
+            let nearby_props = props
+                .iter()
+                .filter(|(_, prop_transform)| {
+                    prop_transform.translation().distance(position) < NEARBY_PROP_DISTANCE
+                })
+                .map(|(prop, _)| prop.prompt.clone())
+                .collect();
+            NpcDialogueBrief {
+                node: node.yarn_node.clone(),
+                name: dialogue.name.clone(),
+                behaviour: behaviour.describe(),
+                nearby_props,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    info!(npcs = briefs.len(), "generating NPC dialogue");
+    let world_prompt = prompt.0.clone();
+    generation.task =
+        Some(IoTaskPool::get().spawn(async move { generate_npc_dialogue(&world_prompt, &briefs) }));
+}
+
+/// Swaps generated dialogue into the dialogue runners, in place of the placeholder nodes. The Yarn
+/// project only recompiles changed files when the file watcher is on, which it isn't in release
+/// builds, so the dialogue is compiled here instead. This waits for running conversations to end,
+/// so that no line disappears while it is shown.
+#[cfg_attr(feature = "hot_patch", hot)]
+fn apply_generated_dialogue(
+    mut generation: ResMut<DialogueGeneration>,
+    yarn_project: Res<YarnProject>,
+    mut dialogue_runners: Query<&mut DialogueRunner>,
+) {
+    let Some(dialogue) = generation.pending.take() else {
+        return;
+    };
+    let compilation = match compile_dialogue(&dialogue.source) {
+        Ok(compilation) => compilation,
+        Err(err) => {
+            error!(?err, "generated NPC dialogue stopped compiling");
+            return;
+        }
+    };
+    let (Some(mut program), Some(generated)) = (
+        yarn_project.compilation().program.clone(),
+        compilation.program,
+    ) else {
+        error!("the Yarn project or the generated NPC dialogue has no program");
+        return;
+    };
+    program
+        .nodes
+        .retain(|title, _| !title.starts_with(GENERATED_NODE_PREFIX));
+    program.nodes.extend(generated.nodes);
+
+    for mut dialogue_runner in &mut dialogue_runners {
+        dialogue_runner.inner_mut().replace_program(program.clone());
+        dialogue_runner
+            .text_provider_mut()
+            .extend_base_string_table(compilation.string_table.clone());
+    }
+    info!(fallback = dialogue.is_fallback, "updated NPC dialogue");
+}
@@ -18,10 +18,23 @@ use super::animation::AnimationPlayerAncestor;
 pub(crate) mod ai;
 mod animation;
 mod assets;
+pub(crate) mod behaviour;
+pub(crate) mod dialogue;
+mod procedural;
 mod sound;
+pub(crate) mod voice;
 
 pub(super) fn plugin(app: &mut App) {
-    app.add_plugins((ai::plugin, animation::plugin, assets::plugin, sound::plugin));
+    app.add_plugins((
+        ai::plugin,
+        animation::plugin,
+        assets::plugin,
+        behaviour::plugin,
+        dialogue::plugin,
+        procedural::plugin,
+        sound::plugin,
+        voice::plugin,
+    ));
     app.register_type::<Npc>();
     app.add_observer(on_add);
 }
@@ -54,9 +67,10 @@ fn on_add(trigger: Trigger<OnAdd, Npc>, mut commands: Commands, assets: Res<Asse
             TnuaAnimatingState::<NpcAnimationState>::default(),
             AnimationPlayerAncestor,
             CollisionLayers::new(CollisionLayer::Character, LayerMask::ALL),
-            // The Yarn Node is what we use to trigger dialogue.
-            YarnNode::new("Npc"),
         ))
+        // The Yarn Node is what we use to trigger dialogue.
+        // NPCs in procedural levels come with their own node.
+        .insert_if_new(YarnNode::new("Npc"))
         .with_child((
             Name::new("Npc Model"),
             SceneRoot(assets.load_trenchbroom_model::<Npc>()),
@@ -0,0 +1,73 @@
+//! Spawning the NPCs of a procedural level from its [`WorldPlan`].
+
+use bevy::prelude::*;
+#[cfg(feature = "hot_patch")]
+use bevy_simple_subsecond_system::hot;
+use rand::Rng as _;
+
+use crate::{
+    gameplay::{
+        player::dialogue::conversation::ConversationalNpc,
+        procedural_level::{PLAYER_SPAWN_POSITION, sample_terrain_height, spawn_procedural_level},
+        world_plan::WorldPlan,
+        world_settings::WorldSettings,
+    },
+    generate::generate_conversation::NpcPersona,
+    screens::Screen,
+};
+
+use super::{NPC_FLOAT_HEIGHT, Npc, dialogue::procedural_npc_dialogue};
+
+/// NPCs spawn at this distance range from the player, in meters.
+const SPAWN_DISTANCE: std::ops::Range<f32> = 8.0..35.0;
+/// How often to look for a spot on dry land before giving up on an NPC.
+const SPAWN_ATTEMPTS: usize = 16;
+
+pub(super) fn plugin(app: &mut App) {
+    app.add_systems(
+        OnEnter(Screen::ProceduralGameplay),
+        spawn_planned_npcs.after(spawn_procedural_level),
+    );
+}
+
+#[cfg_attr(feature = "hot_patch", hot)]
+fn spawn_planned_npcs(
+    mut commands: Commands,
+    plan: Option<Res<WorldPlan>>,
+    settings: Res<WorldSettings>,
+) {
+    let Some(plan) = plan else {
+        return;
+    };
+    let rng = &mut rand::thread_rng();
+    for (index, npc) in plan.npcs.iter().enumerate() {
+        let position = (0..SPAWN_ATTEMPTS).find_map(|_| {
+            let angle = rng.gen_range(0.0..std::f32::consts::TAU);
+            let distance = rng.gen_range(SPAWN_DISTANCE);
+            let point = PLAYER_SPAWN_POSITION + Vec2::from_angle(angle) * distance;
+            let ground = sample_terrain_height(point.x, point.y);
+            let dry = settings.water_level.is_none_or(|level| ground > level);
+            dry.then(|| Vec3::new(point.x, ground + NPC_FLOAT_HEIGHT, point.y))
+        });
+        let Some(position) = position else {
+            warn!(name = npc.name, "found no dry land to spawn NPC on");
+            continue;
+        };
+
+        let mut entity = commands.spawn((
+            Name::new(npc.name.clone()),
+            Npc,
+            npc.behaviour.clone(),
+            procedural_npc_dialogue(index, npc),
+            Transform::from_translation(position),
+            Visibility::default(),
+            StateScoped(Screen::ProceduralGameplay),
+        ));
+        if settings.conversational_npcs {
+            entity.insert(ConversationalNpc::new(NpcPersona {
+                name: npc.name.clone(),
+                description: format!("someone who {}", npc.behaviour.describe()),
+            }));
+        }
+    }
+}
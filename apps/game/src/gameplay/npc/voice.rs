@@ -0,0 +1,134 @@
+//! NPC voices. Lines an NPC says are synthesised with its voice and played from its position.
+
+use bevy::{
+    prelude::*,
+    tasks::{IoTaskPool, Task},
+};
+use bevy_seedling::prelude::*;
+#[cfg(feature = "hot_patch")]
+use bevy_simple_subsecond_system::hot;
+use futures_lite::future;
+use generative::SpeechVoice;
+
+use super::Npc;
+use crate::{
+    PostPhysicsAppSystems, audio::SpatialPool, generate::generate_speech::synthesize_line,
+    screens::Screen,
+};
+
+pub(super) fn plugin(app: &mut App) {
+    app.add_observer(assign_voice);
+    app.add_observer(speak_line);
+    app.add_systems(
+        Update,
+        play_synthesised_lines
+            .run_if(in_state(Screen::Gameplay).or(in_state(Screen::ProceduralGameplay)))
+            .in_set(PostPhysicsAppSystems::PlaySounds),
+    );
+}
+
+/// The voice an NPC speaks with.
+#[derive(Component, Debug, Clone, Copy)]
+pub(crate) struct NpcVoice(pub(crate) SpeechVoice);
+
+/// Makes an NPC say a line out loud. Only the latest line of each NPC is played, so that
+/// skipping through dialogue doesn't make NPCs talk over themselves.
+#[derive(Event, Debug, Clone)]
+pub(crate) struct SpeakLine {
+    pub(crate) speaker: Entity,
+    pub(crate) text: String,
+}
+
+#[derive(Component)]
+struct PendingLine {
+    speaker: Entity,
+    task: Task<anyhow::Result<String>>,
+}
+
+/// A line an NPC is currently saying.
+#[derive(Component)]
+struct SpokenLine;
+
+/// Gives every NPC a voice. Named NPCs always get the same one, so they sound alike across visits.
+#[cfg_attr(feature = "hot_patch", hot)]
+fn assign_voice(trigger: Trigger<OnAdd, Npc>, mut commands: Commands, names: Query<&Name>) {
+    let seed = names
+        .get(trigger.target())
+        .map(|name| name.as_str().bytes().map(usize::from).sum::<usize>())
+        .unwrap_or_default();
+    let voice = SpeechVoice::ALL[seed % SpeechVoice::ALL.len()];
+    commands
+        .entity(trigger.target())
+        .insert_if_new(NpcVoice(voice));
+}
+
+#[cfg_attr(feature = "hot_patch", hot)]
+fn speak_line(
+    trigger: Trigger<SpeakLine>,
+    mut commands: Commands,
+    voices: Query<&NpcVoice>,
+    pending: Query<(Entity, &PendingLine)>,
+    screen: Res<State<Screen>>,
+) {
+    let SpeakLine { speaker, text } = trigger.event().clone();
+    let Ok(voice) = voices.get(speaker) else {
+        return;
+    };
+    let text = text.trim().to_string();
+    if text.is_empty() {
+        return;
+    }
+
+    // Dropping the task of an older line cancels it.
+    for (entity, line) in &pending {
+        if line.speaker == speaker {
+            commands.entity(entity).despawn();
+        }
+    }
+    let voice = voice.0;
+    let task = IoTaskPool::get().spawn(async move { synthesize_line(&text, voice) });
+    commands.spawn((
+        Name::new("Pending Line"),
+        PendingLine { speaker, task },
+        StateScoped(*screen.get()),
+    ));
+}
+
+#[cfg_attr(feature = "hot_patch", hot)]
+fn play_synthesised_lines(
+    mut commands: Commands,
+    mut pending: Query<(Entity, &mut PendingLine)>,
+    spoken_lines: Query<(Entity, &ChildOf), With<SpokenLine>>,
+    speakers: Query<(), With<NpcVoice>>,
+    asset_server: Res<AssetServer>,
+) {
+    for (entity, mut line) in &mut pending {
+        let Some(result) = future::block_on(future::poll_once(&mut line.task)) else {
+            continue;
+        };
+        commands.entity(entity).despawn();
+
+        let path = match result {
+            Ok(path) => path,
+            Err(err) => {
+                error!(?err, "failed to synthesise dialogue line");
+                continue;
+            }
+        };
+        if !speakers.contains(line.speaker) {
+            continue;
+        }
+        for (spoken_line, child_of) in &spoken_lines {
+            if child_of.parent() == line.speaker {
+                commands.entity(spoken_line).despawn();
+            }
+        }
+        commands.entity(line.speaker).with_child((
+            Name::new("Spoken Line"),
+            SpokenLine,
+            Transform::default(),
+            SamplePlayer::new(asset_server.load(path)),
+            SpatialPool,
+        ));
+    }
+}
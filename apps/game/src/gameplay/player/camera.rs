@@ -73,7 +73,14 @@ pub(crate) struct PlayerCamera;
 #[derive(Component, Debug, Reflect)]
 #[reflect(Component)]
 #[require(Transform, Visibility)]
-struct WorldModelCamera;
+pub(crate) struct WorldModelCamera;
+
+/// How bright the skybox is drawn. Skyboxes are not affected by the camera's exposure.
+pub(crate) const SKYBOX_BRIGHTNESS: f32 = 8.0;
+/// Intensity of the environment map lighting of the player's cameras.
+pub(crate) const ENVIRONMENT_MAP_INTENSITY: f32 = 300.0;
+/// Exposure of the player's cameras, optimized for a dark outdoor scene at night.
+pub(crate) const EXPOSURE_EV100: f32 = 4.5;
 
 #[cfg_attr(feature = "hot_patch", hot)]
 fn spawn_view_model(
@@ -124,12 +131,13 @@ fn spawn_view_model(
     let env_map = EnvironmentMapLight {
         diffuse_map: env_map_diffuse.clone(),
         specular_map: env_map_specular.clone(),
-        intensity: 300.0,
+        intensity: ENVIRONMENT_MAP_INTENSITY,
         ..default()
     };
 
-    // Optimized for a dark outdoor scene at night
-    let exposure = Exposure { ev100: 4.5 };
+    let exposure = Exposure {
+        ev100: EXPOSURE_EV100,
+    };
 
     // Determine which screen state to scope to
     let state_scope = match **current_screen {
@@ -188,7 +196,7 @@ fn spawn_view_model(
                 Bloom::NATURAL,
                 Skybox {
                     image: env_map_specular.clone(),
-                    brightness: 8.0,
+                    brightness: SKYBOX_BRIGHTNESS,
                     ..default()
                 },
                 env_map.clone(),
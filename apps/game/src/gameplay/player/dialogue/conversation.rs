@@ -0,0 +1,323 @@
+//! Free-form conversations with NPCs. Instead of starting a Yarn node, interacting with a
+//! [`ConversationalNpc`] opens a chat where the player types messages and the NPC replies through a
+//! chat model. The NPC keeps its persona and remembers earlier turns, even across conversations.
+
+use std::any::TypeId;
+
+use bevy::{
+    input::common_conditions::input_just_pressed,
+    prelude::*,
+    tasks::{IoTaskPool, Task},
+    ui::Val::*,
+};
+#[cfg(feature = "hot_patch")]
+use bevy_simple_subsecond_system::hot;
+use bevy_simple_text_input::{TextInput, TextInputSubmitEvent, TextInputSystem};
+use futures_lite::future;
+use generative::{ChatMessage, ChatRole};
+
+use crate::{
+    PostPhysicsAppSystems,
+    gameplay::{
+        crosshair::CrosshairState, npc::voice::SpeakLine, player::default_input::BlocksInput,
+    },
+    generate::generate_conversation::{NpcPersona, generate_reply},
+    menus::generate::GenerationPrompt,
+    screens::Screen,
+    theme::{palette::SCREEN_BACKGROUND, widget},
+};
+
+/// How many of the latest messages are shown in the conversation panel.
+const VISIBLE_MESSAGES: usize = 6;
+
+pub(super) fn plugin(app: &mut App) {
+    app.init_resource::<ActiveConversation>();
+    app.add_observer(open_conversation);
+
+    app.add_systems(
+        Update,
+        (
+            close_conversation
+                .run_if(is_conversation_open.and(input_just_pressed(KeyCode::Escape))),
+            send_message.after(TextInputSystem),
+            receive_replies,
+            update_transcript,
+            update_typing_indicator,
+        )
+            .chain()
+            .run_if(in_state(Screen::Gameplay).or(in_state(Screen::ProceduralGameplay)))
+            .in_set(PostPhysicsAppSystems::ChangeUi),
+    );
+    app.add_systems(OnExit(Screen::Gameplay), close_conversation);
+    app.add_systems(OnExit(Screen::ProceduralGameplay), close_conversation);
+}
+
+/// An NPC the player talks to freely instead of following its Yarn node.
+#[derive(Component, Debug, Clone)]
+pub(crate) struct ConversationalNpc {
+    pub(crate) persona: NpcPersona,
+    /// Everything the player and the NPC said so far, oldest first.
+    pub(crate) history: Vec<ChatMessage>,
+}
+
+impl ConversationalNpc {
+    pub(crate) fn new(persona: NpcPersona) -> Self {
+        Self {
+            persona,
+            history: Vec::new(),
+        }
+    }
+}
+
+/// Opens the conversation panel for the given NPC.
+#[derive(Event, Debug)]
+pub(super) struct StartConversation {
+    pub(super) npc: Entity,
+}
+
+/// The conversation the player is currently in, if any.
+#[derive(Resource, Default)]
+pub(crate) struct ActiveConversation {
+    npc: Option<Entity>,
+    root: Option<Entity>,
+    /// Whether the NPC failed to answer the player's last message.
+    failed: bool,
+}
+
+pub(crate) fn is_conversation_open(conversation: Res<ActiveConversation>) -> bool {
+    conversation.npc.is_some()
+}
+
+/// The reply an NPC is thinking about.
+#[derive(Component)]
+struct PendingReply(Task<anyhow::Result<String>>);
+
+#[derive(Component)]
+struct ConversationTranscript;
+
+#[derive(Component)]
+struct ConversationTypingIndicator;
+
+#[derive(Component)]
+struct ConversationInput;
+
+#[cfg_attr(feature = "hot_patch", hot)]
+fn open_conversation(
+    trigger: Trigger<StartConversation>,
+    mut commands: Commands,
+    mut conversation: ResMut<ActiveConversation>,
+    npcs: Query<&ConversationalNpc>,
+    mut crosshair: Single<&mut CrosshairState>,
+    mut blocks_input: ResMut<BlocksInput>,
+    screen: Res<State<Screen>>,
+) {
+    let npc = trigger.npc;
+    let Ok(conversational) = npcs.get(npc) else {
+        return;
+    };
+    if conversation.npc.is_some() {
+        return;
+    }
+
+    let root = commands
+        .spawn((
+            Name::new("Conversation"),
+            Node {
+                position_type: PositionType::Absolute,
+                bottom: Px(40.0),
+                left: Percent(20.0),
+                width: Percent(60.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                row_gap: Px(10.0),
+                padding: UiRect::all(Px(16.0)),
+                ..default()
+            },
+            BackgroundColor(SCREEN_BACKGROUND.with_alpha(0.9)),
+            GlobalZIndex(3),
+            StateScoped(*screen.get()),
+            children![
+                widget::label(conversational.persona.name.clone()),
+                (widget::label_small(""), ConversationTranscript),
+                (widget::label_small(""), ConversationTypingIndicator),
+                (
+                    Name::new("Conversation Input"),
+                    Node {
+                        width: Percent(100.0),
+                        ..default()
+                    },
+                    children![(TextInput, ConversationInput)],
+                ),
+                widget::label_small("Press Enter to say something. Press Esc to leave."),
+            ],
+        ))
+        .id();
+
+    *conversation = ActiveConversation {
+        npc: Some(npc),
+        root: Some(root),
+        failed: false,
+    };
+    let id = TypeId::of::<ActiveConversation>();
+    blocks_input.insert(id);
+    crosshair.wants_free_cursor.insert(id);
+}
+
+#[cfg_attr(feature = "hot_patch", hot)]
+fn close_conversation(
+    mut commands: Commands,
+    mut conversation: ResMut<ActiveConversation>,
+    crosshair: Option<Single<&mut CrosshairState>>,
+    mut blocks_input: ResMut<BlocksInput>,
+) {
+    if let Some(root) = conversation.root.take() {
+        // The panel may already be gone with the screen it was scoped to.
+        commands.entity(root).try_despawn();
+    }
+    conversation.npc = None;
+
+    let id = TypeId::of::<ActiveConversation>();
+    blocks_input.remove(&id);
+    if let Some(mut crosshair) = crosshair {
+        crosshair.wants_free_cursor.remove(&id);
+    }
+}
+
+#[cfg_attr(feature = "hot_patch", hot)]
+fn send_message(
+    mut events: EventReader<TextInputSubmitEvent>,
+    mut commands: Commands,
+    mut conversation: ResMut<ActiveConversation>,
+    inputs: Query<(), With<ConversationInput>>,
+    mut npcs: Query<(&mut ConversationalNpc, Has<PendingReply>)>,
+    world_prompt: Res<GenerationPrompt>,
+) {
+    for event in events.read() {
+        let message = event.value.trim();
+        if message.is_empty() || !inputs.contains(event.entity) {
+            continue;
+        }
+        let Some(npc) = conversation.npc else {
+            continue;
+        };
+        let Ok((mut conversational, is_replying)) = npcs.get_mut(npc) else {
+            continue;
+        };
+        // Wait for the NPC's answer before saying the next thing, so turns keep alternating.
+        if is_replying {
+            continue;
+        }
+
+        conversational.history.push(ChatMessage::user(message));
+        conversation.failed = false;
+
+        let world_prompt = world_prompt.0.clone();
+        let persona = conversational.persona.clone();
+        let history = conversational.history.clone();
+        let task = IoTaskPool::get()
+            .spawn(async move { generate_reply(&world_prompt, &persona, &history) });
+        commands.entity(npc).insert(PendingReply(task));
+    }
+}
+
+/// Adds finished replies to the NPCs' memories. Replies arriving after the player walked away
+/// are kept, so the NPC still remembers them next time.
+#[cfg_attr(feature = "hot_patch", hot)]
+fn receive_replies(
+    mut commands: Commands,
+    mut conversation: ResMut<ActiveConversation>,
+    mut npcs: Query<(Entity, &mut ConversationalNpc, &mut PendingReply)>,
+) {
+    for (npc, mut conversational, mut pending) in &mut npcs {
+        let Some(result) = future::block_on(future::poll_once(&mut pending.0)) else {
+            continue;
+        };
+        commands.entity(npc).remove::<PendingReply>();
+
+        match result {
+            Ok(reply) => {
+                commands.trigger(SpeakLine {
+                    speaker: npc,
+                    text: reply.clone(),
+                });
+                conversational.history.push(ChatMessage::assistant(reply));
+            }
+            Err(err) => {
+                error!(
+                    npc = conversational.persona.name,
+                    ?err,
+                    "NPC failed to reply"
+                );
+                // Forget the unanswered message so the player can try again.
+                if conversational
+                    .history
+                    .last()
+                    .is_some_and(|message| message.role == ChatRole::User)
+                {
+                    conversational.history.pop();
+                }
+                if conversation.npc == Some(npc) {
+                    conversation.failed = true;
+                }
+            }
+        }
+    }
+}
+
+#[cfg_attr(feature = "hot_patch", hot)]
+fn update_transcript(
+    conversation: Res<ActiveConversation>,
+    npcs: Query<Ref<ConversationalNpc>>,
+    mut transcript: Query<&mut Text, With<ConversationTranscript>>,
+) {
+    let Some(npc) = conversation.npc.and_then(|npc| npcs.get(npc).ok()) else {
+        return;
+    };
+    let Ok(mut text) = transcript.single_mut() else {
+        return;
+    };
+    if !npc.is_changed() && !text.0.is_empty() {
+        return;
+    }
+
+    let recent = &npc.history[npc.history.len().saturating_sub(VISIBLE_MESSAGES)..];
+    text.0 = if recent.is_empty() {
+        format!("{} is listening.", npc.persona.name)
+    } else {
+        recent
+            .iter()
+            .map(|message| match message.role {
+                ChatRole::User => format!("You: {}", message.content),
+                _ => format!("{}: {}", npc.persona.name, message.content),
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+}
+
+#[cfg_attr(feature = "hot_patch", hot)]
+fn update_typing_indicator(
+    conversation: Res<ActiveConversation>,
+    npcs: Query<(&ConversationalNpc, Has<PendingReply>)>,
+    mut indicator: Query<&mut Text, With<ConversationTypingIndicator>>,
+    time: Res<Time>,
+) {
+    let Some((npc, is_replying)) = conversation.npc.and_then(|npc| npcs.get(npc).ok()) else {
+        return;
+    };
+    let Ok(mut text) = indicator.single_mut() else {
+        return;
+    };
+
+    let status = if is_replying {
+        let dots = 1 + (time.elapsed_secs() * 3.0) as usize % 3;
+        format!("{} is typing{}", npc.persona.name, ".".repeat(dots))
+    } else if conversation.failed {
+        format!("{} didn't catch that. Try again.", npc.persona.name)
+    } else {
+        String::new()
+    };
+    if text.0 != status {
+        text.0 = status;
+    }
+}
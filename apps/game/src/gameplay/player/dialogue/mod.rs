@@ -7,11 +7,14 @@ use bevy::prelude::*;
 use bevy_enhanced_input::prelude::Started;
 #[cfg(feature = "hot_patch")]
 use bevy_simple_subsecond_system::hot;
-use bevy_yarnspinner::{events::DialogueCompleteEvent, prelude::*};
+use bevy_yarnspinner::{
+    events::{DialogueCompleteEvent, PresentLineEvent},
+    prelude::*,
+};
 
 use crate::{
     PostPhysicsAppSystems,
-    gameplay::crosshair::CrosshairState,
+    gameplay::{crosshair::CrosshairState, npc::voice::SpeakLine},
     screens::Screen,
     third_party::{
         avian3d::CollisionLayer,
@@ -19,8 +22,10 @@ use crate::{
     },
 };
 
+pub(crate) mod conversation;
 mod ui;
 
+use self::conversation::{ConversationalNpc, StartConversation, is_conversation_open};
 use super::{
     Player,
     camera::PlayerCamera,
@@ -30,6 +35,7 @@ use super::{
 
 pub(super) fn plugin(app: &mut App) {
     app.register_type::<InteractionPrompt>();
+    app.init_resource::<DialogueSpeaker>();
 
     app.configure_sets(
         Update,
@@ -47,20 +53,37 @@ pub(super) fn plugin(app: &mut App) {
             .in_set(DialogueSystems::UpdateOpportunity)
             .run_if(
                 in_state(Screen::Gameplay)
+                    .or(in_state(Screen::ProceduralGameplay))
                     .and(not(is_dialogue_running))
+                    .and(not(is_conversation_open))
                     .and(not(is_holding_prop)),
             ),
     );
     app.add_systems(
         Update,
         restore_input_context
-            .run_if(in_state(Screen::Gameplay).and(on_event::<DialogueCompleteEvent>))
+            .run_if(
+                in_state(Screen::Gameplay)
+                    .or(in_state(Screen::ProceduralGameplay))
+                    .and(on_event::<DialogueCompleteEvent>),
+            )
             .in_set(PostPhysicsAppSystems::Update),
     );
 
+    app.add_systems(
+        Update,
+        speak_dialogue_lines
+            .run_if(
+                in_state(Screen::Gameplay)
+                    .or(in_state(Screen::ProceduralGameplay))
+                    .and(on_event::<PresentLineEvent>),
+            )
+            .in_set(PostPhysicsAppSystems::PlaySounds),
+    );
+
     app.add_observer(interact_with_dialogue);
 
-    app.add_plugins(ui::plugin);
+    app.add_plugins((conversation::plugin, ui::plugin));
 }
 
 #[derive(Debug, SystemSet, Hash, Eq, PartialEq, Clone, Copy)]
@@ -87,9 +110,12 @@ fn check_for_dialogue_opportunity(
         &SpatialQueryFilter::from_mask(CollisionLayer::Character)
             .with_excluded_entities([*player_collider]),
     );
-    let node = hit
-        .and_then(|hit| q_yarn_node.get(hit.entity).ok())
-        .cloned();
+    let node = hit.and_then(|hit| {
+        q_yarn_node
+            .get(hit.entity)
+            .ok()
+            .map(|node| (hit.entity, node.clone()))
+    });
     if interaction_prompt.0 != node {
         interaction_prompt.0 = node;
     }
@@ -97,7 +123,11 @@ fn check_for_dialogue_opportunity(
 
 #[derive(Component, Default, Reflect)]
 #[reflect(Component, Default)]
-struct InteractionPrompt(Option<YarnNode>);
+struct InteractionPrompt(Option<(Entity, YarnNode)>);
+
+/// The NPC the running Yarn dialogue was started with.
+#[derive(Resource, Default)]
+struct DialogueSpeaker(Option<Entity>);
 
 #[cfg_attr(feature = "hot_patch", hot)]
 fn interact_with_dialogue(
@@ -106,10 +136,18 @@ fn interact_with_dialogue(
     mut dialogue_runner: Single<&mut DialogueRunner>,
     mut crosshair: Single<&mut CrosshairState>,
     mut blocks_input: ResMut<BlocksInput>,
+    conversational_npcs: Query<(), With<ConversationalNpc>>,
+    mut speaker: ResMut<DialogueSpeaker>,
+    mut commands: Commands,
 ) {
-    let Some(node) = interaction_prompt.0.take() else {
+    let Some((npc, node)) = interaction_prompt.0.take() else {
         return;
     };
+    if conversational_npcs.contains(npc) {
+        commands.trigger(StartConversation { npc });
+        return;
+    }
+    speaker.0 = Some(npc);
     dialogue_runner.start_node(&node.yarn_node);
     blocks_input.insert(interact_with_dialogue.type_id());
     crosshair
@@ -121,9 +159,29 @@ fn interact_with_dialogue(
 fn restore_input_context(
     mut crosshair: Single<&mut CrosshairState>,
     mut blocks_input: ResMut<BlocksInput>,
+    mut speaker: ResMut<DialogueSpeaker>,
 ) {
+    speaker.0 = None;
     blocks_input.remove(&interact_with_dialogue.type_id());
     crosshair
         .wants_free_cursor
         .remove(&interact_with_dialogue.type_id());
 }
+
+/// Has the NPC the player is talking to say each Yarn line out loud.
+#[cfg_attr(feature = "hot_patch", hot)]
+fn speak_dialogue_lines(
+    mut lines: EventReader<PresentLineEvent>,
+    speaker: Res<DialogueSpeaker>,
+    mut commands: Commands,
+) {
+    for event in lines.read() {
+        let Some(speaker) = speaker.0 else {
+            continue;
+        };
+        commands.trigger(SpeakLine {
+            speaker,
+            text: event.line.text_without_character_name(),
+        });
+    }
+}
@@ -12,11 +12,15 @@ use std::any::Any;
 
 pub(super) fn plugin(app: &mut App) {
     app.add_systems(OnEnter(Screen::Gameplay), setup_interaction_prompt);
+    app.add_systems(
+        OnEnter(Screen::ProceduralGameplay),
+        setup_interaction_prompt,
+    );
     app.add_systems(
         Update,
         update_interaction_prompt_ui
             .in_set(DialogueSystems::UpdateUi)
-            .run_if(in_state(Screen::Gameplay)),
+            .run_if(in_state(Screen::Gameplay).or(in_state(Screen::ProceduralGameplay))),
     );
     app.add_systems(
         Update,
@@ -24,13 +28,13 @@ pub(super) fn plugin(app: &mut App) {
             hide_crosshair_on_dialogue_start.run_if(on_event::<DialogueStartEvent>),
             show_crosshair_on_dialogue_end.run_if(on_event::<DialogueCompleteEvent>),
         )
-            .run_if(in_state(Screen::Gameplay))
+            .run_if(in_state(Screen::Gameplay).or(in_state(Screen::ProceduralGameplay)))
             .in_set(PostPhysicsAppSystems::ChangeUi),
     );
 }
 
 #[cfg_attr(feature = "hot_patch", hot)]
-pub(crate) fn setup_interaction_prompt(mut commands: Commands) {
+pub(crate) fn setup_interaction_prompt(mut commands: Commands, screen: Res<State<Screen>>) {
     commands
         .spawn((
             Name::new("Interaction Prompt"),
@@ -41,7 +45,7 @@ pub(crate) fn setup_interaction_prompt(mut commands: Commands) {
                 align_items: AlignItems::Center,
                 ..default()
             },
-            StateScoped(Screen::Gameplay),
+            StateScoped(*screen.get()),
             Pickable::IGNORE,
         ))
         .with_children(|parent| {
@@ -68,7 +72,7 @@ fn update_interaction_prompt_ui(
     }
 
     let system_id = update_interaction_prompt_ui.type_id();
-    if let Some(node) = &dialogue_prompt.0 {
+    if let Some((_, node)) = &dialogue_prompt.0 {
         text.0 = format!("E: {}", node.prompt);
         *prompt_visibility = Visibility::Inherited;
         crosshair.wants_square.insert(system_id);
@@ -6,13 +6,20 @@ use bevy_enhanced_input::prelude::*;
 use bevy_simple_subsecond_system::hot;
 use bevy_tnua::prelude::*;
 
-use crate::fixed_update_inspection::did_fixed_update_happen;
+use crate::{
+    fixed_update_inspection::did_fixed_update_happen,
+    gameplay::water::{InWater, SWIM_DEPTH, SWIM_SUBMERSION},
+};
 
 use super::default_input::{Jump, Move};
 
 use super::PLAYER_FLOAT_HEIGHT;
 use super::{Player, camera::PlayerCamera};
 
+/// Speed factor when wading through water just below swimming depth.
+const WADING_SPEED_FACTOR: f32 = 0.6;
+const SWIMMING_SPEED_FACTOR: f32 = 0.5;
+
 pub(super) fn plugin(app: &mut App) {
     app.add_systems(
         FixedUpdate,
@@ -69,11 +76,24 @@ fn clear_accumulated_input(mut accumulated_inputs: Query<&mut AccumulatedInput>)
 
 #[cfg_attr(feature = "hot_patch", hot)]
 fn apply_movement(
-    controller: Single<(&mut TnuaController, &AccumulatedInput)>,
+    controller: Single<(&mut TnuaController, &AccumulatedInput, Option<&InWater>)>,
     transform: Single<&Transform, With<PlayerCamera>>,
 ) {
-    let (mut controller, accumulated_input) = controller.into_inner();
+    let (mut controller, accumulated_input, in_water) = controller.into_inner();
     let last_move = accumulated_input.last_move.unwrap_or_default();
+    // Water slows the player down the deeper it gets. Once swimming, the player floats on the
+    // surface instead of walking on the ground below.
+    let (speed_factor, float_height) = match in_water {
+        Some(in_water) if in_water.is_swimming() => (
+            SWIMMING_SPEED_FACTOR,
+            (in_water.depth - SWIM_SUBMERSION).max(PLAYER_FLOAT_HEIGHT),
+        ),
+        Some(in_water) => (
+            1.0 - (1.0 - WADING_SPEED_FACTOR) * (in_water.depth / SWIM_DEPTH),
+            PLAYER_FLOAT_HEIGHT,
+        ),
+        None => (1.0, PLAYER_FLOAT_HEIGHT),
+    };
     // Feed the basis every frame. Even if the player doesn't move - just use `desired_velocity:
     // Vec3::ZERO`. `TnuaController` starts without a basis, which will make the character collider
     // just fall.
@@ -81,10 +101,10 @@ fn apply_movement(
     let yaw_quat = Quat::from_axis_angle(Vec3::Y, yaw);
     controller.basis(TnuaBuiltinWalk {
         // The `desired_velocity` determines how the character will move.
-        desired_velocity: yaw_quat * last_move,
+        desired_velocity: yaw_quat * last_move * speed_factor,
         // The `float_height` must be greater (even if by little) from the distance between the
         // character's center and the lowest point of its collider.
-        float_height: PLAYER_FLOAT_HEIGHT,
+        float_height,
         // Restrict the max slope so that the player cannot walk up slightly angled chairs.
         max_slope: TAU / 8.0,
         ..default()
@@ -92,9 +112,11 @@ fn apply_movement(
 }
 
 #[cfg_attr(feature = "hot_patch", hot)]
-fn apply_jump(controller: Single<(&mut TnuaController, &AccumulatedInput)>) {
-    let (mut controller, input) = controller.into_inner();
-    if input.jumped {
+fn apply_jump(controller: Single<(&mut TnuaController, &AccumulatedInput, Option<&InWater>)>) {
+    let (mut controller, input, in_water) = controller.into_inner();
+    // There is no ground to push off from while swimming.
+    let swimming = in_water.is_some_and(InWater::is_swimming);
+    if input.jumped && !swimming {
         controller.action(TnuaBuiltinJump {
             height: 1.5,
             ..default()
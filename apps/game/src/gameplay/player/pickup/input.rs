@@ -7,7 +7,10 @@ use bevy_simple_subsecond_system::hot;
 use avian_pickup::prelude::*;
 use bevy_enhanced_input::prelude::*;
 
-use crate::gameplay::player::default_input::{DropProp, PickupProp};
+use crate::{
+    gameplay::player::default_input::{DropProp, PickupProp},
+    props::generated::edit::PropEditingInput,
+};
 
 pub(super) fn plugin(app: &mut App) {
     app.add_observer(pull_prop);
@@ -20,7 +23,11 @@ fn pull_prop(
     _trigger: Trigger<Fired<PickupProp>>,
     actor: Single<Entity, With<AvianPickupActor>>,
     mut avian_pickup_input_writer: EventWriter<AvianPickupInput>,
+    editing: PropEditingInput,
 ) {
+    if editing.uses_mouse() {
+        return;
+    }
     avian_pickup_input_writer.write(AvianPickupInput {
         action: AvianPickupAction::Pull,
         actor: *actor,
@@ -32,7 +39,11 @@ fn throw_prop(
     _trigger: Trigger<Started<PickupProp>>,
     actor: Single<Entity, With<AvianPickupActor>>,
     mut avian_pickup_input_writer: EventWriter<AvianPickupInput>,
+    editing: PropEditingInput,
 ) {
+    if editing.uses_mouse() {
+        return;
+    }
     avian_pickup_input_writer.write(AvianPickupInput {
         action: AvianPickupAction::Throw,
         actor: *actor,
@@ -44,7 +55,11 @@ fn drop_prop(
     _trigger: Trigger<Started<DropProp>>,
     actor: Single<Entity, With<AvianPickupActor>>,
     mut avian_pickup_input_writer: EventWriter<AvianPickupInput>,
+    editing: PropEditingInput,
 ) {
+    if editing.uses_mouse() {
+        return;
+    }
     avian_pickup_input_writer.write(AvianPickupInput {
         action: AvianPickupAction::Drop,
         actor: *actor,
@@ -5,7 +5,7 @@ use bevy::prelude::*;
 
 mod collision;
 mod input;
-mod sound;
+pub(crate) mod sound;
 mod ui;
 
 pub(super) fn plugin(app: &mut App) {
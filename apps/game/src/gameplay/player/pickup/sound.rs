@@ -1,26 +1,55 @@
-//! Player pickup sound effects.
+//! Player pickup sound effects. Props with a [`PropMaterial`] also make sounds when picked up and
+//! when they hit something, drawn from the player's sound pools.
 
 use crate::gameplay::player::Player;
-use avian_pickup::output::PropThrown;
-use bevy::prelude::*;
+use avian_pickup::{output::PropThrown, prop::HeldProp};
+use avian3d::prelude::*;
+use bevy::{ecs::entity::EntityHashMap, prelude::*};
 use bevy_seedling::prelude::*;
 #[cfg(feature = "hot_patch")]
 use bevy_simple_subsecond_system::hot;
+use std::time::Duration;
 
 use crate::{
-    PostPhysicsAppSystems, audio::SpatialPool, gameplay::player::assets::PlayerAssets,
+    PostPhysicsAppSystems,
+    audio::SpatialPool,
+    gameplay::player::assets::PlayerAssets,
+    props::generated::material::{PropMaterial, PropSound},
     screens::Screen,
 };
 
+/// Impacts slower than this, in m/s, are too soft to hear.
+const MIN_IMPACT_SPEED: f32 = 1.5;
+/// Impacts at this speed or faster play at full volume.
+const FULL_IMPACT_SPEED: f32 = 8.0;
+/// A prop sliding or bouncing in place would otherwise play a burst of impacts.
+const IMPACT_COOLDOWN: Duration = Duration::from_millis(150);
+
 pub(super) fn plugin(app: &mut App) {
+    app.add_observer(enable_impact_events);
     app.add_systems(
         Update,
-        play_throw_sound
-            .run_if(in_state(Screen::Gameplay).and(on_event::<PropThrown>))
+        (
+            play_throw_sound.run_if(on_event::<PropThrown>),
+            play_pickup_sound,
+            play_impact_sounds.run_if(on_event::<CollisionStarted>),
+        )
+            .run_if(in_state(Screen::Gameplay).or(in_state(Screen::ProceduralGameplay)))
             .in_set(PostPhysicsAppSystems::PlaySounds),
     );
 }
 
+/// The pools of [`PlayerAssets`] that prop sounds are drawn from.
+#[derive(Reflect, Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum PropSoundPool {
+    /// Light footsteps, for small and light impacts.
+    Steps,
+    /// Heavy landings, for solid thuds.
+    Landing,
+    /// Jump starts, for scraping and rustling.
+    Scuffs,
+}
+
 #[cfg_attr(feature = "hot_patch", hot)]
 fn play_throw_sound(
     mut commands: Commands,
@@ -35,3 +64,111 @@ fn play_throw_sound(
         SpatialPool,
     ));
 }
+
+#[cfg_attr(feature = "hot_patch", hot)]
+fn play_pickup_sound(
+    mut commands: Commands,
+    mut player_assets: ResMut<PlayerAssets>,
+    props: Query<(Entity, &PropMaterial), Added<HeldProp>>,
+) {
+    for (prop, material) in &props {
+        play_prop_sound(
+            &mut commands,
+            &mut player_assets,
+            prop,
+            material.pickup_sound(),
+            1.0,
+        );
+    }
+}
+
+/// Props only report collisions if one of their colliders asks for it.
+#[cfg_attr(feature = "hot_patch", hot)]
+fn enable_impact_events(
+    trigger: Trigger<OnAdd, ColliderOf>,
+    colliders: Query<&ColliderOf>,
+    materials: Query<(), With<PropMaterial>>,
+    mut commands: Commands,
+) {
+    let Ok(collider_of) = colliders.get(trigger.target()) else {
+        return;
+    };
+    if materials.contains(collider_of.body) {
+        commands
+            .entity(trigger.target())
+            .insert(CollisionEventsEnabled);
+    }
+}
+
+#[cfg_attr(feature = "hot_patch", hot)]
+fn play_impact_sounds(
+    mut commands: Commands,
+    mut collisions: EventReader<CollisionStarted>,
+    mut player_assets: ResMut<PlayerAssets>,
+    colliders: Query<&ColliderOf>,
+    props: Query<(&PropMaterial, &LinearVelocity), Without<HeldProp>>,
+    velocities: Query<&LinearVelocity>,
+    time: Res<Time>,
+    mut last_impacts: Local<EntityHashMap<Duration>>,
+) {
+    let now = time.elapsed();
+    last_impacts.retain(|_, last_impact| now - *last_impact < IMPACT_COOLDOWN);
+
+    for CollisionStarted(first, second) in collisions.read() {
+        let bodies = [*first, *second].map(|collider| {
+            colliders
+                .get(collider)
+                .map_or(collider, |collider_of| collider_of.body)
+        });
+        for (prop, other) in [(bodies[0], bodies[1]), (bodies[1], bodies[0])] {
+            let Ok((material, velocity)) = props.get(prop) else {
+                continue;
+            };
+            let other_velocity = velocities
+                .get(other)
+                .map_or(Vec3::ZERO, |velocity| velocity.0);
+            let impact_speed = (velocity.0 - other_velocity).length();
+            if impact_speed < MIN_IMPACT_SPEED || last_impacts.contains_key(&prop) {
+                continue;
+            }
+            last_impacts.insert(prop, now);
+
+            let loudness = ((impact_speed - MIN_IMPACT_SPEED)
+                / (FULL_IMPACT_SPEED - MIN_IMPACT_SPEED))
+                .clamp(0.2, 1.0);
+            play_prop_sound(
+                &mut commands,
+                &mut player_assets,
+                prop,
+                material.impact_sound(),
+                loudness,
+            );
+        }
+    }
+}
+
+fn play_prop_sound(
+    commands: &mut Commands,
+    player_assets: &mut PlayerAssets,
+    prop: Entity,
+    sound: PropSound,
+    loudness: f32,
+) {
+    let rng = &mut rand::thread_rng();
+    let sample = match sound.pool {
+        PropSoundPool::Steps => player_assets.steps.pick(rng),
+        PropSoundPool::Landing => player_assets.land_sounds.pick(rng),
+        PropSoundPool::Scuffs => player_assets.jump_start_sounds.pick(rng),
+    }
+    .clone();
+
+    commands.entity(prop).with_child((
+        Transform::default(),
+        SamplePlayer::new(sample).with_volume(Volume::Linear(sound.volume * loudness)),
+        PlaybackSettings {
+            speed: sound.speed,
+            ..default()
+        },
+        SpatialPool,
+    ));
+}
@@ -1,6 +1,14 @@
 //! Procedural level generation.
 
-use crate::{audio::MusicPool, gameplay::npc::NPC_RADIUS, screens::Screen};
+use crate::{
+    audio::MusicPool,
+    gameplay::{
+        npc::NPC_RADIUS,
+        sky_lighting::{SkyLighting, spawn_sky_lighting},
+        terrain_material::{TerrainMaterial, terrain_material, terrain_splat_weights},
+    },
+    screens::Screen,
+};
 use avian3d::prelude::*;
 use bevy::prelude::*;
 use bevy::render::mesh::{Indices, PrimitiveTopology};
@@ -18,6 +26,8 @@ pub(super) fn plugin(app: &mut App) {
 
 pub(crate) const TERRAIN_SIZE: usize = 200;
 pub(crate) const TERRAIN_SCALE: f32 = 2.0;
+/// Where the player spawns on the XZ plane.
+pub(crate) const PLAYER_SPAWN_POSITION: Vec2 = Vec2::new(-30.0, 0.0);
 
 /// A system that spawns a procedural level.
 #[cfg_attr(feature = "hot_patch", hot)]
@@ -25,30 +35,42 @@ pub(crate) fn spawn_procedural_level(
     mut commands: Commands,
     assets: Res<ProceduralLevelAssets>,
     mut meshes: ResMut<Assets<Mesh>>,
+    sky_lighting: Option<Res<SkyLighting>>,
 ) {
     // Spawn level container
-    commands.spawn((
-        Name::new("Procedural Level"),
-        StateScoped(Screen::ProceduralGameplay),
-        ProceduralLevel,
-        children![(
-            Name::new("Level Music"),
-            SamplePlayer::new(assets.music.clone()).looping(),
-            MusicPool
-        )],
-    ));
-
-    // Set ambient light to none (like Volta level)
-    commands.insert_resource(AmbientLight::NONE);
-
-    // Create archipelago for navigation
-    let _archipelago = commands
+    let level = commands
         .spawn((
-            Name::new("Procedural Level Archipelago"),
+            Name::new("Procedural Level"),
             StateScoped(Screen::ProceduralGameplay),
-            Archipelago3d::new(ArchipelagoOptions::from_agent_radius(NPC_RADIUS)),
+            ProceduralLevel,
+            children![(
+                Name::new("Level Ambience"),
+                SamplePlayer::new(assets.ambience.clone()).looping(),
+                MusicPool
+            )],
         ))
         .id();
+    if let Some(music) = assets.music.clone() {
+        commands.entity(level).with_child((
+            Name::new("Level Music"),
+            SamplePlayer::new(music).looping(),
+            MusicPool,
+        ));
+    }
+
+    if let Some(sky_lighting) = sky_lighting {
+        spawn_sky_lighting(&mut commands, &sky_lighting);
+    } else {
+        // Without a sky analysis, rely on the environment map alone (like Volta level)
+        commands.insert_resource(AmbientLight::NONE);
+    }
+
+    // Create archipelago for navigation. Its islands are generated at runtime, see `procedural_navmesh`.
+    commands.spawn((
+        Name::new("Procedural Level Archipelago"),
+        StateScoped(Screen::ProceduralGameplay),
+        Archipelago3d::new(ArchipelagoOptions::from_agent_radius(NPC_RADIUS)),
+    ));
 
     // Generate the ground plane
     spawn_ground(&mut commands, &assets, &mut meshes);
@@ -161,6 +183,24 @@ fn create_terrain_mesh(heights: &Vec<Vec<f32>>, terrain_size: usize, terrain_sca
         }
     }
 
+    // Bake the terrain layer weights into the vertex colors so the terrain material knows
+    // where to show which layer.
+    let (min_height, max_height) = heights
+        .iter()
+        .flatten()
+        .fold((f32::MAX, f32::MIN), |(min, max), &h| {
+            (min.min(h), max.max(h))
+        });
+    let height_range = (max_height - min_height).max(f32::EPSILON);
+    let mut splat_weights = Vec::with_capacity(num_vertices);
+    for z in 0..terrain_size {
+        for x in 0..terrain_size {
+            let height_fraction = (heights[x][z] - min_height) / height_range;
+            let normal_y = normals[z * terrain_size + x][1];
+            splat_weights.push(terrain_splat_weights(height_fraction, normal_y));
+        }
+    }
+
     let mut mesh = Mesh::new(
         PrimitiveTopology::TriangleList,
         RenderAssetUsages::default(),
@@ -168,6 +208,7 @@ fn create_terrain_mesh(heights: &Vec<Vec<f32>>, terrain_size: usize, terrain_sca
     mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
     mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
     mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, splat_weights);
     mesh.insert_indices(Indices::U32(indices));
 
     mesh
@@ -191,8 +232,8 @@ pub(crate) fn sample_terrain_height(world_x: f32, world_z: f32) -> f32 {
 #[cfg_attr(feature = "hot_patch", hot)]
 fn spawn_player(commands: &mut Commands) {
     // Calculate terrain height at spawn position
-    let spawn_x = -30.0;
-    let spawn_z = 0.0;
+    let spawn_x = PLAYER_SPAWN_POSITION.x;
+    let spawn_z = PLAYER_SPAWN_POSITION.y;
     let terrain_height = sample_terrain_height(spawn_x, spawn_z);
 
     // Spawn player entity at a good spawn position
@@ -213,9 +254,13 @@ pub(crate) struct ProceduralLevel;
 #[derive(Resource, Asset, Clone, TypePath)]
 pub(crate) struct ProceduralLevelAssets {
     #[dependency]
-    pub(crate) ground_material: Handle<StandardMaterial>,
+    pub(crate) ground_material: Handle<TerrainMaterial>,
+    /// Music matched to the world prompt, if any fits.
+    #[dependency]
+    pub(crate) music: Option<Handle<Sample>>,
+    /// Looping background sound matched to the world prompt.
     #[dependency]
-    pub(crate) music: Handle<Sample>,
+    pub(crate) ambience: Handle<Sample>,
     #[dependency]
     pub(crate) env_map_specular: Handle<Image>,
     #[dependency]
@@ -226,15 +271,17 @@ impl FromWorld for ProceduralLevelAssets {
     fn from_world(world: &mut World) -> Self {
         // Get immutable reference to assets first
         let assets = world.resource::<AssetServer>();
-        let music = assets.load("audio/music/Ambiance_Rain_Calm_Loop_Stereo.ogg");
+        // Replaced by audio matched to the world prompt while the level generates.
+        let ambience = assets.load("audio/music/Ambiance_Rain_Calm_Loop_Stereo.ogg");
 
         // Create placeholder material/handles until procedural generation completes
-        let mut materials = world.resource_mut::<Assets<StandardMaterial>>();
-        let ground_material = materials.add(StandardMaterial::default());
+        let mut materials = world.resource_mut::<Assets<TerrainMaterial>>();
+        let ground_material = materials.add(terrain_material());
 
         Self {
             ground_material,
-            music,
+            music: None,
+            ambience,
             env_map_specular: Handle::default(),
             env_map_diffuse: Handle::default(),
         }
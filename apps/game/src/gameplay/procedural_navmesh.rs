@@ -0,0 +1,302 @@
+//! Runtime navmesh generation for procedural levels.
+//!
+//! The main level ships with a navmesh baked in the `bevy_rerecast` editor, but procedural levels
+//! only exist at runtime. Their navmesh is generated from the terrain heightfield and all static
+//! colliders once the level spawns. The level is split into a grid of tiles, each of which is its
+//! own landmass island, so that placing, moving or removing a prop only regenerates the tiles it touches.
+
+use std::time::Duration;
+
+use avian3d::prelude::*;
+use bevy::{
+    ecs::entity::EntityHashSet,
+    math::bounding::{Aabb3d, BoundingVolume as _},
+    prelude::*,
+};
+use bevy_landmass::prelude::*;
+use bevy_rerecast::prelude::*;
+#[cfg(feature = "hot_patch")]
+use bevy_simple_subsecond_system::hot;
+use landmass_rerecast::{Island3dBundle, NavMeshHandle3d};
+
+use crate::{
+    gameplay::{
+        npc::{NPC_HEIGHT, NPC_RADIUS},
+        procedural_level::{TERRAIN_SCALE, TERRAIN_SIZE, spawn_procedural_level},
+    },
+    screens::Screen,
+};
+
+/// Number of navmesh tiles along each side of the level.
+const TILES_PER_SIDE: usize = 4;
+/// Vertical extent of the navmesh around the origin. Generous, as props can be stacked on hills.
+const NAVMESH_HALF_HEIGHT: f32 = 64.0;
+/// How long to wait after the last change before regenerating, so that a prop tumbling into place
+/// or a burst of edits only regenerates the affected tiles once.
+const REBUILD_DELAY: Duration = Duration::from_millis(750);
+
+pub(super) fn plugin(app: &mut App) {
+    app.register_type::<NavmeshObstacle>();
+    app.register_type::<NavmeshTile>();
+
+    app.add_systems(
+        OnEnter(Screen::ProceduralGameplay),
+        build_procedural_navmesh.after(spawn_procedural_level),
+    );
+    app.add_systems(
+        OnExit(Screen::ProceduralGameplay),
+        remove_procedural_navmesh,
+    );
+    app.add_systems(
+        Update,
+        rebuild_dirty_tiles
+            .run_if(in_state(Screen::ProceduralGameplay).and(resource_exists::<ProceduralNavmesh>)),
+    );
+
+    app.add_observer(on_collider_added);
+    app.add_observer(on_collider_removed);
+    app.add_observer(on_obstacle_fell_asleep);
+    app.add_observer(on_obstacle_woke_up);
+}
+
+/// Marks a dynamic body that agents should walk around once it has come to rest,
+/// such as a generated prop.
+#[derive(Component, Reflect, Debug, Default)]
+#[reflect(Component)]
+pub(crate) struct NavmeshObstacle;
+
+/// A landmass island covering one tile of the procedural level's navmesh.
+#[derive(Component, Reflect, Debug)]
+#[reflect(Component)]
+pub(crate) struct NavmeshTile {
+    index: UVec2,
+}
+
+/// Keeps track of the navmesh tiles of the current procedural level and which of them are out of date.
+#[derive(Resource, Debug)]
+struct ProceduralNavmesh {
+    tiles: Vec<TileState>,
+    /// Colliders that were just added. Their bounds are only known after the next physics step.
+    added_colliders: EntityHashSet,
+    last_change: Option<Duration>,
+}
+
+#[derive(Debug)]
+struct TileState {
+    bounds: Aabb3d,
+    navmesh: Handle<Navmesh>,
+    dirty: bool,
+}
+
+impl ProceduralNavmesh {
+    /// Marks all tiles overlapping the given bounds as out of date.
+    fn mark_dirty(&mut self, bounds: &ColliderAabb, now: Duration) {
+        for tile in &mut self.tiles {
+            let overlaps = bounds.min.x <= tile.bounds.max.x
+                && bounds.max.x >= tile.bounds.min.x
+                && bounds.min.z <= tile.bounds.max.z
+                && bounds.max.z >= tile.bounds.min.z;
+            if overlaps {
+                tile.dirty = true;
+                self.last_change = Some(now);
+            }
+        }
+    }
+}
+
+fn tile_bounds(index: UVec2) -> Aabb3d {
+    let level_size = TERRAIN_SIZE as f32 * TERRAIN_SCALE;
+    let tile_size = level_size / TILES_PER_SIDE as f32;
+    let min = Vec2::splat(-level_size / 2.0) + index.as_vec2() * tile_size;
+    let center = min + Vec2::splat(tile_size / 2.0);
+    Aabb3d::new(
+        Vec3::new(center.x, 0.0, center.y),
+        Vec3::new(tile_size / 2.0, NAVMESH_HALF_HEIGHT, tile_size / 2.0),
+    )
+}
+
+/// The settings for generating a single tile. Only colliders that don't move are part of the navmesh,
+/// so that agents don't see the player, other agents or props being carried around as obstacles.
+fn tile_settings(bounds: Aabb3d, obstacles: &EntityHashSet) -> NavmeshSettings {
+    NavmeshSettings {
+        aabb: Some(bounds),
+        filter: Some(obstacles.clone()),
+        ..NavmeshSettings::from_agent_3d(NPC_RADIUS, NPC_HEIGHT)
+    }
+}
+
+fn navmesh_obstacles(
+    colliders: &Query<(Entity, &ColliderOf), Without<Sensor>>,
+    bodies: &Query<(&RigidBody, Has<NavmeshObstacle>, Has<Sleeping>)>,
+) -> EntityHashSet {
+    colliders
+        .iter()
+        .filter(|(_, collider_of)| {
+            bodies
+                .get(collider_of.body)
+                .is_ok_and(|(rigid_body, obstacle, sleeping)| {
+                    rigid_body.is_static() || (obstacle && sleeping)
+                })
+        })
+        .map(|(entity, _)| entity)
+        .collect()
+}
+
+#[cfg_attr(feature = "hot_patch", hot)]
+fn build_procedural_navmesh(
+    mut commands: Commands,
+    mut generator: NavmeshGenerator,
+    archipelago: Single<Entity, With<Archipelago3d>>,
+    colliders: Query<(Entity, &ColliderOf), Without<Sensor>>,
+    bodies: Query<(&RigidBody, Has<NavmeshObstacle>, Has<Sleeping>)>,
+) {
+    let obstacles = navmesh_obstacles(&colliders, &bodies);
+    let mut tiles = Vec::with_capacity(TILES_PER_SIDE * TILES_PER_SIDE);
+    for z in 0..TILES_PER_SIDE as u32 {
+        for x in 0..TILES_PER_SIDE as u32 {
+            let index = UVec2::new(x, z);
+            let bounds = tile_bounds(index);
+            let navmesh = generator.generate(tile_settings(bounds, &obstacles));
+
+            commands.spawn((
+                Name::new(format!("Procedural Level Island {x}x{z}")),
+                NavmeshTile { index },
+                StateScoped(Screen::ProceduralGameplay),
+                Island3dBundle {
+                    island: Island,
+                    archipelago_ref: ArchipelagoRef3d::new(*archipelago),
+                    nav_mesh: NavMeshHandle3d(navmesh.clone()),
+                },
+            ));
+            tiles.push(TileState {
+                bounds,
+                navmesh,
+                dirty: false,
+            });
+        }
+    }
+
+    commands.insert_resource(ProceduralNavmesh {
+        tiles,
+        added_colliders: default(),
+        last_change: None,
+    });
+}
+
+fn remove_procedural_navmesh(mut commands: Commands) {
+    commands.remove_resource::<ProceduralNavmesh>();
+}
+
+#[cfg_attr(feature = "hot_patch", hot)]
+fn rebuild_dirty_tiles(
+    mut navmesh: ResMut<ProceduralNavmesh>,
+    mut generator: NavmeshGenerator,
+    time: Res<Time>,
+    colliders: Query<(Entity, &ColliderOf), Without<Sensor>>,
+    bodies: Query<(&RigidBody, Has<NavmeshObstacle>, Has<Sleeping>)>,
+    aabbs: Query<&ColliderAabb>,
+) {
+    let now = time.elapsed();
+    let added_colliders = std::mem::take(&mut navmesh.added_colliders);
+    for collider in added_colliders {
+        let Ok((_, collider_of)) = colliders.get(collider) else {
+            continue;
+        };
+        let is_static = bodies
+            .get(collider_of.body)
+            .is_ok_and(|(rigid_body, ..)| rigid_body.is_static());
+        if !is_static {
+            continue;
+        }
+        // Until the physics step has computed the bounds, they are missing or empty, so check
+        // again next frame.
+        match aabbs.get(collider) {
+            Ok(aabb) if aabb.min.cmple(aabb.max).all() => navmesh.mark_dirty(aabb, now),
+            _ => {
+                navmesh.added_colliders.insert(collider);
+            }
+        }
+    }
+
+    let Some(last_change) = navmesh.last_change else {
+        return;
+    };
+    if now.saturating_sub(last_change) < REBUILD_DELAY {
+        return;
+    }
+    navmesh.last_change = None;
+
+    let obstacles = navmesh_obstacles(&colliders, &bodies);
+    for tile in navmesh.tiles.iter_mut().filter(|tile| tile.dirty) {
+        tile.dirty = false;
+        debug!(center = ?tile.bounds.center(), "regenerating navmesh tile");
+        generator.regenerate(&tile.navmesh, tile_settings(tile.bounds, &obstacles));
+    }
+}
+
+#[cfg_attr(feature = "hot_patch", hot)]
+fn on_collider_added(
+    trigger: Trigger<OnAdd, ColliderOf>,
+    navmesh: Option<ResMut<ProceduralNavmesh>>,
+) {
+    if let Some(mut navmesh) = navmesh {
+        navmesh.added_colliders.insert(trigger.target());
+    }
+}
+
+#[cfg_attr(feature = "hot_patch", hot)]
+fn on_collider_removed(
+    trigger: Trigger<OnRemove, ColliderOf>,
+    navmesh: Option<ResMut<ProceduralNavmesh>>,
+    time: Res<Time>,
+    colliders: Query<&ColliderAabb>,
+) {
+    let Some(mut navmesh) = navmesh else {
+        return;
+    };
+    if let Ok(aabb) = colliders.get(trigger.target()) {
+        navmesh.mark_dirty(aabb, time.elapsed());
+    }
+}
+
+/// A prop that came to rest is now part of the scenery agents have to walk around.
+#[cfg_attr(feature = "hot_patch", hot)]
+fn on_obstacle_fell_asleep(
+    trigger: Trigger<OnAdd, Sleeping>,
+    navmesh: Option<ResMut<ProceduralNavmesh>>,
+    time: Res<Time>,
+    obstacles: Query<&RigidBodyColliders, With<NavmeshObstacle>>,
+    colliders: Query<&ColliderAabb>,
+) {
+    mark_obstacle_dirty(trigger.target(), navmesh, &time, &obstacles, &colliders);
+}
+
+/// A prop that starts moving, for example because it was picked up, leaves a gap in the navmesh.
+#[cfg_attr(feature = "hot_patch", hot)]
+fn on_obstacle_woke_up(
+    trigger: Trigger<OnRemove, Sleeping>,
+    navmesh: Option<ResMut<ProceduralNavmesh>>,
+    time: Res<Time>,
+    obstacles: Query<&RigidBodyColliders, With<NavmeshObstacle>>,
+    colliders: Query<&ColliderAabb>,
+) {
+    mark_obstacle_dirty(trigger.target(), navmesh, &time, &obstacles, &colliders);
+}
+
+fn mark_obstacle_dirty(
+    body: Entity,
+    navmesh: Option<ResMut<ProceduralNavmesh>>,
+    time: &Time,
+    obstacles: &Query<&RigidBodyColliders, With<NavmeshObstacle>>,
+    colliders: &Query<&ColliderAabb>,
+) {
+    let Some(mut navmesh) = navmesh else {
+        return;
+    };
+    let Ok(body_colliders) = obstacles.get(body) else {
+        return;
+    };
+    for aabb in colliders.iter_many(body_colliders.iter()) {
+        navmesh.mark_dirty(aabb, time.elapsed());
+    }
+}
@@ -0,0 +1,155 @@
+//! Sun, ambient light and fog for procedural levels.
+//!
+//! The generated sky only lights the level through its environment map, which gives no shadows and
+//! no sense of where the sun is. The sky panorama is analysed during generation, see
+//! [`analyze_sky`](crate::generate::sky_analysis::analyze_sky), and the result is turned into a
+//! [`DirectionalLight`], an [`AmbientLight`] and a [`DistanceFog`] that match what the sky shows.
+
+use std::f32::consts::PI;
+
+use bevy::{pbr::CascadeShadowConfigBuilder, prelude::*};
+#[cfg(feature = "hot_patch")]
+use bevy_simple_subsecond_system::hot;
+
+use crate::{
+    gameplay::{
+        player::camera::{SKYBOX_BRIGHTNESS, WorldModelCamera},
+        terrain_material::smoothstep,
+    },
+    generate::sky_analysis::SkyAnalysis,
+    screens::Screen,
+};
+
+/// Illuminance of a bright sun in a clear sky. This is much lower than real sunlight
+/// because it is tuned to the player camera's exposure and environment map intensity.
+const MAX_SUN_ILLUMINANCE: f32 = 1_500.0;
+/// Brightness of the ambient light on a bright day.
+const MAX_AMBIENT_BRIGHTNESS: f32 = 20.0;
+/// The sun never sinks lower than 10 degrees, as shadows get unusably long near the horizon.
+const MIN_SUN_ELEVATION: f32 = PI / 18.0;
+
+pub(super) fn plugin(app: &mut App) {
+    app.register_type::<SkyLighting>();
+    app.register_type::<Sun>();
+    app.add_observer(add_fog_to_world_model_camera);
+}
+
+/// The lighting of a procedural level, derived from its generated sky.
+#[derive(Resource, Reflect, Debug, Clone)]
+#[reflect(Resource)]
+pub(crate) struct SkyLighting {
+    /// Direction pointing from the world towards the sun.
+    pub(crate) sun_direction: Vec3,
+    pub(crate) sun_color: Color,
+    pub(crate) sun_illuminance: f32,
+    pub(crate) ambient_color: Color,
+    pub(crate) ambient_brightness: f32,
+    pub(crate) fog_color: Color,
+    /// Distance in meters at which objects are barely visible through the fog.
+    pub(crate) fog_visibility: f32,
+}
+
+impl From<SkyAnalysis> for SkyLighting {
+    fn from(analysis: SkyAnalysis) -> Self {
+        let horizontal =
+            Vec2::new(analysis.sun_direction.x, analysis.sun_direction.z).normalize_or(Vec2::X);
+        let elevation = analysis
+            .sun_direction
+            .y
+            .clamp(-1.0, 1.0)
+            .asin()
+            .max(MIN_SUN_ELEVATION);
+        let sun_direction = Vec3::new(
+            elevation.cos() * horizontal.x,
+            elevation.sin(),
+            elevation.cos() * horizontal.y,
+        );
+
+        // A bright spot in a dark sky is the moon, not the sun.
+        let daylight = smoothstep(0.02, 0.3, analysis.sky_brightness);
+        let sun_illuminance =
+            MAX_SUN_ILLUMINANCE * (0.25 + 0.75 * analysis.sun_strength) * (0.1 + 0.9 * daylight);
+
+        // Hazy skies have a washed out horizon, clear skies have a saturated one.
+        let clarity = Hsla::from(analysis.horizon_color)
+            .saturation
+            .max(analysis.sun_strength)
+            .clamp(0.0, 1.0);
+
+        Self {
+            sun_direction,
+            sun_color: analysis.sun_color,
+            sun_illuminance,
+            ambient_color: analysis.ambient_color,
+            ambient_brightness: MAX_AMBIENT_BRIGHTNESS * (0.2 + 0.8 * daylight),
+            // The skybox ignores exposure, so the fog must be scaled the same way to blend into it.
+            fog_color: (LinearRgba::from(analysis.horizon_color) * SKYBOX_BRIGHTNESS)
+                .with_alpha(1.0)
+                .into(),
+            fog_visibility: 120.0 + 280.0 * clarity,
+        }
+    }
+}
+
+impl SkyLighting {
+    pub(crate) fn distance_fog(&self) -> DistanceFog {
+        DistanceFog {
+            color: self.fog_color,
+            directional_light_color: self.sun_color.with_alpha(0.4),
+            directional_light_exponent: 20.0,
+            falloff: FogFalloff::from_visibility_squared(self.fog_visibility),
+        }
+    }
+}
+
+/// The directional light of a procedural level.
+#[derive(Component, Debug, Reflect)]
+#[reflect(Component)]
+pub(crate) struct Sun;
+
+/// Spawns the sun and sets the ambient light of a procedural level.
+#[cfg_attr(feature = "hot_patch", hot)]
+pub(crate) fn spawn_sky_lighting(commands: &mut Commands, lighting: &SkyLighting) {
+    commands.insert_resource(AmbientLight {
+        color: lighting.ambient_color,
+        brightness: lighting.ambient_brightness,
+        ..default()
+    });
+
+    commands.spawn((
+        Name::new("Sun"),
+        Sun,
+        DirectionalLight {
+            color: lighting.sun_color,
+            illuminance: lighting.sun_illuminance,
+            shadows_enabled: true,
+            ..default()
+        },
+        Transform::default().looking_to(-lighting.sun_direction, Vec3::Y),
+        CascadeShadowConfigBuilder {
+            first_cascade_far_bound: 12.0,
+            maximum_distance: 150.0,
+            ..default()
+        }
+        .build(),
+        StateScoped(Screen::ProceduralGameplay),
+    ));
+}
+
+#[cfg_attr(feature = "hot_patch", hot)]
+fn add_fog_to_world_model_camera(
+    trigger: Trigger<OnAdd, WorldModelCamera>,
+    lighting: Option<Res<SkyLighting>>,
+    screen: Res<State<Screen>>,
+    mut commands: Commands,
+) {
+    if *screen.get() != Screen::ProceduralGameplay {
+        return;
+    }
+    let Some(lighting) = lighting else {
+        return;
+    };
+    commands
+        .entity(trigger.target())
+        .insert(lighting.distance_fog());
+}
@@ -0,0 +1,155 @@
+//! The ground material used by procedural terrain.
+//!
+//! A generated ground texture is seamless, but repeating it hundreds of times across the
+//! terrain still produces an obvious checkerboard. This material hides the repetition with
+//! stochastic hex-tiling: every hexagonal cell samples the texture with its own random rotation
+//! and offset, and neighbouring cells are blended together. On top of that, low-frequency noise
+//! modulates the brightness and distant ground is sampled at a larger scale, which breaks up the
+//! patterns that would otherwise become visible at grazing angles.
+//!
+//! The terrain blends between one texture per [`TerrainLayer`]. The blend weights are baked into
+//! the terrain mesh's vertex colors, see [`terrain_splat_weights`].
+
+use bevy::{
+    pbr::{ExtendedMaterial, MaterialExtension},
+    prelude::*,
+    render::render_resource::{AsBindGroup, ShaderRef, ShaderType},
+};
+
+use crate::generate::generate_ground::TerrainLayer;
+
+const SHADER_PATH: &str = "shaders/terrain_material.wgsl";
+
+pub(super) fn plugin(app: &mut App) {
+    app.add_plugins(MaterialPlugin::<TerrainMaterial>::default());
+    app.register_type::<TerrainMaterialExtension>();
+}
+
+/// The material used for the ground of procedural levels.
+pub(crate) type TerrainMaterial = ExtendedMaterial<StandardMaterial, TerrainMaterialExtension>;
+
+/// Builds a [`TerrainMaterial`] without any layer textures.
+/// Use [`TerrainMaterialExtension::layer_texture_mut`] to fill them in once they are generated.
+pub(crate) fn terrain_material() -> TerrainMaterial {
+    ExtendedMaterial {
+        base: StandardMaterial {
+            perceptual_roughness: 0.9,
+            metallic: 0.0,
+            ..default()
+        },
+        extension: TerrainMaterialExtension::default(),
+    }
+}
+
+#[derive(Asset, AsBindGroup, Reflect, Debug, Clone, Default)]
+pub(crate) struct TerrainMaterialExtension {
+    #[uniform(100)]
+    pub(crate) settings: TerrainMaterialSettings,
+    // All layers are sampled with the ground texture's sampler to stay well below the
+    // sampler limit of the standard material's bind group.
+    #[texture(101)]
+    #[sampler(102)]
+    pub(crate) ground_texture: Option<Handle<Image>>,
+    #[texture(103)]
+    pub(crate) lowland_texture: Option<Handle<Image>>,
+    #[texture(104)]
+    pub(crate) cliff_texture: Option<Handle<Image>>,
+    #[texture(105)]
+    pub(crate) peak_texture: Option<Handle<Image>>,
+}
+
+impl TerrainMaterialExtension {
+    pub(crate) fn layer_texture_mut(&mut self, layer: TerrainLayer) -> &mut Option<Handle<Image>> {
+        match layer {
+            TerrainLayer::Ground => &mut self.ground_texture,
+            TerrainLayer::Lowland => &mut self.lowland_texture,
+            TerrainLayer::Cliff => &mut self.cliff_texture,
+            TerrainLayer::Peak => &mut self.peak_texture,
+        }
+    }
+
+    /// Uses the ground texture for every layer that has no texture of its own,
+    /// e.g. because its generation failed.
+    pub(crate) fn fill_missing_layers(&mut self) {
+        let ground = self.ground_texture.clone();
+        for layer in TerrainLayer::ALL {
+            let texture = self.layer_texture_mut(layer);
+            if texture.is_none() {
+                *texture = ground.clone();
+            }
+        }
+    }
+}
+
+impl MaterialExtension for TerrainMaterialExtension {
+    fn fragment_shader() -> ShaderRef {
+        SHADER_PATH.into()
+    }
+
+    fn deferred_fragment_shader() -> ShaderRef {
+        SHADER_PATH.into()
+    }
+}
+
+/// Tuning values for the stochastic tiling. All distances are in meters.
+#[derive(ShaderType, Reflect, Debug, Clone, Copy)]
+pub(crate) struct TerrainMaterialSettings {
+    /// Size of one repetition of the ground texture.
+    pub(crate) tile_size: f32,
+    /// How much each hex cell rotates its sample, from 0 (never) to 1 (any angle).
+    pub(crate) rotation_strength: f32,
+    /// Sharpness of the blend between neighbouring hex cells. Higher values show less ghosting
+    /// but make cell borders more visible.
+    pub(crate) blend_contrast: f32,
+    /// Size of the noise used for macro brightness variation.
+    pub(crate) macro_scale: f32,
+    /// Strength of the macro brightness variation right in front of the camera.
+    pub(crate) macro_strength_near: f32,
+    /// Strength of the macro brightness variation at [`Self::far_distance`] and beyond.
+    pub(crate) macro_strength_far: f32,
+    /// Distance at which the texture is fully sampled at the far scale.
+    pub(crate) far_distance: f32,
+    /// Multiplier applied to [`Self::tile_size`] for far-away ground.
+    pub(crate) far_tile_multiplier: f32,
+}
+
+impl Default for TerrainMaterialSettings {
+    fn default() -> Self {
+        Self {
+            // The ground texture is generated as a 3m x 3m patch.
+            tile_size: 3.0,
+            rotation_strength: 1.0,
+            blend_contrast: 4.0,
+            macro_scale: 60.0,
+            macro_strength_near: 0.12,
+            macro_strength_far: 0.3,
+            far_distance: 80.0,
+            far_tile_multiplier: 4.0,
+        }
+    }
+}
+
+/// Computes how much each [`TerrainLayer`] covers a terrain vertex, in the order
+/// `[lowland, ground, cliff, peak]`. The weights always sum up to 1.
+///
+/// - `height_fraction` is the vertex height relative to the lowest (0) and highest (1) point of the terrain.
+/// - `normal_y` is the Y component of the vertex normal, i.e. 1 on flat ground and 0 on vertical walls.
+pub(crate) fn terrain_splat_weights(height_fraction: f32, normal_y: f32) -> [f32; 4] {
+    // Rise over run. The generated hills are gentle, so even a slope of 10% is worth showing as rock.
+    let normal_y = normal_y.clamp(1e-3, 1.0);
+    let slope = (1.0 - normal_y * normal_y).sqrt() / normal_y;
+    let cliff = smoothstep(0.09, 0.16, slope);
+    let lowland = 1.0 - smoothstep(0.08, 0.22, height_fraction);
+    let peak = smoothstep(0.72, 0.88, height_fraction);
+
+    let remaining = 1.0 - cliff;
+    let lowland = remaining * lowland;
+    let peak = (remaining - lowland) * peak;
+    let ground = remaining - lowland - peak;
+    [lowland, ground, cliff, peak]
+}
+
+pub(crate) fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
@@ -0,0 +1,193 @@
+//! The day/night cycle of procedural levels.
+//!
+//! The generated sky is a single daytime panorama, so instead of swapping skies, the cycle moves
+//! the sun along its path and dims the sky, environment map and fog at night while a faint moon
+//! takes over the directional light. The camera's exposure is raised at night so the world stays
+//! readable. The speed and start time come from [`WorldSettings`].
+
+use std::f32::consts::{FRAC_PI_4, TAU};
+
+use bevy::{core_pipeline::Skybox, prelude::*, render::camera::Exposure};
+#[cfg(feature = "hot_patch")]
+use bevy_simple_subsecond_system::hot;
+
+use crate::{
+    PausableSystems, PostPhysicsAppSystems,
+    gameplay::{
+        player::camera::{ENVIRONMENT_MAP_INTENSITY, EXPOSURE_EV100, SKYBOX_BRIGHTNESS},
+        sky_lighting::{SkyLighting, Sun},
+        terrain_material::smoothstep,
+        world_settings::{TimeOfDaySettings, WorldSettings},
+    },
+    screens::Screen,
+};
+
+/// The sun climbs at least this high at noon, even if the generated sky shows it lower.
+const MIN_NOON_ELEVATION: f32 = FRAC_PI_4;
+/// Moonlight relative to the sunlight of the generated sky.
+const MOONLIGHT_FACTOR: f32 = 0.06;
+const MOONLIGHT_COLOR: Color = Color::srgb(0.6, 0.7, 1.0);
+/// The color the sun and fog take on at dawn and dusk.
+const TWILIGHT_COLOR: Color = Color::srgb(1.0, 0.55, 0.3);
+/// How much of the sky and environment map brightness is left at night.
+const NIGHT_SKY_FACTOR: f32 = 0.05;
+/// Exposure at night. Lower values brighten the image.
+const NIGHT_EXPOSURE_EV100: f32 = 2.5;
+
+pub(super) fn plugin(app: &mut App) {
+    app.register_type::<TimeOfDay>();
+    app.add_systems(OnEnter(Screen::ProceduralGameplay), start_time_of_day);
+    app.add_systems(OnExit(Screen::ProceduralGameplay), stop_time_of_day);
+    app.add_systems(
+        Update,
+        (
+            advance_time_of_day.in_set(PausableSystems),
+            (update_sun_and_moon, update_sky_and_fog),
+        )
+            .chain()
+            .run_if(in_state(Screen::ProceduralGameplay).and(resource_exists::<TimeOfDay>))
+            .in_set(PostPhysicsAppSystems::Update),
+    );
+}
+
+/// The current time in a procedural level with a day/night cycle.
+#[derive(Resource, Reflect, Debug, Clone, Copy)]
+#[reflect(Resource)]
+pub(crate) struct TimeOfDay {
+    /// Hour of the day, from 0 to 24.
+    pub(crate) hour: f32,
+    /// In-game hours that pass per real-time second.
+    pub(crate) hours_per_second: f32,
+}
+
+impl TimeOfDay {
+    fn from_settings(settings: &TimeOfDaySettings) -> Self {
+        let day_length_minutes = settings.day_length_minutes.clamp(
+            TimeOfDaySettings::MIN_DAY_LENGTH_MINUTES,
+            TimeOfDaySettings::MAX_DAY_LENGTH_MINUTES,
+        );
+        Self {
+            hour: settings.start_hour.rem_euclid(24.0),
+            hours_per_second: 24.0 / (day_length_minutes * 60.0),
+        }
+    }
+
+    /// Direction towards the sun. At noon, the sun stands in the direction of the generated sky's sun,
+    /// at 6 and 18 o'clock it is on the horizon and at midnight it is straight below that.
+    pub(crate) fn sun_direction(&self, lighting: &SkyLighting) -> Vec3 {
+        let azimuth = Vec3::new(lighting.sun_direction.x, 0.0, lighting.sun_direction.z)
+            .normalize_or(Vec3::X);
+        let elevation = lighting
+            .sun_direction
+            .y
+            .clamp(-1.0, 1.0)
+            .asin()
+            .max(MIN_NOON_ELEVATION);
+        let noon = azimuth * elevation.cos() + Vec3::Y * elevation.sin();
+
+        // The sun rises and sets perpendicular to its noon direction.
+        let sunset = Vec3::Y.cross(azimuth);
+        let axis = noon.cross(sunset).normalize_or(Vec3::X);
+        let angle = (self.hour - 12.0) / 24.0 * TAU;
+        Quat::from_axis_angle(axis, angle) * noon
+    }
+}
+
+#[cfg_attr(feature = "hot_patch", hot)]
+fn start_time_of_day(
+    mut commands: Commands,
+    settings: Res<WorldSettings>,
+    lighting: Option<Res<SkyLighting>>,
+) {
+    // Without a sun to move, there is no cycle to show.
+    if !settings.time_of_day.enabled || lighting.is_none() {
+        return;
+    }
+    commands.insert_resource(TimeOfDay::from_settings(&settings.time_of_day));
+}
+
+fn stop_time_of_day(mut commands: Commands) {
+    commands.remove_resource::<TimeOfDay>();
+}
+
+#[cfg_attr(feature = "hot_patch", hot)]
+fn advance_time_of_day(time: Res<Time>, mut time_of_day: ResMut<TimeOfDay>) {
+    time_of_day.hour =
+        (time_of_day.hour + time.delta_secs() * time_of_day.hours_per_second).rem_euclid(24.0);
+}
+
+/// How bright the day is, from 0 (night) to 1 (sun well above the horizon).
+fn daylight_factor(sun_direction: Vec3) -> f32 {
+    smoothstep(-0.1, 0.2, sun_direction.y)
+}
+
+/// How much of the dawn or dusk tint to apply, peaking when the sun touches the horizon.
+fn twilight_factor(sun_direction: Vec3) -> f32 {
+    1.0 - smoothstep(0.0, 0.35, sun_direction.y.abs())
+}
+
+#[cfg_attr(feature = "hot_patch", hot)]
+fn update_sun_and_moon(
+    time_of_day: Res<TimeOfDay>,
+    lighting: Res<SkyLighting>,
+    mut sun: Single<(&mut DirectionalLight, &mut Transform), With<Sun>>,
+    mut ambient: ResMut<AmbientLight>,
+) {
+    let (light, transform) = &mut *sun;
+    let sun_direction = time_of_day.sun_direction(&lighting);
+    let daylight = daylight_factor(sun_direction);
+    let moonlight = daylight_factor(-sun_direction);
+
+    // A single directional light is either the sun or the moon, whichever is higher.
+    let light_direction = if sun_direction.y >= 0.0 {
+        light.illuminance = lighting.sun_illuminance * daylight;
+        light.color = lighting
+            .sun_color
+            .mix(&TWILIGHT_COLOR, twilight_factor(sun_direction));
+        sun_direction
+    } else {
+        light.illuminance = lighting.sun_illuminance * MOONLIGHT_FACTOR * moonlight;
+        light.color = MOONLIGHT_COLOR;
+        -sun_direction
+    };
+    transform.look_to(-light_direction, Vec3::Y);
+
+    ambient.brightness = lighting.ambient_brightness * (0.15 + 0.85 * daylight);
+}
+
+#[cfg_attr(feature = "hot_patch", hot)]
+pub(crate) fn update_sky_and_fog(
+    time_of_day: Res<TimeOfDay>,
+    lighting: Res<SkyLighting>,
+    mut cameras: Query<(
+        &mut Exposure,
+        &mut EnvironmentMapLight,
+        Option<&mut Skybox>,
+        Option<&mut DistanceFog>,
+    )>,
+) {
+    let sun_direction = time_of_day.sun_direction(&lighting);
+    let daylight = daylight_factor(sun_direction);
+    // The fog color is brighter than 1 to match the skybox, so it is tinted by multiplying.
+    let fog_tint =
+        LinearRgba::from(Color::WHITE.mix(&TWILIGHT_COLOR, 0.6 * twilight_factor(sun_direction)));
+    let sky_factor = NIGHT_SKY_FACTOR + (1.0 - NIGHT_SKY_FACTOR) * daylight;
+
+    for (mut exposure, mut environment_map, skybox, fog) in &mut cameras {
+        exposure.ev100 = NIGHT_EXPOSURE_EV100 + (EXPOSURE_EV100 - NIGHT_EXPOSURE_EV100) * daylight;
+        environment_map.intensity = ENVIRONMENT_MAP_INTENSITY * sky_factor;
+        if let Some(mut skybox) = skybox {
+            skybox.brightness = SKYBOX_BRIGHTNESS * sky_factor;
+        }
+        if let Some(mut fog) = fog {
+            let base = LinearRgba::from(lighting.fog_color) * sky_factor;
+            fog.color = LinearRgba::rgb(
+                base.red * fog_tint.red,
+                base.green * fog_tint.green,
+                base.blue * fog_tint.blue,
+            )
+            .into();
+            fog.directional_light_color = lighting.sun_color.with_alpha(0.4 * daylight);
+        }
+    }
+}
@@ -0,0 +1,608 @@
+//! Grass and foliage for procedural levels.
+//!
+//! The terrain is covered by a grid of square grass patches. All patches of a level of detail share
+//! the same few meshes and one material, so Bevy draws them as instanced batches. The meshes are
+//! modelled on flat ground; the vertex shader moves every blade onto the terrain by sampling a
+//! heightmap built from [`sample_terrain_height`] and sways it in the wind. Patches far from the
+//! camera fade out with a [`VisibilityRange`].
+
+use bevy::{
+    color::palettes::tailwind,
+    pbr::{ExtendedMaterial, MaterialExtension, OpaqueRendererMethod},
+    prelude::*,
+    render::{
+        mesh::{Indices, PrimitiveTopology},
+        primitives::Aabb,
+        render_asset::RenderAssetUsages,
+        render_resource::{
+            AsBindGroup, Extent3d, ShaderRef, ShaderType, TextureDimension, TextureFormat,
+        },
+        view::VisibilityRange,
+    },
+};
+#[cfg(feature = "hot_patch")]
+use bevy_simple_subsecond_system::hot;
+use rand::{Rng, SeedableRng, rngs::StdRng};
+
+use crate::{
+    gameplay::{
+        procedural_level::{TERRAIN_SCALE, TERRAIN_SIZE, sample_terrain_height},
+        terrain_material::{smoothstep, terrain_splat_weights},
+    },
+    screens::Screen,
+};
+
+const SHADER_PATH: &str = "shaders/grass.wgsl";
+
+/// Side length of one grass patch in meters.
+const PATCH_SIZE: f32 = 8.0;
+/// Number of different meshes per level of detail, so neighbouring patches don't look the same.
+const PATCH_VARIANTS: u64 = 3;
+/// Blades per square meter for a prompt and ground that are perfect for grass.
+const MAX_GRASS_DENSITY: f32 = 10.0;
+/// Below this density, a world is considered to have no grass at all.
+const MIN_GRASS_DENSITY: f32 = 0.5;
+
+pub(super) fn plugin(app: &mut App) {
+    // The blades are moved around in the vertex shader, so the default prepass and shadow
+    // shaders would place them wrongly. Grass is rendered in the forward pass only.
+    app.add_plugins(MaterialPlugin::<GrassMaterial> {
+        prepass_enabled: false,
+        shadows_enabled: false,
+        ..default()
+    });
+    app.register_type::<VegetationSettings>();
+    app.register_type::<GrassMaterialExtension>();
+    app.add_systems(OnEnter(Screen::ProceduralGameplay), spawn_vegetation);
+}
+
+/// How the vegetation of a procedural level looks.
+/// Derived from the world prompt and the generated ground texture by [`Self::from_prompt`].
+#[derive(Resource, Reflect, Debug, Clone)]
+#[reflect(Resource)]
+pub(crate) struct VegetationSettings {
+    /// Grass blades per square meter close to the camera. Zero disables grass.
+    pub(crate) density: f32,
+    /// Multiplier for the height of the blades, which are about 0.4m tall by default.
+    pub(crate) blade_height: f32,
+    /// Color at the root of the blades.
+    pub(crate) base_color: Color,
+    /// Color at the tip of the blades.
+    pub(crate) tip_color: Color,
+    /// How far the blade tips bend in the wind, in meters.
+    pub(crate) wind_strength: f32,
+}
+
+impl VegetationSettings {
+    pub(crate) fn from_prompt(prompt: &str, ground_color: Color) -> Self {
+        let prompt = prompt.to_lowercase();
+        let prompt_words = prompt
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .collect::<Vec<_>>();
+
+        // The ground texture is the best hint for whether grass fits the world at all:
+        // grass on sand or snow looks wrong, grass on a green meadow texture looks right.
+        let ground = Hsla::from(ground_color);
+        let greenness = if (50.0..=170.0).contains(&ground.hue) {
+            smoothstep(0.08, 0.3, ground.saturation)
+        } else {
+            0.0
+        };
+
+        let mut density = MAX_GRASS_DENSITY * (0.3 + 0.7 * greenness);
+        let mut blade_height = 1.0;
+        let mut wind_strength = 0.12;
+        let mut tint = None;
+        for keyword in VEGETATION_KEYWORDS
+            .iter()
+            .filter(|keyword| keyword.matches(&prompt_words))
+        {
+            density *= keyword.density;
+            blade_height *= keyword.height;
+            wind_strength *= keyword.wind;
+            tint = tint.or(keyword.tint);
+        }
+        let density = density.min(MAX_GRASS_DENSITY);
+        let density = if density < MIN_GRASS_DENSITY {
+            0.0
+        } else {
+            density
+        };
+
+        // Grass that is a darker and a lighter shade of the ground blends in with it.
+        let mut base_color = Hsla {
+            lightness: ground.lightness * 0.55,
+            ..ground
+        };
+        let mut tip_color = Hsla {
+            saturation: (ground.saturation * 1.15).min(1.0),
+            lightness: (ground.lightness * 1.3 + 0.05).min(0.9),
+            ..ground
+        };
+        if let Some(tint) = tint {
+            base_color = base_color.mix(&Hsla::from(tint), 0.25);
+            tip_color = tip_color.mix(&Hsla::from(tint), 0.5);
+        }
+
+        Self {
+            density,
+            blade_height,
+            base_color: base_color.into(),
+            tip_color: tip_color.into(),
+            wind_strength,
+        }
+    }
+}
+
+/// How a word in the world prompt changes the vegetation.
+struct VegetationKeyword {
+    words: &'static [&'static str],
+    density: f32,
+    height: f32,
+    wind: f32,
+    tint: Option<Srgba>,
+}
+
+impl VegetationKeyword {
+    /// Keywords match whole prompt words with one of the [`KEYWORD_SUFFIXES`], so "dunes" and
+    /// "snowy" match but "marsh" isn't on Mars.
+    fn matches(&self, prompt_words: &[&str]) -> bool {
+        self.words.iter().any(|keyword| {
+            prompt_words.iter().any(|prompt_word| {
+                prompt_word
+                    .strip_prefix(keyword)
+                    .is_some_and(|suffix| KEYWORD_SUFFIXES.contains(&suffix))
+            })
+        })
+    }
+}
+
+/// Endings a prompt word may have and still match a [`VegetationKeyword`].
+const KEYWORD_SUFFIXES: &[&str] = &["", "s", "es", "y"];
+
+const VEGETATION_KEYWORDS: &[VegetationKeyword] = &[
+    VegetationKeyword {
+        words: &["lush", "jungle", "rainforest", "meadow", "overgrown"],
+        density: 1.6,
+        height: 1.2,
+        wind: 1.0,
+        tint: None,
+    },
+    VegetationKeyword {
+        words: &["prairie", "savanna", "steppe", "wheat", "field"],
+        density: 1.3,
+        height: 1.8,
+        wind: 1.2,
+        tint: Some(tailwind::AMBER_300),
+    },
+    VegetationKeyword {
+        words: &["autumn", "fall"],
+        density: 1.0,
+        height: 1.0,
+        wind: 1.0,
+        tint: Some(tailwind::ORANGE_500),
+    },
+    VegetationKeyword {
+        words: &["dry", "arid", "drought"],
+        density: 0.5,
+        height: 0.8,
+        wind: 1.0,
+        tint: Some(tailwind::YELLOW_600),
+    },
+    VegetationKeyword {
+        words: &["desert", "dune", "beach", "sand"],
+        density: 0.1,
+        height: 0.7,
+        wind: 1.0,
+        tint: Some(tailwind::YELLOW_700),
+    },
+    VegetationKeyword {
+        words: &["snow", "ice", "arctic", "tundra", "glacier", "frozen"],
+        density: 0.1,
+        height: 0.6,
+        wind: 1.0,
+        tint: None,
+    },
+    VegetationKeyword {
+        words: &[
+            "lava", "volcano", "volcanic", "moon", "mars", "asteroid", "city", "urban", "street",
+        ],
+        density: 0.0,
+        height: 1.0,
+        wind: 1.0,
+        tint: None,
+    },
+    VegetationKeyword {
+        words: &["storm", "windy", "gale", "blizzard"],
+        density: 1.0,
+        height: 1.0,
+        wind: 2.5,
+        tint: None,
+    },
+    VegetationKeyword {
+        words: &["calm", "still", "serene", "peaceful"],
+        density: 1.0,
+        height: 1.0,
+        wind: 0.4,
+        tint: None,
+    },
+];
+
+/// The material used for grass blades.
+pub(crate) type GrassMaterial = ExtendedMaterial<StandardMaterial, GrassMaterialExtension>;
+
+#[derive(Asset, AsBindGroup, Reflect, Debug, Clone)]
+pub(crate) struct GrassMaterialExtension {
+    #[uniform(100)]
+    pub(crate) settings: GrassMaterialSettings,
+    /// Terrain height in the red channel and how much grass grows there in the green channel.
+    /// See [`terrain_heightmap`].
+    #[texture(101, sample_type = "float", filterable = false)]
+    pub(crate) heightmap: Handle<Image>,
+}
+
+impl MaterialExtension for GrassMaterialExtension {
+    fn vertex_shader() -> ShaderRef {
+        SHADER_PATH.into()
+    }
+}
+
+#[derive(ShaderType, Reflect, Debug, Clone, Copy)]
+pub(crate) struct GrassMaterialSettings {
+    /// Linear color at the root of the blades.
+    pub(crate) base_color: Vec4,
+    /// Linear color at the tip of the blades.
+    pub(crate) tip_color: Vec4,
+    /// Normalized direction the wind blows in on the XZ plane.
+    pub(crate) wind_direction: Vec2,
+    /// How far the blade tips bend in the wind, in meters.
+    pub(crate) wind_strength: f32,
+    /// How fast gusts travel over the grass.
+    pub(crate) wind_speed: f32,
+    /// Side length of the terrain covered by the heightmap, in meters.
+    pub(crate) terrain_size: f32,
+}
+
+fn grass_material(settings: &VegetationSettings, heightmap: Handle<Image>) -> GrassMaterial {
+    ExtendedMaterial {
+        base: StandardMaterial {
+            perceptual_roughness: 0.8,
+            reflectance: 0.3,
+            // Blades are single quads, so both sides need to be visible. Their normals point
+            // mostly upwards, which would be flipped on the back faces of double sided materials.
+            cull_mode: None,
+            double_sided: false,
+            opaque_render_method: OpaqueRendererMethod::Forward,
+            ..default()
+        },
+        extension: GrassMaterialExtension {
+            settings: GrassMaterialSettings {
+                base_color: LinearRgba::from(settings.base_color).to_vec4(),
+                tip_color: LinearRgba::from(settings.tip_color).to_vec4(),
+                wind_direction: Vec2::new(0.8, 0.6),
+                wind_strength: settings.wind_strength,
+                wind_speed: 1.5,
+                terrain_size: TERRAIN_SIZE as f32 * TERRAIN_SCALE,
+            },
+            heightmap,
+        },
+    }
+}
+
+/// The levels of detail of the grass patches.
+#[derive(Debug, Clone, Copy)]
+enum GrassLod {
+    Near,
+    Far,
+}
+
+impl GrassLod {
+    const ALL: [GrassLod; 2] = [GrassLod::Near, GrassLod::Far];
+
+    /// Fraction of the blades that are kept at this level of detail.
+    fn density_factor(self) -> f32 {
+        match self {
+            GrassLod::Near => 1.0,
+            GrassLod::Far => 0.25,
+        }
+    }
+
+    /// Far blades are wider so that the thinned out grass still covers the ground.
+    fn width_factor(self) -> f32 {
+        match self {
+            GrassLod::Near => 1.0,
+            GrassLod::Far => 2.2,
+        }
+    }
+
+    fn visibility_range(self) -> VisibilityRange {
+        match self {
+            GrassLod::Near => VisibilityRange {
+                start_margin: 0.0..0.0,
+                end_margin: 30.0..40.0,
+                use_aabb: false,
+            },
+            GrassLod::Far => VisibilityRange {
+                start_margin: 30.0..40.0,
+                end_margin: 70.0..85.0,
+                use_aabb: false,
+            },
+        }
+    }
+}
+
+#[cfg_attr(feature = "hot_patch", hot)]
+fn spawn_vegetation(
+    mut commands: Commands,
+    settings: Option<Res<VegetationSettings>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<GrassMaterial>>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    let Some(settings) = settings else {
+        return;
+    };
+    if settings.density <= 0.0 {
+        info!("world has no grass");
+        return;
+    }
+
+    let heightmap = images.add(terrain_heightmap());
+    let material = materials.add(grass_material(&settings, heightmap));
+
+    let lods = GrassLod::ALL.map(|lod| {
+        let variants = (0..PATCH_VARIANTS)
+            .map(|seed| meshes.add(grass_patch_mesh(&settings, lod, seed)))
+            .collect::<Vec<_>>();
+        (lod, variants)
+    });
+
+    let terrain_size = TERRAIN_SIZE as f32 * TERRAIN_SCALE;
+    let patches_per_side = (terrain_size / PATCH_SIZE) as usize;
+    let max_blade_height = 0.8 * settings.blade_height;
+    // The shader moves blades up or down to the terrain, so leave some room for the slope.
+    let aabb = Aabb::from_min_max(
+        Vec3::new(-PATCH_SIZE / 2.0, -2.0, -PATCH_SIZE / 2.0),
+        Vec3::new(PATCH_SIZE / 2.0, max_blade_height + 2.0, PATCH_SIZE / 2.0),
+    );
+    let rng = &mut StdRng::seed_from_u64(0);
+
+    commands
+        .spawn((
+            Name::new("Vegetation"),
+            Transform::default(),
+            Visibility::default(),
+            StateScoped(Screen::ProceduralGameplay),
+        ))
+        .with_children(|parent| {
+            for patch_x in 0..patches_per_side {
+                for patch_z in 0..patches_per_side {
+                    let x = (patch_x as f32 + 0.5) * PATCH_SIZE - terrain_size / 2.0;
+                    let z = (patch_z as f32 + 0.5) * PATCH_SIZE - terrain_size / 2.0;
+                    let transform = Transform::from_xyz(x, sample_terrain_height(x, z), z)
+                        .with_rotation(Quat::from_rotation_y(
+                            rng.gen_range(0..4) as f32 * std::f32::consts::FRAC_PI_2,
+                        ));
+
+                    for (lod, variants) in &lods {
+                        let mesh = variants[rng.gen_range(0..variants.len())].clone();
+                        parent.spawn((
+                            Name::new("Grass Patch"),
+                            Mesh3d(mesh),
+                            MeshMaterial3d(material.clone()),
+                            transform,
+                            aabb,
+                            lod.visibility_range(),
+                        ));
+                    }
+                }
+            }
+        });
+}
+
+/// Bakes the terrain into a texture that the grass shader can sample.
+/// The red channel holds the height, the green channel how much grass grows there
+/// based on the same layer weights the terrain material uses.
+fn terrain_heightmap() -> Image {
+    let resolution = TERRAIN_SIZE;
+    let terrain_size = TERRAIN_SIZE as f32 * TERRAIN_SCALE;
+    let cell_size = terrain_size / (resolution - 1) as f32;
+    let world_position = |index: usize| index as f32 * cell_size - terrain_size / 2.0;
+
+    let mut heights = Vec::with_capacity(resolution * resolution);
+    for z in 0..resolution {
+        for x in 0..resolution {
+            heights.push(sample_terrain_height(world_position(x), world_position(z)));
+        }
+    }
+    let (min_height, max_height) = heights.iter().fold((f32::MAX, f32::MIN), |(min, max), &h| {
+        (min.min(h), max.max(h))
+    });
+    let height_range = (max_height - min_height).max(f32::EPSILON);
+
+    let height =
+        |x: usize, z: usize| heights[z.min(resolution - 1) * resolution + x.min(resolution - 1)];
+
+    let mut data = Vec::with_capacity(resolution * resolution * 2 * size_of::<f32>());
+    for z in 0..resolution {
+        for x in 0..resolution {
+            let h = height(x, z);
+            let normal = Vec3::new(
+                height(x.saturating_sub(1), z) - height(x + 1, z),
+                2.0 * cell_size,
+                height(x, z.saturating_sub(1)) - height(x, z + 1),
+            )
+            .normalize_or(Vec3::Y);
+            let [lowland, ground, _cliff, _peak] =
+                terrain_splat_weights((h - min_height) / height_range, normal.y);
+            let coverage = ground + 0.5 * lowland;
+
+            data.extend_from_slice(&h.to_le_bytes());
+            data.extend_from_slice(&coverage.to_le_bytes());
+        }
+    }
+
+    Image::new(
+        Extent3d {
+            width: resolution as u32,
+            height: resolution as u32,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rg32Float,
+        RenderAssetUsages::RENDER_WORLD,
+    )
+}
+
+/// Builds a square patch of grass blades standing on `y = 0`, centered on the origin.
+///
+/// - The UV's `y` coordinate goes from 0 at the root to 1 at the tip of each blade.
+/// - The vertex colors hold a per-blade brightness that the shader multiplies with the grass colors.
+fn grass_patch_mesh(settings: &VegetationSettings, lod: GrassLod, seed: u64) -> Mesh {
+    let rng = &mut StdRng::seed_from_u64(seed);
+    let blade_count =
+        (settings.density * lod.density_factor() * PATCH_SIZE * PATCH_SIZE).round() as usize;
+
+    let mut builder = BladeBuilder::default();
+    for _ in 0..blade_count {
+        let root = Vec3::new(
+            rng.gen_range(-0.5..0.5) * PATCH_SIZE,
+            0.0,
+            rng.gen_range(-0.5..0.5) * PATCH_SIZE,
+        );
+        let brightness = rng.gen_range(0.75..1.1);
+
+        // Every now and then, grow a tuft of broad leaves instead of a single blade.
+        if rng.gen_bool(0.03) {
+            let leaves = rng.gen_range(4..7);
+            for leaf in 0..leaves {
+                let angle =
+                    leaf as f32 / leaves as f32 * std::f32::consts::TAU + rng.r#gen::<f32>();
+                builder.add_blade(Blade {
+                    root,
+                    facing: angle,
+                    height: rng.gen_range(0.5..0.8) * settings.blade_height,
+                    width: 0.08 * lod.width_factor(),
+                    lean: rng.gen_range(0.25..0.45),
+                    brightness: brightness * 0.85,
+                });
+            }
+        } else {
+            builder.add_blade(Blade {
+                root,
+                facing: rng.gen_range(0.0..std::f32::consts::TAU),
+                height: rng.gen_range(0.25..0.5) * settings.blade_height,
+                width: rng.gen_range(0.03..0.05) * lod.width_factor(),
+                lean: rng.gen_range(0.0..0.2),
+                brightness,
+            });
+        }
+    }
+    builder.build()
+}
+
+struct Blade {
+    root: Vec3,
+    /// Rotation around the Y axis in radians.
+    facing: f32,
+    height: f32,
+    width: f32,
+    /// How far the tip bends away from the root, in meters.
+    lean: f32,
+    brightness: f32,
+}
+
+#[derive(Default)]
+struct BladeBuilder {
+    positions: Vec<[f32; 3]>,
+    normals: Vec<[f32; 3]>,
+    uvs: Vec<[f32; 2]>,
+    colors: Vec<[f32; 4]>,
+    indices: Vec<u32>,
+}
+
+impl BladeBuilder {
+    /// Number of segments along the height of a blade. The last one is a single triangle.
+    const SEGMENTS: u32 = 3;
+
+    fn add_blade(&mut self, blade: Blade) {
+        let rotation = Quat::from_rotation_y(blade.facing);
+        let side = rotation * Vec3::X;
+        let forward = rotation * Vec3::Z;
+        // Pointing the normals mostly upwards makes the grass shade like the ground it grows on
+        // instead of showing the flat blades.
+        let normal = forward.lerp(Vec3::Y, 0.7).normalize();
+        let color = [blade.brightness, blade.brightness, blade.brightness, 1.0];
+
+        let first = self.positions.len() as u32;
+        for segment in 0..=Self::SEGMENTS {
+            let t = segment as f32 / Self::SEGMENTS as f32;
+            let center = blade.root + Vec3::Y * blade.height * t + forward * blade.lean * t * t;
+            let half_width = blade.width * 0.5 * (1.0 - t);
+
+            if segment == Self::SEGMENTS {
+                self.positions.push(center.into());
+                self.uvs.push([0.5, t]);
+            } else {
+                self.positions.push((center - side * half_width).into());
+                self.positions.push((center + side * half_width).into());
+                self.uvs.push([0.0, t]);
+                self.uvs.push([1.0, t]);
+            }
+        }
+        let vertex_count = self.positions.len() - self.normals.len();
+        self.normals
+            .extend(std::iter::repeat_n(<[f32; 3]>::from(normal), vertex_count));
+        self.colors.extend(std::iter::repeat_n(color, vertex_count));
+
+        for segment in 0..Self::SEGMENTS - 1 {
+            let bottom_left = first + segment * 2;
+            let bottom_right = bottom_left + 1;
+            let top_left = bottom_left + 2;
+            let top_right = bottom_left + 3;
+            self.indices.extend([
+                bottom_left,
+                bottom_right,
+                top_left,
+                top_left,
+                bottom_right,
+                top_right,
+            ]);
+        }
+        let last = first + (Self::SEGMENTS - 1) * 2;
+        self.indices.extend([last, last + 1, last + 2]);
+    }
+
+    fn build(self) -> Mesh {
+        Mesh::new(
+            PrimitiveTopology::TriangleList,
+            RenderAssetUsages::RENDER_WORLD,
+        )
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, self.positions)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, self.normals)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, self.uvs)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_COLOR, self.colors)
+        .with_inserted_indices(Indices::U32(self.indices))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grass_density(prompt: &str) -> f32 {
+        VegetationSettings::from_prompt(prompt, Color::srgb(0.3, 0.6, 0.2)).density
+    }
+
+    #[test]
+    fn keywords_match_whole_words() {
+        assert_eq!(grass_density("a base on mars"), 0.0);
+        assert_eq!(grass_density("a volcanic island"), 0.0);
+        assert_eq!(grass_density("sandy dunes"), grass_density("a sand dune"));
+        // Neither "marsh" nor "cityscape" are barren like Mars or a city.
+        assert!(grass_density("a misty marsh") > 0.0);
+        assert!(grass_density("a green cityscape painting") > 0.0);
+    }
+}
@@ -0,0 +1,416 @@
+//! Water for procedural levels.
+//!
+//! A flat water surface at the world's water level fills the valleys of the terrain. The surface
+//! is a mostly smooth [`StandardMaterial`], so it reflects the generated environment map, and it is
+//! tinted by how deep the water is below it. Props lighter than water float, characters wade and
+//! swim, and the camera sees through a dense fog when it dips below the surface.
+
+use avian3d::prelude::*;
+use bevy::{
+    pbr::{ExtendedMaterial, MaterialExtension, NotShadowCaster},
+    platform::collections::HashMap,
+    prelude::*,
+    render::{
+        mesh::{Indices, PrimitiveTopology},
+        render_asset::RenderAssetUsages,
+        render_resource::{AsBindGroup, ShaderRef, ShaderType},
+    },
+};
+#[cfg(feature = "hot_patch")]
+use bevy_simple_subsecond_system::hot;
+use bevy_tnua::prelude::*;
+
+use crate::{
+    PostPhysicsAppSystems,
+    gameplay::{
+        player::camera::WorldModelCamera,
+        procedural_level::{TERRAIN_SCALE, TERRAIN_SIZE, sample_terrain_height},
+        time_of_day,
+        world_settings::WorldSettings,
+    },
+    screens::Screen,
+};
+
+const SHADER_PATH: &str = "shaders/water.wgsl";
+
+/// Number of quads along each side of the water mesh.
+const WATER_RESOLUTION: usize = 128;
+/// In kg/m³, like the densities of prop materials. Denser props sink and lighter ones float.
+const WATER_DENSITY: f32 = 1_000.0;
+/// How quickly props slow down in water.
+const WATER_LINEAR_DRAG: f32 = 1.5;
+const WATER_ANGULAR_DRAG: f32 = 2.0;
+/// Characters start swimming once the water is deeper than this.
+pub(crate) const SWIM_DEPTH: f32 = 1.2;
+/// How far below the surface a swimming character's center floats.
+pub(crate) const SWIM_SUBMERSION: f32 = 0.3;
+
+pub(super) fn plugin(app: &mut App) {
+    // Water is transparent, so it never takes part in the prepass or casts shadows.
+    app.add_plugins(MaterialPlugin::<WaterMaterial> {
+        prepass_enabled: false,
+        shadows_enabled: false,
+        ..default()
+    });
+    app.register_type::<WaterLevel>();
+    app.register_type::<InWater>();
+    app.register_type::<WaterMaterialExtension>();
+
+    app.add_systems(OnEnter(Screen::ProceduralGameplay), spawn_water);
+    app.add_systems(OnExit(Screen::ProceduralGameplay), remove_water_level);
+    app.add_systems(
+        FixedUpdate,
+        (
+            apply_buoyancy,
+            update_characters_in_water.before(TnuaUserControlsSystemSet),
+        )
+            .run_if(in_state(Screen::ProceduralGameplay).and(resource_exists::<WaterLevel>)),
+    );
+    app.add_systems(
+        Update,
+        update_underwater_fog
+            .after(time_of_day::update_sky_and_fog)
+            .run_if(in_state(Screen::ProceduralGameplay).and(resource_exists::<WaterLevel>))
+            .in_set(PostPhysicsAppSystems::Update),
+    );
+}
+
+/// The height of the water surface in the current procedural level.
+#[derive(Resource, Reflect, Debug, Clone, Copy)]
+#[reflect(Resource)]
+pub(crate) struct WaterLevel(pub(crate) f32);
+
+impl WaterLevel {
+    /// How deep the water is at the given horizontal position. Negative on dry land.
+    pub(crate) fn depth_at(&self, x: f32, z: f32) -> f32 {
+        self.0 - sample_terrain_height(x, z)
+    }
+}
+
+/// Marks a character standing in water.
+#[derive(Component, Reflect, Debug, Clone, Copy)]
+#[reflect(Component)]
+pub(crate) struct InWater {
+    /// How deep the water is where the character is.
+    pub(crate) depth: f32,
+}
+
+impl InWater {
+    pub(crate) fn is_swimming(&self) -> bool {
+        self.depth > SWIM_DEPTH
+    }
+}
+
+/// Marks a camera below the water surface. Holds the fog the camera had before diving.
+#[derive(Component, Debug, Clone)]
+struct Underwater {
+    previous_fog: Option<DistanceFog>,
+}
+
+/// The material used for the water surface.
+pub(crate) type WaterMaterial = ExtendedMaterial<StandardMaterial, WaterMaterialExtension>;
+
+#[derive(Asset, AsBindGroup, Reflect, Debug, Clone)]
+pub(crate) struct WaterMaterialExtension {
+    #[uniform(100)]
+    pub(crate) settings: WaterMaterialSettings,
+}
+
+impl MaterialExtension for WaterMaterialExtension {
+    fn fragment_shader() -> ShaderRef {
+        SHADER_PATH.into()
+    }
+}
+
+#[derive(ShaderType, Reflect, Debug, Clone, Copy)]
+pub(crate) struct WaterMaterialSettings {
+    /// Linear color of shallow water, including its opacity.
+    pub(crate) shallow_color: Vec4,
+    /// Linear color of deep water, including its opacity.
+    pub(crate) deep_color: Vec4,
+    /// Depth in meters at which the water has fully turned into [`Self::deep_color`].
+    pub(crate) deep_depth: f32,
+    /// Wavelength of the largest waves in meters.
+    pub(crate) wave_length: f32,
+    /// How much the waves tilt the surface normal.
+    pub(crate) wave_strength: f32,
+    pub(crate) wave_speed: f32,
+}
+
+impl Default for WaterMaterialSettings {
+    fn default() -> Self {
+        Self {
+            shallow_color: LinearRgba::new(0.1, 0.35, 0.35, 0.35).to_vec4(),
+            deep_color: LinearRgba::new(0.01, 0.05, 0.08, 0.92).to_vec4(),
+            deep_depth: 4.0,
+            wave_length: 6.0,
+            wave_strength: 0.08,
+            wave_speed: 1.0,
+        }
+    }
+}
+
+impl WaterMaterialSettings {
+    /// The fog color seen from below the surface.
+    fn underwater_fog_color(&self) -> Color {
+        LinearRgba::from_vec4(self.deep_color.lerp(self.shallow_color, 0.3))
+            .with_alpha(1.0)
+            .into()
+    }
+}
+
+fn water_material() -> WaterMaterial {
+    ExtendedMaterial {
+        base: StandardMaterial {
+            perceptual_roughness: 0.05,
+            // Water has a reflectance of about 2% at normal incidence.
+            reflectance: 0.35,
+            alpha_mode: AlphaMode::Blend,
+            ..default()
+        },
+        extension: WaterMaterialExtension {
+            settings: WaterMaterialSettings::default(),
+        },
+    }
+}
+
+#[cfg_attr(feature = "hot_patch", hot)]
+fn spawn_water(
+    mut commands: Commands,
+    settings: Res<WorldSettings>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<WaterMaterial>>,
+) {
+    let Some(level) = settings.water_level else {
+        return;
+    };
+    let water_level = WaterLevel(level);
+    commands.insert_resource(water_level);
+
+    let Some(mesh) = water_mesh(water_level) else {
+        info!(level, "water level is below the terrain");
+        return;
+    };
+    commands.spawn((
+        Name::new("Water"),
+        Mesh3d(meshes.add(mesh)),
+        MeshMaterial3d(materials.add(water_material())),
+        Transform::from_xyz(0.0, level, 0.0),
+        NotShadowCaster,
+        StateScoped(Screen::ProceduralGameplay),
+    ));
+}
+
+fn remove_water_level(mut commands: Commands) {
+    commands.remove_resource::<WaterLevel>();
+}
+
+/// Builds a flat grid covering the terrain, leaving out the parts that are far above the water.
+/// The second UV channel holds the water depth below each vertex.
+fn water_mesh(water_level: WaterLevel) -> Option<Mesh> {
+    let terrain_size = TERRAIN_SIZE as f32 * TERRAIN_SCALE;
+    let cell_size = terrain_size / WATER_RESOLUTION as f32;
+    let vertices_per_side = WATER_RESOLUTION + 1;
+
+    let mut positions = Vec::with_capacity(vertices_per_side * vertices_per_side);
+    let mut depths = Vec::with_capacity(vertices_per_side * vertices_per_side);
+    for z in 0..vertices_per_side {
+        for x in 0..vertices_per_side {
+            let px = x as f32 * cell_size - terrain_size / 2.0;
+            let pz = z as f32 * cell_size - terrain_size / 2.0;
+            positions.push([px, 0.0, pz]);
+            depths.push(water_level.depth_at(px, pz));
+        }
+    }
+
+    let mut indices = Vec::new();
+    for z in 0..WATER_RESOLUTION {
+        for x in 0..WATER_RESOLUTION {
+            let top_left = (z * vertices_per_side + x) as u32;
+            let top_right = top_left + 1;
+            let bottom_left = top_left + vertices_per_side as u32;
+            let bottom_right = bottom_left + 1;
+
+            let corners = [top_left, top_right, bottom_left, bottom_right];
+            if corners.iter().all(|&corner| depths[corner as usize] < 0.0) {
+                continue;
+            }
+            indices.extend([top_left, bottom_left, top_right]);
+            indices.extend([top_right, bottom_left, bottom_right]);
+        }
+    }
+    if indices.is_empty() {
+        return None;
+    }
+
+    let vertex_count = positions.len();
+    let uvs = positions
+        .iter()
+        .map(|[x, _, z]| [x / terrain_size + 0.5, z / terrain_size + 0.5])
+        .collect::<Vec<_>>();
+    let depth_uvs = depths
+        .into_iter()
+        .map(|depth| [depth, 0.0])
+        .collect::<Vec<_>>();
+
+    Some(
+        Mesh::new(
+            PrimitiveTopology::TriangleList,
+            RenderAssetUsages::RENDER_WORLD,
+        )
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, vec![[0.0, 1.0, 0.0]; vertex_count])
+        .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, uvs)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_UV_1, depth_uvs)
+        .with_inserted_indices(Indices::U32(indices)),
+    )
+}
+
+/// Pushes dynamic props up by the weight of the water they displace and slows them down while they
+/// are in water. The displaced volume is the volume of their colliders, scaled by how much of their
+/// bounding box is below the water surface. Characters are handled by
+/// [`update_characters_in_water`].
+#[cfg_attr(feature = "hot_patch", hot)]
+fn apply_buoyancy(
+    mut commands: Commands,
+    water_level: Res<WaterLevel>,
+    gravity: Res<Gravity>,
+    time: Res<Time>,
+    colliders: Query<(
+        &ColliderAabb,
+        &ColliderOf,
+        &ColliderMassProperties,
+        &ColliderDensity,
+    )>,
+    mut bodies: Query<
+        (
+            &RigidBody,
+            &mut LinearVelocity,
+            &mut AngularVelocity,
+            Option<&mut ExternalForce>,
+        ),
+        Without<TnuaController>,
+    >,
+) {
+    // Props built from several meshes have several colliders, so combine their bounds and volumes
+    // per body.
+    let mut body_bounds = HashMap::<Entity, (f32, f32, f32)>::default();
+    for (aabb, collider_of, mass_properties, density) in &colliders {
+        let bounds = body_bounds
+            .entry(collider_of.body)
+            .or_insert((f32::MAX, f32::MIN, 0.0));
+        bounds.0 = bounds.0.min(aabb.min.y);
+        bounds.1 = bounds.1.max(aabb.max.y);
+        if density.0 > 0.0 {
+            bounds.2 += mass_properties.mass / density.0;
+        }
+    }
+
+    let dt = time.delta_secs();
+    for (body, (min_y, max_y, volume)) in body_bounds {
+        let Ok((rigid_body, mut linear_velocity, mut angular_velocity, external_force)) =
+            bodies.get_mut(body)
+        else {
+            continue;
+        };
+        if !rigid_body.is_dynamic() || water_level.0 <= min_y {
+            continue;
+        }
+        let submerged = ((water_level.0 - min_y) / (max_y - min_y).max(0.01)).clamp(0.0, 1.0);
+
+        // The force is applied again every step, so it must not carry over to the next one.
+        let force = ExternalForce::new(-gravity.0 * WATER_DENSITY * volume * submerged)
+            .with_persistence(false);
+        match external_force {
+            Some(mut external_force) => *external_force = force,
+            None => {
+                commands.entity(body).insert(force);
+            }
+        }
+
+        linear_velocity.0 *= 1.0 / (1.0 + WATER_LINEAR_DRAG * submerged * dt);
+        angular_velocity.0 *= 1.0 / (1.0 + WATER_ANGULAR_DRAG * submerged * dt);
+    }
+}
+
+/// Keeps track of which characters stand in water, so their movement can switch to wading or swimming.
+#[cfg_attr(feature = "hot_patch", hot)]
+fn update_characters_in_water(
+    mut commands: Commands,
+    water_level: Res<WaterLevel>,
+    characters: Query<(Entity, &GlobalTransform, Option<&InWater>), With<TnuaController>>,
+) {
+    for (entity, transform, in_water) in &characters {
+        let position = transform.translation();
+        let depth = water_level.depth_at(position.x, position.z);
+        if depth > 0.0 {
+            commands.entity(entity).insert(InWater { depth });
+        } else if in_water.is_some() {
+            commands.entity(entity).remove::<InWater>();
+        }
+    }
+}
+
+#[cfg_attr(feature = "hot_patch", hot)]
+fn update_underwater_fog(
+    mut commands: Commands,
+    water_level: Res<WaterLevel>,
+    materials: Res<Assets<WaterMaterial>>,
+    mut cameras: Query<
+        (
+            Entity,
+            &GlobalTransform,
+            Option<&mut DistanceFog>,
+            Option<&Underwater>,
+        ),
+        With<WorldModelCamera>,
+    >,
+) {
+    let settings = materials
+        .iter()
+        .next()
+        .map(|(_, material)| material.extension.settings)
+        .unwrap_or_default();
+
+    for (entity, transform, fog, underwater) in &mut cameras {
+        let position = transform.translation();
+        let is_underwater =
+            position.y < water_level.0 && water_level.depth_at(position.x, position.z) > 0.0;
+
+        match (is_underwater, underwater) {
+            (true, underwater) => {
+                if underwater.is_none() {
+                    commands.entity(entity).insert(Underwater {
+                        previous_fog: fog.as_deref().cloned(),
+                    });
+                }
+                let underwater_fog = DistanceFog {
+                    color: settings.underwater_fog_color(),
+                    directional_light_color: Color::NONE,
+                    directional_light_exponent: 1.0,
+                    falloff: FogFalloff::Exponential { density: 0.25 },
+                };
+                match fog {
+                    // Overwrite the fog every frame, as the day/night cycle keeps updating it.
+                    Some(mut fog) => *fog = underwater_fog,
+                    None => {
+                        commands.entity(entity).insert(underwater_fog);
+                    }
+                }
+            }
+            (false, Some(underwater)) => {
+                let mut entity_commands = commands.entity(entity);
+                entity_commands.remove::<Underwater>();
+                match &underwater.previous_fog {
+                    Some(previous_fog) => {
+                        entity_commands.insert(previous_fog.clone());
+                    }
+                    None => {
+                        entity_commands.remove::<DistanceFog>();
+                    }
+                }
+            }
+            (false, None) => {}
+        }
+    }
+}
@@ -0,0 +1,182 @@
+//! What populates a generated world besides its terrain and sky.
+//!
+//! The plan is derived from the world prompt while the level generates and read when the level
+//! spawns. Right now it describes the NPCs living in the world.
+
+use bevy::prelude::*;
+
+use crate::gameplay::npc::behaviour::{NpcBehaviour, NpcRoutine};
+
+/// The most NPCs a world plan will contain, so crowded prompts stay playable.
+/// `dialogue/procedural_npc.yarn` has a placeholder node for each of them.
+pub(crate) const MAX_NPCS: usize = 6;
+
+pub(super) fn plugin(app: &mut App) {
+    app.register_type::<WorldPlan>();
+}
+
+/// The plan for a procedural level. Built by [`Self::from_prompt`].
+#[derive(Resource, Reflect, Debug, Clone, Default)]
+#[reflect(Resource)]
+pub(crate) struct WorldPlan {
+    pub(crate) npcs: Vec<NpcPlan>,
+}
+
+/// An NPC that should be spawned in a procedural level.
+#[derive(Reflect, Debug, Clone)]
+pub(crate) struct NpcPlan {
+    pub(crate) name: String,
+    /// How the NPC behaves. Positions in it are relative to where the NPC spawns.
+    pub(crate) behaviour: NpcBehaviour,
+}
+
+/// A kind of NPC that fits worlds whose prompt mentions one of the keywords.
+struct NpcArchetype {
+    keywords: &'static [&'static str],
+    name: &'static str,
+    count: usize,
+    behaviour: fn() -> NpcBehaviour,
+}
+
+impl NpcArchetype {
+    /// Whether any word of the prompt is one of the keywords, or its plural like "forests".
+    fn matches(&self, prompt_words: &[&str]) -> bool {
+        prompt_words.iter().any(|word| {
+            self.keywords.iter().any(|keyword| {
+                word.strip_prefix(keyword)
+                    .is_some_and(|suffix| ["", "s", "es"].contains(&suffix))
+            })
+        })
+    }
+}
+
+const NPC_ARCHETYPES: &[NpcArchetype] = &[
+    NpcArchetype {
+        keywords: &[
+            "village",
+            "town",
+            "city",
+            "cities",
+            "market",
+            "farm",
+            "settlement",
+            "harbor",
+        ],
+        name: "Villager",
+        count: 3,
+        behaviour: || NpcBehaviour::new(NpcRoutine::Wander { radius: 12.0 }),
+    },
+    NpcArchetype {
+        keywords: &["castle", "fort", "camp", "ruin", "wall", "outpost"],
+        name: "Guard",
+        count: 2,
+        behaviour: || {
+            NpcBehaviour::new(NpcRoutine::Patrol {
+                waypoints: vec![
+                    Vec3::new(10.0, 0.0, 0.0),
+                    Vec3::new(10.0, 0.0, 10.0),
+                    Vec3::new(0.0, 0.0, 10.0),
+                    Vec3::ZERO,
+                ],
+            })
+        },
+    },
+    NpcArchetype {
+        keywords: &[
+            "forest",
+            "wood",
+            "jungle",
+            "meadow",
+            "wild",
+            "wilderness",
+            "grove",
+            "savanna",
+            "valley",
+        ],
+        name: "Fox",
+        count: 3,
+        behaviour: || NpcBehaviour::new(NpcRoutine::Wander { radius: 20.0 }).fleeing_within(6.0),
+    },
+    NpcArchetype {
+        keywords: &["friend", "companion", "dog", "pet", "guide", "cozy"],
+        name: "Companion",
+        count: 1,
+        behaviour: || NpcBehaviour::new(NpcRoutine::Follow { distance: 3.0 }),
+    },
+    NpcArchetype {
+        keywords: &["temple", "shrine", "monument", "statue", "tower", "library"],
+        name: "Keeper",
+        count: 1,
+        behaviour: || NpcBehaviour::new(NpcRoutine::Idle),
+    },
+];
+
+impl WorldPlan {
+    pub(crate) fn from_prompt(prompt: &str) -> Self {
+        let prompt = prompt.to_lowercase();
+        let prompt_words = prompt
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .collect::<Vec<_>>();
+
+        let mut npcs = NPC_ARCHETYPES
+            .iter()
+            .filter(|archetype| archetype.matches(&prompt_words))
+            .flat_map(|archetype| {
+                (0..archetype.count).map(|i| NpcPlan {
+                    name: format!("{} {}", archetype.name, i + 1),
+                    behaviour: (archetype.behaviour)(),
+                })
+            })
+            .take(MAX_NPCS)
+            .collect::<Vec<_>>();
+
+        // Every world gets some life, even if the prompt gives no hint about its inhabitants.
+        if npcs.is_empty() {
+            npcs = vec![
+                NpcPlan {
+                    name: "Wanderer 1".into(),
+                    behaviour: NpcBehaviour::new(NpcRoutine::Wander { radius: 15.0 }),
+                },
+                NpcPlan {
+                    name: "Wanderer 2".into(),
+                    behaviour: NpcBehaviour::new(NpcRoutine::Wander { radius: 15.0 }),
+                },
+                NpcPlan {
+                    name: "Fox 1".into(),
+                    behaviour: NpcBehaviour::new(NpcRoutine::Wander { radius: 20.0 })
+                        .fleeing_within(6.0),
+                },
+            ];
+        }
+        Self { npcs }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn npc_names(prompt: &str) -> Vec<String> {
+        WorldPlan::from_prompt(prompt)
+            .npcs
+            .into_iter()
+            .map(|npc| npc.name)
+            .collect()
+    }
+
+    #[test]
+    fn archetypes_match_whole_words() {
+        assert_eq!(npc_names("an old fort"), ["Guard 1", "Guard 2"]);
+        assert_eq!(npc_names("two forts"), ["Guard 1", "Guard 2"]);
+        assert_eq!(
+            npc_names("a temple in the cities"),
+            ["Villager 1", "Villager 2", "Villager 3", "Keeper 1"]
+        );
+        // Neither a fortune teller nor petrified trees bring guards or companions.
+        assert_eq!(
+            npc_names("a fortune teller among petrified trees"),
+            ["Wanderer 1", "Wanderer 2", "Fox 1"]
+        );
+    }
+}
@@ -0,0 +1,68 @@
+//! Settings of a generated world that the player picks before generating it.
+
+use bevy::prelude::*;
+
+use crate::generate::model_options::ModelOptions;
+
+pub(super) fn plugin(app: &mut App) {
+    app.init_resource::<WorldSettings>();
+    app.register_type::<WorldSettings>();
+}
+
+/// The settings of the generated world. Edited in the generate menu.
+#[derive(Resource, Reflect, Debug, Clone)]
+#[reflect(Resource)]
+pub(crate) struct WorldSettings {
+    pub(crate) time_of_day: TimeOfDaySettings,
+    /// Height of the water surface in meters, or `None` for a world without water. Worlds have no
+    /// water unless the player adds it.
+    pub(crate) water_level: Option<f32>,
+    /// Whether the player can chat freely with NPCs instead of following their scripted dialogue.
+    pub(crate) conversational_npcs: bool,
+    /// How props generated in the world look unless the player asks otherwise, so that they fit
+    /// together.
+    #[reflect(ignore)]
+    pub(crate) prop_options: ModelOptions,
+}
+
+impl WorldSettings {
+    pub(crate) const MIN_WATER_LEVEL: f32 = -8.0;
+    pub(crate) const MAX_WATER_LEVEL: f32 = 4.0;
+}
+
+impl Default for WorldSettings {
+    fn default() -> Self {
+        Self {
+            time_of_day: default(),
+            water_level: None,
+            conversational_npcs: false,
+            prop_options: default(),
+        }
+    }
+}
+
+#[derive(Reflect, Debug, Clone)]
+pub(crate) struct TimeOfDaySettings {
+    /// Whether the day/night cycle runs at all. If not, the world is lit like its generated sky.
+    /// Off unless the player turns it on, so that worlds keep the lighting they had without it.
+    pub(crate) enabled: bool,
+    /// Hour of the day when the player enters the world, from 0 to 24.
+    pub(crate) start_hour: f32,
+    /// How many real-time minutes a full day and night take.
+    pub(crate) day_length_minutes: f32,
+}
+
+impl TimeOfDaySettings {
+    pub(crate) const MIN_DAY_LENGTH_MINUTES: f32 = 5.0;
+    pub(crate) const MAX_DAY_LENGTH_MINUTES: f32 = 120.0;
+}
+
+impl Default for TimeOfDaySettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            start_hour: 10.0,
+            day_length_minutes: 20.0,
+        }
+    }
+}
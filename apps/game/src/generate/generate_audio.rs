@@ -0,0 +1,226 @@
+use std::{fs, path::Path};
+
+use anyhow::{Context, Result};
+use generative::{AudioGenerationRequest, AudioGenerator, AudioKind, LocalAudioGenerator};
+use tokio::runtime::Builder;
+use uuid::Uuid;
+
+const GENERATED_AUDIO_DIR: &str = "audio/generated/world";
+/// Set this to the URL of a local audio model server to generate music and ambience for worlds.
+/// Without it, the best-matching bundled tracks are used.
+const AUDIO_SERVER_ENV: &str = "AUDIO_SERVER_URL";
+const MUSIC_DURATION_SECONDS: f32 = 60.0;
+const AMBIENCE_DURATION_SECONDS: f32 = 30.0;
+
+/// A track that ships with the game, described by tags to match against world prompts.
+struct BundledTrack {
+    path: &'static str,
+    kind: AudioKind,
+    tags: &'static [&'static str],
+}
+
+const BUNDLED_TRACKS: &[BundledTrack] = &[
+    BundledTrack {
+        path: "audio/music/Ambiance_Rain_Calm_Loop_Stereo.ogg",
+        kind: AudioKind::Ambience,
+        tags: &[
+            "rain", "storm", "calm", "forest", "jungle", "swamp", "wet", "cloud", "grey", "gray",
+            "gloom", "night",
+        ],
+    },
+    BundledTrack {
+        path: "audio/music/loop_flames_03.ogg",
+        kind: AudioKind::Ambience,
+        tags: &[
+            "fire", "flame", "camp", "lava", "volcan", "burn", "hell", "forge", "ember", "cabin",
+        ],
+    },
+    BundledTrack {
+        path: "audio/music/Monkeys Spinning Monkeys.ogg",
+        kind: AudioKind::Music,
+        tags: &[
+            "monkey", "cartoon", "silly", "funny", "playful", "happy", "toy", "candy", "circus",
+            "cheer", "sunny", "tropic",
+        ],
+    },
+];
+
+/// The soundtrack of a generated world as asset paths.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WorldAudio {
+    /// No music plays if this is `None`.
+    pub music: Option<String>,
+    pub ambience: String,
+}
+
+/// Generates loopable music and ambience for the world prompt. Falls back to the bundled tracks
+/// that match the prompt best if no audio server is configured or generation fails.
+pub fn generate_world_audio(prompt: String) -> WorldAudio {
+    let fallback = bundled_world_audio(&prompt);
+    let Some(server_url) = std::env::var(AUDIO_SERVER_ENV)
+        .ok()
+        .filter(|url| !url.is_empty())
+    else {
+        tracing::info!("{AUDIO_SERVER_ENV} is not set; using bundled world audio");
+        return fallback;
+    };
+
+    let result = Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .context("failed to build tokio runtime for audio generation")
+        .and_then(|runtime| {
+            let generator = LocalAudioGenerator::new(server_url);
+            let output_dir = Path::new("assets").join(GENERATED_AUDIO_DIR);
+            runtime.block_on(generate_world_audio_with(&generator, &prompt, &output_dir))
+        });
+    match result {
+        Ok(audio) => audio,
+        Err(err) => {
+            tracing::warn!(?err, "falling back to bundled world audio");
+            fallback
+        }
+    }
+}
+
+/// Generates music and ambience with the given backend and writes them to `output_dir`, which
+/// must lie inside the asset folder.
+pub async fn generate_world_audio_with(
+    generator: &dyn AudioGenerator,
+    prompt: &str,
+    output_dir: &Path,
+) -> Result<WorldAudio> {
+    let generation_id = Uuid::new_v4().to_string();
+    let dir = output_dir.join(&generation_id);
+    fs::create_dir_all(&dir).with_context(|| format!("failed to create directory {dir:?}"))?;
+
+    let mut paths = Vec::new();
+    for (kind, duration, description) in [
+        (
+            AudioKind::Music,
+            MUSIC_DURATION_SECONDS,
+            "Calm, loopable background music for exploring",
+        ),
+        (
+            AudioKind::Ambience,
+            AMBIENCE_DURATION_SECONDS,
+            "Loopable environmental ambience, no music, for",
+        ),
+    ] {
+        let request = AudioGenerationRequest::new(format!("{description} {prompt}"), kind)
+            .with_duration(duration);
+        let audio = generator
+            .generate_audio(&request)
+            .await
+            .with_context(|| format!("failed to generate {}", kind.name()))?;
+
+        let file_name = format!("{}.{}", kind.name(), audio.format.extension());
+        let path = dir.join(&file_name);
+        fs::write(&path, &audio.bytes)
+            .with_context(|| format!("failed to write {} to {path:?}", kind.name()))?;
+        paths.push(format!("{GENERATED_AUDIO_DIR}/{generation_id}/{file_name}"));
+    }
+
+    let [music, ambience] = <[String; 2]>::try_from(paths).expect("one path per audio kind");
+    tracing::info!(music, ambience, "generated world audio written to disk");
+    Ok(WorldAudio {
+        music: Some(music),
+        ambience,
+    })
+}
+
+/// Picks the bundled tracks whose tags match the prompt best. Ambience always falls back to
+/// rain, while no music is better than music that doesn't fit.
+pub fn bundled_world_audio(prompt: &str) -> WorldAudio {
+    let prompt = prompt.to_lowercase();
+    let words = prompt
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>();
+
+    let best_match = |kind: AudioKind| {
+        BUNDLED_TRACKS
+            .iter()
+            // `max_by_key` picks the last of equal scores, so this prefers the track listed first.
+            .rev()
+            .filter(|track| track.kind == kind)
+            .map(|track| {
+                let score = track
+                    .tags
+                    .iter()
+                    .filter(|tag| words.iter().any(|word| word.starts_with(*tag)))
+                    .count();
+                (score, track.path)
+            })
+            .filter(|(score, _)| *score > 0)
+            .max_by_key(|(score, _)| *score)
+            .map(|(_, path)| path)
+    };
+
+    WorldAudio {
+        music: best_match(AudioKind::Music).map(str::to_string),
+        ambience: best_match(AudioKind::Ambience)
+            .unwrap_or(BUNDLED_TRACKS[0].path)
+            .to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_lite::future;
+    use generative::MockAudioGenerator;
+
+    use super::*;
+
+    #[test]
+    fn bundled_tracks_match_prompt_tags() {
+        let audio = bundled_world_audio("A volcanic island with rivers of lava");
+        assert_eq!(audio.ambience, "audio/music/loop_flames_03.ogg");
+        assert_eq!(audio.music, None);
+
+        let audio = bundled_world_audio("A silly cartoon jungle full of monkeys");
+        assert_eq!(
+            audio.music.as_deref(),
+            Some("audio/music/Monkeys Spinning Monkeys.ogg")
+        );
+        assert_eq!(
+            audio.ambience,
+            "audio/music/Ambiance_Rain_Calm_Loop_Stereo.ogg"
+        );
+
+        let audio = bundled_world_audio("A space station orbiting Saturn");
+        assert_eq!(audio.ambience, BUNDLED_TRACKS[0].path);
+        assert_eq!(audio.music, None);
+    }
+
+    #[test]
+    fn generated_audio_is_written_to_disk() {
+        let generator = MockAudioGenerator::new();
+        let output_dir = std::env::temp_dir().join(format!("world-audio-{}", Uuid::new_v4()));
+
+        let audio = future::block_on(generate_world_audio_with(
+            &generator,
+            "a windy desert",
+            &output_dir,
+        ))
+        .unwrap();
+
+        let requests = generator.requests();
+        assert_eq!(requests.len(), 2);
+        assert!(requests.iter().all(|request| request.loopable));
+        assert!(requests[0].prompt.contains("a windy desert"));
+
+        let music = audio.music.unwrap();
+        assert!(music.starts_with(GENERATED_AUDIO_DIR) && music.ends_with("music.wav"));
+        assert!(audio.ambience.ends_with("ambience.wav"));
+        let generation_id = music.split('/').nth_back(1).unwrap();
+        assert!(
+            output_dir
+                .join(generation_id)
+                .join("ambience.wav")
+                .is_file()
+        );
+
+        fs::remove_dir_all(output_dir).unwrap();
+    }
+}
@@ -0,0 +1,336 @@
+use std::{fs, path::Path};
+
+use anyhow::{Context, Result, bail};
+use avian3d::prelude::*;
+use bevy::math::{Mat4, Quat, Vec3};
+use bincode::{Decode, Encode};
+use gltf::{Gltf, Node, buffer, mesh::Mode};
+use serde::{Deserialize, Serialize};
+
+/// Bump this when the decomposition changes, so that colliders cached by older builds are rebuilt.
+const COLLIDER_CACHE_VERSION: u32 = 1;
+
+/// How closely the collider of a generated prop follows its shape. Finer colliders cost more to
+/// compute once and to simulate every frame.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Encode, Decode, Serialize, Deserialize,
+)]
+pub enum ColliderQuality {
+    /// A single convex hull, which fills in holes and concave parts.
+    Hull,
+    Low,
+    #[default]
+    Medium,
+    High,
+}
+
+impl ColliderQuality {
+    pub const ALL: [Self; 4] = [Self::Hull, Self::Low, Self::Medium, Self::High];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Hull => "hull",
+            Self::Low => "low",
+            Self::Medium => "medium",
+            Self::High => "high",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|quality| *quality == self);
+        Self::ALL[index.map_or(0, |index| (index + 1) % Self::ALL.len())]
+    }
+
+    fn vhacd_parameters(self) -> Option<VhacdParameters> {
+        let (resolution, max_convex_hulls) = match self {
+            Self::Hull => return None,
+            Self::Low => (32, 8),
+            Self::Medium => (64, 16),
+            Self::High => (128, 32),
+        };
+        Some(VhacdParameters {
+            resolution,
+            max_convex_hulls,
+            ..Default::default()
+        })
+    }
+}
+
+/// The convex parts of a model's collider, in the model's own units.
+#[derive(Debug, Clone, PartialEq, Encode, Decode)]
+pub struct ConvexDecomposition {
+    version: u32,
+    quality: ColliderQuality,
+    parts: Vec<Vec<[f32; 3]>>,
+}
+
+impl ConvexDecomposition {
+    /// A compound collider of the parts. Returns `None` if no part has any volume.
+    pub fn to_collider(&self) -> Option<Collider> {
+        let parts = self
+            .parts
+            .iter()
+            .filter_map(|points| {
+                let points = points.iter().copied().map(Vec3::from_array).collect();
+                Collider::convex_hull(points)
+            })
+            .map(|collider| (Vec3::ZERO, Quat::IDENTITY, collider))
+            .collect::<Vec<_>>();
+        (!parts.is_empty()).then(|| Collider::compound(parts))
+    }
+}
+
+/// Loads the collider of the model at the given asset path from its cache next to the model, or
+/// decomposes the model and caches the result. Decomposing takes a while, so call this from a task.
+///
+/// The collider is built from the last scene of the model, which is its coarsest level of detail.
+pub fn load_or_decompose(
+    model_path: &str,
+    quality: ColliderQuality,
+) -> Result<ConvexDecomposition> {
+    load_or_decompose_at(&Path::new("assets").join(model_path), quality)
+}
+
+fn load_or_decompose_at(
+    model_path: &Path,
+    quality: ColliderQuality,
+) -> Result<ConvexDecomposition> {
+    let cache_path = model_path.with_extension(format!("{}.collider", quality.name()));
+    let config = bincode::config::standard();
+
+    if let Ok(bytes) = fs::read(&cache_path) {
+        match bincode::decode_from_slice::<ConvexDecomposition, _>(&bytes, config) {
+            Ok((decomposition, _))
+                if decomposition.version == COLLIDER_CACHE_VERSION
+                    && decomposition.quality == quality =>
+            {
+                return Ok(decomposition);
+            }
+            Ok(_) => tracing::info!(path = ?cache_path, "rebuilding outdated collider cache"),
+            Err(err) => {
+                tracing::warn!(?err, path = ?cache_path, "rebuilding corrupt collider cache")
+            }
+        }
+    }
+
+    let bytes =
+        fs::read(model_path).with_context(|| format!("failed to read model {model_path:?}"))?;
+    let decomposition = decompose_glb(&bytes, quality)?;
+    let encoded = bincode::encode_to_vec(&decomposition, config)
+        .context("failed to encode convex decomposition")?;
+    // Write to a temporary file first, so that a half-written cache is never read.
+    let partial_path = cache_path.with_extension("collider.partial");
+    fs::write(&partial_path, encoded)
+        .with_context(|| format!("failed to write collider to {partial_path:?}"))?;
+    fs::rename(&partial_path, &cache_path)
+        .with_context(|| format!("failed to move collider to {cache_path:?}"))?;
+    tracing::info!(
+        path = ?cache_path,
+        parts = decomposition.parts.len(),
+        "cached convex decomposition of generated model"
+    );
+    Ok(decomposition)
+}
+
+fn decompose_glb(bytes: &[u8], quality: ColliderQuality) -> Result<ConvexDecomposition> {
+    let Gltf { document, blob } = Gltf::from_slice(bytes).context("failed to parse GLB")?;
+    let buffers =
+        gltf::import_buffers(&document, None, blob).context("failed to read GLB buffers")?;
+    let scene = document.scenes().last().context("GLB contains no scene")?;
+
+    let mut vertices = Vec::new();
+    let mut triangles = Vec::new();
+    for node in scene.nodes() {
+        collect_triangles(
+            &node,
+            Mat4::IDENTITY,
+            &buffers,
+            &mut vertices,
+            &mut triangles,
+        )?;
+    }
+    if triangles.is_empty() {
+        bail!("GLB scene contains no triangles");
+    }
+
+    let collider = match quality.vhacd_parameters() {
+        Some(parameters) => {
+            Collider::convex_decomposition_with_config(vertices, triangles, &parameters)
+        }
+        None => Collider::convex_hull(vertices).context("model has no volume")?,
+    };
+    let shape = collider.shape();
+    let parts = match shape.as_compound() {
+        Some(compound) => compound
+            .shapes()
+            .iter()
+            .filter_map(|(isometry, part)| {
+                let polyhedron = part.as_convex_polyhedron()?;
+                Some(
+                    polyhedron
+                        .points()
+                        .iter()
+                        .map(|point| {
+                            let point = isometry * point;
+                            [point.x, point.y, point.z]
+                        })
+                        .collect(),
+                )
+            })
+            .collect(),
+        None => vec![
+            shape
+                .as_convex_polyhedron()
+                .context("collider is neither a compound nor a convex hull")?
+                .points()
+                .iter()
+                .map(|point| [point.x, point.y, point.z])
+                .collect(),
+        ],
+    };
+
+    Ok(ConvexDecomposition {
+        version: COLLIDER_CACHE_VERSION,
+        quality,
+        parts,
+    })
+}
+
+fn collect_triangles(
+    node: &Node,
+    parent: Mat4,
+    buffers: &[buffer::Data],
+    vertices: &mut Vec<Vec3>,
+    triangles: &mut Vec<[u32; 3]>,
+) -> Result<()> {
+    let transform = parent * Mat4::from_cols_array_2d(&node.transform().matrix());
+    if let Some(mesh) = node.mesh() {
+        for primitive in mesh.primitives() {
+            if primitive.mode() != Mode::Triangles {
+                continue;
+            }
+            let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
+            let Some(positions) = reader.read_positions() else {
+                continue;
+            };
+            let offset = vertices.len() as u32;
+            vertices.extend(
+                positions.map(|position| transform.transform_point3(Vec3::from_array(position))),
+            );
+            let vertex_count = vertices.len() as u32 - offset;
+            let indices = reader
+                .read_indices()
+                .map(|indices| indices.into_u32().collect::<Vec<_>>())
+                .unwrap_or_else(|| (0..vertex_count).collect());
+            if let Some(index) = indices.iter().find(|&&index| index >= vertex_count) {
+                bail!(
+                    "mesh {} has index {index} past its {vertex_count} vertices",
+                    mesh.index()
+                );
+            }
+            triangles.extend(
+                indices
+                    .chunks_exact(3)
+                    .map(|triangle| [0, 1, 2].map(|corner| offset + triangle[corner])),
+            );
+        }
+    }
+    for child in node.children() {
+        collect_triangles(&child, transform, buffers, vertices, triangles)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::generate::optimize_model::test_glb;
+
+    /// Writes a box model to a directory of its own and returns its path.
+    fn write_box_model() -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("collider-cache-{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let (positions, indices) = test_glb::cuboid(Vec3::ZERO, Vec3::new(2.0, 1.0, 1.0));
+        let path = dir.join("model.glb");
+        fs::write(
+            &path,
+            test_glb::mesh(&positions, &indices, Vec3::ZERO, None),
+        )
+        .unwrap();
+        path
+    }
+
+    fn write_cache(model_path: &Path, decomposition: &ConvexDecomposition) {
+        let cache_path =
+            model_path.with_extension(format!("{}.collider", decomposition.quality.name()));
+        let encoded = bincode::encode_to_vec(decomposition, bincode::config::standard()).unwrap();
+        fs::write(cache_path, encoded).unwrap();
+    }
+
+    #[test]
+    fn decomposition_is_cached_next_to_the_model() {
+        let model_path = write_box_model();
+
+        let decomposition = load_or_decompose_at(&model_path, ColliderQuality::Hull).unwrap();
+        assert_eq!(decomposition.quality, ColliderQuality::Hull);
+        assert!(decomposition.to_collider().is_some());
+        assert!(model_path.with_extension("hull.collider").is_file());
+
+        // Without the model, only the cache can provide the decomposition.
+        fs::remove_file(&model_path).unwrap();
+        let cached = load_or_decompose_at(&model_path, ColliderQuality::Hull).unwrap();
+        assert_eq!(cached, decomposition);
+
+        fs::remove_dir_all(model_path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn outdated_caches_are_rebuilt() {
+        let model_path = write_box_model();
+        let stale = |version, quality| ConvexDecomposition {
+            version,
+            quality,
+            parts: Vec::new(),
+        };
+
+        write_cache(
+            &model_path,
+            &stale(COLLIDER_CACHE_VERSION - 1, ColliderQuality::Hull),
+        );
+        let decomposition = load_or_decompose_at(&model_path, ColliderQuality::Hull).unwrap();
+        assert_eq!(decomposition.version, COLLIDER_CACHE_VERSION);
+        assert!(!decomposition.parts.is_empty());
+
+        // A cache of another quality under this quality's name is rebuilt as well.
+        let mismatched = stale(COLLIDER_CACHE_VERSION, ColliderQuality::Low);
+        write_cache(&model_path, &mismatched);
+        fs::rename(
+            model_path.with_extension("low.collider"),
+            model_path.with_extension("hull.collider"),
+        )
+        .unwrap();
+        let decomposition = load_or_decompose_at(&model_path, ColliderQuality::Hull).unwrap();
+        assert_eq!(decomposition.quality, ColliderQuality::Hull);
+        assert!(!decomposition.parts.is_empty());
+
+        // Caches that are up to date are used as they are.
+        write_cache(
+            &model_path,
+            &stale(COLLIDER_CACHE_VERSION, ColliderQuality::Hull),
+        );
+        let decomposition = load_or_decompose_at(&model_path, ColliderQuality::Hull).unwrap();
+        assert!(decomposition.parts.is_empty());
+
+        fs::remove_dir_all(model_path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn rejects_indices_past_the_vertices() {
+        let (positions, mut indices) = test_glb::cuboid(Vec3::ZERO, Vec3::ONE);
+        indices[0] = positions.len() as u32;
+        let glb = test_glb::mesh(&positions, &indices, Vec3::ZERO, None);
+        assert!(decompose_glb(&glb, ColliderQuality::Hull).is_err());
+    }
+}
@@ -0,0 +1,100 @@
+use std::{fs, path::Path};
+
+use anyhow::{Context, Result};
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use generative::{
+    ImageData, ImageGenerationRequest, ImageGenerator, ImageOutputFormat, OpenAiImageGenerator,
+};
+use serde::{Deserialize, Serialize};
+use tokio::runtime::Builder;
+use uuid::Uuid;
+
+const GENERATED_CONCEPT_DIR: &str = "models/generated/concepts";
+
+/// A concept image of a prop, painted before the prop is modeled from it.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ConceptArt {
+    /// The asset path of the image.
+    pub path: String,
+}
+
+impl ConceptArt {
+    /// The image as a data URI, which Meshy accepts in place of a URL. Image URLs from the
+    /// generator expire, and a job may only start long after its concept art was painted.
+    pub fn data_uri(&self) -> Result<String> {
+        let file_path = Path::new("assets").join(&self.path);
+        let bytes =
+            fs::read(&file_path).with_context(|| format!("failed to read {file_path:?}"))?;
+        let format = image::guess_format(&bytes)
+            .with_context(|| format!("{file_path:?} is not an image"))?;
+        Ok(format!(
+            "data:{};base64,{}",
+            format.to_mime_type(),
+            STANDARD.encode(bytes)
+        ))
+    }
+
+    /// Deletes the image once nothing needs it anymore, e.g. because the player rejected it.
+    pub fn delete(&self) {
+        let file_path = Path::new("assets").join(&self.path);
+        if let Err(err) = fs::remove_file(&file_path) {
+            tracing::warn!(?err, ?file_path, "failed to delete concept art");
+        }
+    }
+}
+
+/// Paints a concept image of a prop in the style of the world it is for.
+pub fn generate_concept_art(prompt: String, world_prompt: String) -> Result<ConceptArt> {
+    // Image to 3D works best with a single object that is fully in view and easy to cut out.
+    let full_prompt = format!(
+        "concept art of a single {prompt}, fully in view from a three-quarter angle, centered on \
+         a plain white background with no shadows or other objects, in the style of a world \
+         described as: {world_prompt}"
+    );
+
+    let runtime = Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .context("failed to build tokio runtime for generative request")?;
+
+    runtime.block_on(async move {
+        let generator = OpenAiImageGenerator::default();
+        let request =
+            ImageGenerationRequest::new(full_prompt).with_output_format(ImageOutputFormat::Url);
+        let result = generator.generate_image(&request).await?;
+
+        let image_url = result
+            .images
+            .first()
+            .and_then(|image| match &image.data {
+                ImageData::Url(url) => Some(url.clone()),
+                _ => None,
+            })
+            .context("image generation returned no URL")?;
+
+        let bytes = reqwest::get(&image_url)
+            .await
+            .with_context(|| format!("failed to download concept art from {image_url}"))?
+            .bytes()
+            .await
+            .context("failed to read concept art bytes")?;
+
+        // The generator decides the format, whatever was asked for.
+        let format = image::guess_format(&bytes)
+            .with_context(|| format!("concept art from {image_url} is not an image"))?;
+
+        let generated_dir = Path::new("assets").join(GENERATED_CONCEPT_DIR);
+        fs::create_dir_all(&generated_dir)
+            .context("failed to create generated concept directory")?;
+        // Every re-roll gets its own file, as the asset server caches images by path.
+        let file_name = format!("{}.{}", Uuid::new_v4(), format.extensions_str()[0]);
+        let file_path = generated_dir.join(&file_name);
+        fs::write(&file_path, &bytes).context("failed to save concept art")?;
+
+        tracing::info!(prompt, "Generated concept art saved to {:?}", file_path);
+
+        Ok(ConceptArt {
+            path: format!("{GENERATED_CONCEPT_DIR}/{file_name}"),
+        })
+    })
+}
@@ -0,0 +1,163 @@
+use anyhow::{Context, Result, bail};
+use generative::{ChatMessage, OpenAiTextGenerator, TextGenerationRequest, TextGenerator};
+use tokio::runtime::Builder;
+
+/// How many of the most recent messages of a conversation are sent along with a new message.
+/// Older turns are forgotten, which keeps requests small in long conversations.
+pub const MAX_REMEMBERED_MESSAGES: usize = 20;
+
+/// Who an NPC is in a free-form conversation.
+#[derive(Debug, Clone)]
+pub struct NpcPersona {
+    pub name: String,
+    /// A short description of the NPC's character, e.g. "a guard who patrols a fixed route".
+    pub description: String,
+}
+
+/// Asks the model for the NPC's reply to the conversation so far, whose last message is the
+/// player's. Blocks until the reply arrives.
+pub fn generate_reply(
+    world_prompt: &str,
+    persona: &NpcPersona,
+    history: &[ChatMessage],
+) -> Result<String> {
+    let runtime = Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .context("failed to build tokio runtime for conversation")?;
+
+    runtime.block_on(async move {
+        let generator = OpenAiTextGenerator::default();
+        request_reply(&generator, world_prompt, persona, history).await
+    })
+}
+
+/// Asks the given backend for the NPC's reply to the conversation so far.
+pub async fn request_reply(
+    generator: &dyn TextGenerator,
+    world_prompt: &str,
+    persona: &NpcPersona,
+    history: &[ChatMessage],
+) -> Result<String> {
+    let request = conversation_request(world_prompt, persona, history);
+    let response = generator
+        .generate_text(&request)
+        .await
+        .context("text generation request failed")?;
+    let reply = clean_reply(&response.text, &persona.name);
+    if reply.is_empty() {
+        bail!("{} replied with an empty message", persona.name);
+    }
+    Ok(reply)
+}
+
+/// The request for the NPC's next reply: its persona and the world, followed by the most recent
+/// messages of the conversation.
+pub fn conversation_request(
+    world_prompt: &str,
+    persona: &NpcPersona,
+    history: &[ChatMessage],
+) -> TextGenerationRequest {
+    let system_prompt = format!(
+        "You are {name}, a character in a 3D exploration game set in this world: {world_prompt}\n\
+         About you: {description}.\n\
+         Stay in character and never mention that you are in a game or an AI. \
+         Answer the traveller in one to three short sentences of plain spoken text, \
+         without stage directions, markup or your name in front.",
+        name = persona.name,
+        description = persona.description,
+    );
+    let recent = &history[history.len().saturating_sub(MAX_REMEMBERED_MESSAGES)..];
+    TextGenerationRequest::new(
+        std::iter::once(ChatMessage::system(system_prompt)).chain(recent.iter().cloned()),
+    )
+    .with_temperature(0.9)
+    .with_max_tokens(200)
+}
+
+/// Models like to prefix replies with the speaker or wrap them in quotes even when asked not to.
+fn clean_reply(text: &str, name: &str) -> String {
+    let text = text.trim();
+    let text = text
+        .strip_prefix(name)
+        .and_then(|rest| rest.strip_prefix(':'))
+        .unwrap_or(text)
+        .trim();
+    text.trim_matches('"').trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_lite::future;
+    use generative::{ChatRole, MockTextGenerator};
+
+    use super::*;
+
+    fn persona() -> NpcPersona {
+        NpcPersona {
+            name: "Guard 1".to_string(),
+            description: "patrols a fixed route".to_string(),
+        }
+    }
+
+    #[test]
+    fn request_includes_persona_world_and_memory() {
+        let generator = MockTextGenerator::with_replies(["Halt! Who goes there?"]);
+        let history = [
+            ChatMessage::user("Hello"),
+            ChatMessage::assistant("Move along."),
+            ChatMessage::user("What are you guarding?"),
+        ];
+
+        let reply = future::block_on(request_reply(
+            &generator,
+            "a ruined castle on a hill",
+            &persona(),
+            &history,
+        ))
+        .unwrap();
+        assert_eq!(reply, "Halt! Who goes there?");
+
+        let requests = generator.requests();
+        assert_eq!(requests.len(), 1);
+        let messages = &requests[0].messages;
+        assert_eq!(messages[0].role, ChatRole::System);
+        assert!(messages[0].content.contains("Guard 1"));
+        assert!(messages[0].content.contains("patrols a fixed route"));
+        assert!(messages[0].content.contains("a ruined castle on a hill"));
+        assert_eq!(&messages[1..], &history);
+    }
+
+    #[test]
+    fn long_conversations_forget_the_oldest_turns() {
+        let generator = MockTextGenerator::new();
+        let history = (0..MAX_REMEMBERED_MESSAGES + 5)
+            .map(|i| ChatMessage::user(format!("message {i}")))
+            .collect::<Vec<_>>();
+
+        let reply =
+            future::block_on(request_reply(&generator, "a meadow", &persona(), &history)).unwrap();
+        assert_eq!(
+            reply,
+            format!("You said: message {}", MAX_REMEMBERED_MESSAGES + 4)
+        );
+
+        let messages = &generator.requests()[0].messages;
+        assert_eq!(messages.len(), MAX_REMEMBERED_MESSAGES + 1);
+        assert_eq!(messages[1].content, "message 5");
+    }
+
+    #[test]
+    fn replies_are_cleaned_up() {
+        let generator =
+            MockTextGenerator::with_replies(["Guard 1: \"Stay out of trouble.\" ", "  "]);
+        let history = [ChatMessage::user("Hi")];
+
+        let reply =
+            future::block_on(request_reply(&generator, "a town", &persona(), &history)).unwrap();
+        assert_eq!(reply, "Stay out of trouble.");
+
+        let empty = future::block_on(request_reply(&generator, "a town", &persona(), &history));
+        assert!(empty.is_err());
+    }
+}
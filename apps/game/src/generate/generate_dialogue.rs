@@ -0,0 +1,269 @@
+use std::{collections::HashSet, sync::LazyLock};
+
+use anyhow::{Context, Result, bail};
+use generative::{ChatMessage, OpenAiTextGenerator, TextGenerationRequest, TextGenerator};
+use regex::Regex;
+use tokio::runtime::Builder;
+use yarnspinner::prelude::{Compilation, YarnCompiler, YarnFile};
+
+/// All nodes of generated dialogue start with this prefix, so they can never clash with the
+/// hand-written nodes of the Yarn project.
+pub const GENERATED_NODE_PREFIX: &str = "ProceduralNpc";
+/// The commands generated dialogue may use, the same ones the system prompt allows. Anything else
+/// would need a handler in the game, or variables that the rest of the project doesn't declare.
+const ALLOWED_COMMANDS: &[&str] = &["jump", "stop"];
+
+static COMMAND: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"<<\s*(\w+)\s*([^>]*)>>").expect("command regex is valid"));
+
+const SYSTEM_PROMPT: &str = r#"You write dialogue for NPCs in a 3D exploration game, in the Yarn Spinner 2 script format.
+Write one node per NPC, using exactly the node titles you are given. You may add more nodes for
+branches, but their titles must start with the title of the NPC they belong to followed by an
+underscore, for example "ProceduralNpc1_Rumours". Nodes look like this:
+
+title: ProceduralNpc1
+---
+Mira: Oh, a visitor! Welcome to the valley.
+-> Who are you?
+    Mira: I keep an eye on the old well. Someone has to.
+-> What is that tower over there?
+    <<jump ProceduralNpc1_Tower>>
+-> Goodbye.
+    Mira: Safe travels.
+===
+
+Rules:
+- Every line of speech starts with the NPC's name followed by a colon.
+- Only use the commands jump and stop. Do not use variables, functions or markup.
+- Do not use the characters #, {, }, [ or ] in lines.
+- Keep each conversation short: at most eight lines and one or two choices per node.
+- Ground the dialogue in the world and the objects near the NPC. Do not mention that this is a game.
+- Reply with the Yarn script only, without code fences or explanations."#;
+
+/// What an NPC's dialogue should be about.
+#[derive(Debug, Clone)]
+pub struct NpcDialogueBrief {
+    /// The title of the NPC's Yarn node. Must start with [`GENERATED_NODE_PREFIX`].
+    pub node: String,
+    pub name: String,
+    /// A short description of how the NPC behaves, e.g. "wanders around the village".
+    pub behaviour: String,
+    /// Descriptions of props near the NPC.
+    pub nearby_props: Vec<String>,
+}
+
+/// Yarn dialogue generated for the NPCs of a world.
+#[derive(Debug, Clone)]
+pub struct GeneratedDialogue {
+    /// The source of a Yarn file containing a node for every NPC.
+    pub source: String,
+    /// Whether generation failed and the source contains fallback lines instead.
+    pub is_fallback: bool,
+}
+
+/// Generates dialogue for the given NPCs and validates it. Falls back to simple generic lines
+/// for all NPCs if the generated dialogue doesn't compile or breaks the rules.
+pub fn generate_npc_dialogue(world_prompt: &str, npcs: &[NpcDialogueBrief]) -> GeneratedDialogue {
+    let result = request_dialogue(world_prompt, npcs).and_then(|source| {
+        let source = complete_dialogue(source, npcs);
+        validate_dialogue(&source, npcs)?;
+        Ok(source)
+    });
+    match result {
+        Ok(source) => GeneratedDialogue {
+            source,
+            is_fallback: false,
+        },
+        Err(err) => {
+            tracing::warn!(?err, "falling back to generic NPC dialogue");
+            GeneratedDialogue {
+                source: fallback_dialogue(world_prompt, npcs),
+                is_fallback: true,
+            }
+        }
+    }
+}
+
+fn request_dialogue(world_prompt: &str, npcs: &[NpcDialogueBrief]) -> Result<String> {
+    let runtime = Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .context("failed to build tokio runtime for dialogue generation")?;
+
+    runtime.block_on(async move {
+        let generator = OpenAiTextGenerator::default();
+        let request = TextGenerationRequest::new([
+            ChatMessage::system(SYSTEM_PROMPT),
+            ChatMessage::user(dialogue_prompt(world_prompt, npcs)),
+        ])
+        .with_temperature(0.8)
+        .with_max_tokens(2_000);
+
+        let response = generator
+            .generate_text(&request)
+            .await
+            .context("text generation request failed")?;
+        Ok(strip_code_fences(&response.text))
+    })
+}
+
+fn dialogue_prompt(world_prompt: &str, npcs: &[NpcDialogueBrief]) -> String {
+    let mut prompt = format!("The world: {world_prompt}\n\nThe NPCs:\n");
+    for npc in npcs {
+        let props = if npc.nearby_props.is_empty() {
+            "nothing in particular".to_string()
+        } else {
+            npc.nearby_props.join(", ")
+        };
+        prompt.push_str(&format!(
+            "- Node title: {}. Name: {}. Behaviour: {}. Nearby: {}.\n",
+            npc.node, npc.name, npc.behaviour, props
+        ));
+    }
+    prompt
+}
+
+/// Chat models like to wrap their answer in Markdown code fences even when asked not to.
+fn strip_code_fences(text: &str) -> String {
+    text.lines()
+        .filter(|line| !line.trim_start().starts_with("```"))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Adds fallback nodes for NPCs the model forgot about.
+fn complete_dialogue(mut source: String, npcs: &[NpcDialogueBrief]) -> String {
+    let titles = node_titles(&source);
+    for npc in npcs.iter().filter(|npc| !titles.contains(&npc.node)) {
+        tracing::warn!(node = npc.node, "generated dialogue is missing a node");
+        source.push_str("\n\n");
+        source.push_str(&fallback_node(npc, None));
+    }
+    source
+}
+
+fn node_titles(source: &str) -> HashSet<String> {
+    source
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("title:"))
+        .map(|title| title.trim().to_string())
+        .collect()
+}
+
+/// Compiles generated dialogue on its own, to swap its nodes and lines into a dialogue runner.
+pub fn compile_dialogue(source: &str) -> Result<Compilation> {
+    YarnCompiler::new()
+        .add_file(YarnFile {
+            file_name: "generated.yarn".to_string(),
+            source: source.to_string(),
+        })
+        .compile()
+        .map_err(|err| anyhow::anyhow!("generated dialogue does not compile: {err}"))
+}
+
+/// Compiles the dialogue and checks that it only uses node titles and commands that are safe to
+/// add to the game's Yarn project.
+pub fn validate_dialogue(source: &str, npcs: &[NpcDialogueBrief]) -> Result<()> {
+    let program = compile_dialogue(source)?
+        .program
+        .context("compiled dialogue contains no program")?;
+
+    let titles = program.nodes.keys().cloned().collect::<HashSet<_>>();
+    for npc in npcs {
+        if !titles.contains(&npc.node) {
+            bail!("generated dialogue is missing node {}", npc.node);
+        }
+    }
+    if let Some(title) = titles
+        .iter()
+        .find(|title| !title.starts_with(GENERATED_NODE_PREFIX))
+    {
+        bail!(
+            "generated dialogue contains node {title} without the {GENERATED_NODE_PREFIX} prefix"
+        );
+    }
+
+    for captures in COMMAND.captures_iter(source) {
+        let name = &captures[1];
+        if !ALLOWED_COMMANDS.contains(&name) {
+            bail!("generated dialogue uses unsupported command {name}");
+        }
+        let target = captures[2].trim();
+        if name == "jump" && !titles.contains(target) {
+            bail!("generated dialogue jumps to unknown node {target}");
+        }
+    }
+    Ok(())
+}
+
+/// Simple dialogue for all NPCs that is guaranteed to compile.
+pub fn fallback_dialogue(world_prompt: &str, npcs: &[NpcDialogueBrief]) -> String {
+    npcs.iter()
+        .map(|npc| fallback_node(npc, Some(world_prompt)))
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+fn fallback_node(npc: &NpcDialogueBrief, world_prompt: Option<&str>) -> String {
+    let name = sanitize_line(&npc.name);
+    let mut lines = vec![format!(
+        "{name}: Oh, hello there. I don't see many travellers around here."
+    )];
+    if let Some(world_prompt) = world_prompt.map(sanitize_line).filter(|p| !p.is_empty()) {
+        lines.push(format!(
+            "{name}: Welcome to {world_prompt}. Have a look around."
+        ));
+    }
+    if let Some(prop) = npc.nearby_props.first().map(|prop| sanitize_line(prop)) {
+        lines.push(format!("{name}: Careful with that {prop} over there."));
+    }
+    lines.push(format!("{name}: Safe travels."));
+    format!("title: {}\n---\n{}\n===", npc.node, lines.join("\n"))
+}
+
+/// Removes characters that have a special meaning in Yarn lines.
+fn sanitize_line(text: &str) -> String {
+    text.chars()
+        .filter(|c| {
+            !matches!(
+                c,
+                '#' | '{' | '}' | '[' | ']' | '<' | '>' | '\\' | '/' | ':'
+            )
+        })
+        .map(|c| if c.is_whitespace() { ' ' } else { c })
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn brief() -> NpcDialogueBrief {
+        NpcDialogueBrief {
+            node: "ProceduralNpc1".to_string(),
+            name: "Mira".to_string(),
+            behaviour: "guards the well".to_string(),
+            nearby_props: vec!["an old well".to_string()],
+        }
+    }
+
+    #[test]
+    fn accepts_jumps_between_generated_nodes() {
+        let source = "title: ProceduralNpc1\n---\nMira: Hello.\n-> Tell me more.\n    <<jump ProceduralNpc1_More>>\n===\n\ntitle: ProceduralNpc1_More\n---\nMira: That's all.\n<<stop>>\n===";
+        validate_dialogue(source, &[brief()]).unwrap();
+    }
+
+    #[test]
+    fn rejects_variables() {
+        let source = "title: ProceduralNpc1\n---\n<<declare $met = false>>\n<<set $met to true>>\nMira: Hello.\n===";
+        assert!(validate_dialogue(source, &[brief()]).is_err());
+    }
+
+    #[test]
+    fn fallback_dialogue_is_valid() {
+        let source = fallback_dialogue("a misty valley: with a tower", &[brief()]);
+        validate_dialogue(&source, &[brief()]).unwrap();
+    }
+}
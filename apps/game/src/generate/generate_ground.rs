@@ -1,16 +1,79 @@
 use std::{fs, path::Path};
 
 use anyhow::{Context, Result};
+use bevy::color::{Color, Srgba};
 use generative::{
     ImageData, ImageGenerationRequest, ImageGenerator, ImageOutputFormat, OpenAiImageGenerator,
 };
+use image::load_from_memory;
 use tokio::runtime::Builder;
 
-const GENERATED_TEXTURE_PATH: &str = "textures/generated/ground.png";
+const GENERATED_TEXTURE_DIR: &str = "textures/generated";
 
-pub fn generate_ground_texture(prompt: String) -> Result<String> {
+/// The ground layers that procedural terrain blends between.
+/// Which layer covers a point of the terrain depends on its height and slope.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum TerrainLayer {
+    /// The main ground cover on flat and gently sloped terrain, e.g. grass.
+    Ground,
+    /// The bottom of valleys, e.g. sand or mud.
+    Lowland,
+    /// Steep slopes, e.g. bare rock.
+    Cliff,
+    /// The tops of the highest hills, e.g. snow.
+    Peak,
+}
+
+impl TerrainLayer {
+    pub const ALL: [TerrainLayer; 4] = [
+        TerrainLayer::Ground,
+        TerrainLayer::Lowland,
+        TerrainLayer::Cliff,
+        TerrainLayer::Peak,
+    ];
+
+    fn description(self) -> &'static str {
+        match self {
+            TerrainLayer::Ground => {
+                "the main ground cover of flat open land (for example grass, moss or soil)"
+            }
+            TerrainLayer::Lowland => {
+                "the ground at the bottom of valleys and along shores (for example sand, mud or pebbles)"
+            }
+            TerrainLayer::Cliff => {
+                "steep rocky slopes and cliff faces (for example bare rock or gravel)"
+            }
+            TerrainLayer::Peak => "the highest hilltops (for example snow, ice or weathered stone)",
+        }
+    }
+
+    fn file_name(self) -> &'static str {
+        match self {
+            TerrainLayer::Ground => "ground.png",
+            TerrainLayer::Lowland => "ground_lowland.png",
+            TerrainLayer::Cliff => "ground_cliff.png",
+            TerrainLayer::Peak => "ground_peak.png",
+        }
+    }
+}
+
+/// A ground texture that was generated and saved to the assets directory.
+#[derive(Debug, Clone)]
+pub struct GeneratedGroundTexture {
+    /// The asset path of the texture.
+    pub path: String,
+    /// The average color of the texture. Textures under `textures/` only live in the render world
+    /// once loaded, so this is computed right after generation.
+    pub average_color: Color,
+}
+
+pub fn generate_ground_texture(
+    prompt: String,
+    layer: TerrainLayer,
+) -> Result<GeneratedGroundTexture> {
     let full_prompt = format!(
-        "a 3mx3m tilable, seamless ground texture for a world described as: {}",
+        "a 3mx3m tilable, seamless ground texture of {} for a world described as: {}",
+        layer.description(),
         prompt
     );
 
@@ -41,14 +104,34 @@ pub fn generate_ground_texture(prompt: String) -> Result<String> {
             .await
             .context("failed to read generated image bytes")?;
 
-        let generated_dir = Path::new("assets").join("textures/generated");
+        let generated_dir = Path::new("assets").join(GENERATED_TEXTURE_DIR);
         fs::create_dir_all(&generated_dir)
             .context("failed to create generated texture directory")?;
-        let file_path = generated_dir.join("ground.png");
+        let file_path = generated_dir.join(layer.file_name());
         fs::write(&file_path, &bytes).context("failed to save generated texture")?;
 
-        tracing::info!("Generated ground texture saved to {:?}", file_path);
+        tracing::info!(?layer, "Generated ground texture saved to {:?}", file_path);
 
-        Ok(GENERATED_TEXTURE_PATH.to_string())
+        Ok(GeneratedGroundTexture {
+            path: format!("{GENERATED_TEXTURE_DIR}/{}", layer.file_name()),
+            average_color: average_color(&bytes)?,
+        })
     })
 }
+
+fn average_color(bytes: &[u8]) -> Result<Color> {
+    // A thumbnail is plenty for an average and much cheaper than the full texture.
+    let image = load_from_memory(bytes)
+        .context("failed to decode generated ground texture")?
+        .thumbnail(64, 64)
+        .to_rgb8();
+
+    let mut sum = [0.0_f32; 3];
+    for pixel in image.pixels() {
+        for (channel, value) in sum.iter_mut().zip(pixel.0) {
+            *channel += value as f32 / 255.0;
+        }
+    }
+    let count = (image.width() * image.height()).max(1) as f32;
+    Ok(Srgba::rgb(sum[0] / count, sum[1] / count, sum[2] / count).into())
+}
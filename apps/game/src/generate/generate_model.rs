@@ -1,26 +1,240 @@
-use std::{fs, path::Path, time::Duration};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
 use anyhow::{Context, Result, bail};
+use gltf::Gltf;
+use image::ImageFormat;
 use reqwest::{
     Client,
     header::{AUTHORIZATION, HeaderMap, HeaderValue},
 };
 use serde_json::json;
+use thiserror::Error;
 use tokio::{runtime::Builder, time::sleep};
 use uuid::Uuid;
 
+use super::{
+    generate_collider::{ColliderQuality, ConvexDecomposition, load_or_decompose},
+    generate_concept::ConceptArt,
+    model_bounds::{ModelBounds, count_triangles, measure_glb},
+    model_options::ModelOptions,
+    optimize_model::optimize_glb,
+    validate_model::{ModelValidationError, validate_glb},
+};
+
 const MESHY_BASE_URL: &str = "https://api.meshy.ai/openapi/v2/text-to-3d";
+const MESHY_IMAGE_TO_3D_URL: &str = "https://api.meshy.ai/openapi/v1/image-to-3d";
 const GENERATED_MODEL_DIR: &str = "models/generated";
 const PREVIEW_FILENAME: &str = "preview.glb";
 const REFINED_FILENAME: &str = "refined.glb";
+const THUMBNAIL_FILENAME: &str = "thumbnail.png";
+/// Thumbnails are only shown in the prop library, so there is no point in keeping them large.
+const THUMBNAIL_SIZE: u32 = 256;
 const POLL_INTERVAL: Duration = Duration::from_secs(5);
 
-pub struct GeneratedModelPaths {
-    pub preview_path: String,
-    pub refined_path: String,
+/// A generated model written to disk, ready to spawn.
+#[derive(Debug, Clone)]
+pub struct GeneratedModel {
+    /// The asset path of the model.
+    pub path: String,
+    /// How many levels of detail the model has, each loaded as `#Scene{n}`.
+    pub lod_count: usize,
+    /// The bounds of the model, used to scale it to its real-world size. `None` if the model
+    /// could not be measured.
+    pub bounds: Option<ModelBounds>,
+    /// How many triangles the finest level of detail has.
+    pub triangles: usize,
+    /// The collider of the model, which is also cached next to it. `None` if it hasn't been
+    /// decomposed or the decomposition failed.
+    pub collider: Option<ConvexDecomposition>,
+}
+
+/// Meshy gave up on a task, so it has to be started over instead of being waited for again.
+#[derive(Debug, Error)]
+#[error("Meshy task {task_id} failed: {reason}")]
+pub struct MeshyTaskFailed {
+    pub task_id: String,
+    pub reason: String,
+}
+
+/// The untextured preview of a prop, which arrives minutes before the refined model.
+#[derive(Debug, Clone)]
+pub struct ModelPreview {
+    pub generation_id: String,
+    /// The Meshy task that generated the preview, which the refine task continues from.
+    pub preview_task_id: String,
+    pub model: GeneratedModel,
+}
+
+/// The final model of a prop.
+#[derive(Debug)]
+pub struct RefinedModel {
+    pub generation_id: String,
+    /// The refined model, or the preview if the refined model was invalid.
+    pub model: GeneratedModel,
+    /// Why the refined model was invalid, if it was.
+    pub refined_error: Option<ModelValidationError>,
+    /// The asset path of a thumbnail of the refined model, if Meshy rendered one.
+    pub thumbnail: Option<String>,
+}
+
+/// Asks Meshy for the untextured preview of a prop and returns the ID of its task. Continue with
+/// [`finish_preview`].
+pub fn start_preview(prompt: String, options: ModelOptions) -> Result<String> {
+    run_meshy_pipeline(async move |client| create_preview_task(client, &prompt, &options).await)
+}
+
+/// Waits for a preview task to finish and downloads its model. The task may have been started
+/// before the game restarted, in which case it has likely finished already. Continue with
+/// [`start_refine`] to texture the preview.
+pub fn finish_preview(preview_task_id: String) -> Result<ModelPreview> {
+    run_meshy_pipeline(async move |client| {
+        let preview_task =
+            poll_task_until_finished(client, MESHY_BASE_URL, &preview_task_id).await?;
+        let preview_url =
+            extract_glb_url(&preview_task).context("Meshy preview response missing glb URL")?;
+        let preview_bytes = download_model(client, &preview_url).await?;
+        validate_glb(&preview_bytes).context("Meshy returned an invalid preview model")?;
+
+        let generation_id = Uuid::new_v4().to_string();
+        let model = write_model(&generation_id, PREVIEW_FILENAME, &preview_bytes)?;
+        Ok(ModelPreview {
+            generation_id,
+            preview_task_id,
+            model,
+        })
+    })
+}
+
+/// Asks Meshy to texture a preview and returns the ID of the refine task. Continue with
+/// [`finish_refine`].
+pub fn start_refine(preview_task_id: String, options: ModelOptions) -> Result<String> {
+    run_meshy_pipeline(async move |client| {
+        create_refine_task(client, &preview_task_id, &options).await
+    })
+}
+
+/// Waits for a refine task to finish, downloads the textured model and decomposes its collider.
+/// Like [`finish_preview`], this picks up tasks started before the game restarted.
+pub fn finish_refine(
+    preview: ModelPreview,
+    refined_task_id: String,
+    collider_quality: ColliderQuality,
+) -> Result<RefinedModel> {
+    run_meshy_pipeline(async move |client| {
+        let refined_task =
+            poll_task_until_finished(client, MESHY_BASE_URL, &refined_task_id).await?;
+        let refined_url =
+            extract_glb_url(&refined_task).context("Meshy refine response missing glb URL")?;
+        let refined_bytes = download_model(client, &refined_url).await?;
+        let thumbnail = match extract_thumbnail_url(&refined_task) {
+            Some(url) => save_thumbnail(client, &preview.generation_id, &url)
+                .await
+                .inspect_err(|err| tracing::warn!(?err, "failed to save thumbnail"))
+                .ok(),
+            None => None,
+        };
+
+        // The preview is untextured, but still better than nothing.
+        let (mut model, refined_error) = match validate_glb(&refined_bytes) {
+            Ok(()) => (
+                write_model(&preview.generation_id, REFINED_FILENAME, &refined_bytes)?,
+                None,
+            ),
+            Err(err) => {
+                tracing::warn!(%err, "refined model is invalid; falling back to the preview");
+                (preview.model, Some(err))
+            }
+        };
+        model.collider = load_or_decompose(&model.path, collider_quality)
+            .inspect_err(|err| tracing::warn!(?err, "failed to decompose generated model"))
+            .ok();
+
+        Ok(RefinedModel {
+            generation_id: preview.generation_id,
+            model,
+            refined_error,
+            thumbnail,
+        })
+    })
+}
+
+/// Asks Meshy for a textured model of a concept image and returns the ID of its task. Unlike text
+/// to 3D, there is no preview in between. Continue with [`finish_image_to_3d`].
+pub fn start_image_to_3d(concept: ConceptArt, options: ModelOptions) -> Result<String> {
+    let image_url = concept.data_uri()?;
+    run_meshy_pipeline(async move |client| {
+        create_image_to_3d_task(client, &image_url, &options).await
+    })
+}
+
+/// Waits for an image to 3D task to finish, downloads the model and decomposes its collider. Like
+/// [`finish_preview`], this picks up tasks started before the game restarted.
+pub fn finish_image_to_3d(
+    task_id: String,
+    collider_quality: ColliderQuality,
+) -> Result<RefinedModel> {
+    run_meshy_pipeline(async move |client| {
+        let task = poll_task_until_finished(client, MESHY_IMAGE_TO_3D_URL, &task_id).await?;
+        let url = extract_glb_url(&task).context("Meshy image to 3D response missing glb URL")?;
+        let bytes = download_model(client, &url).await?;
+        validate_glb(&bytes).context("Meshy returned an invalid model")?;
+
+        let generation_id = Uuid::new_v4().to_string();
+        let mut model = write_model(&generation_id, REFINED_FILENAME, &bytes)?;
+        let thumbnail = match extract_thumbnail_url(&task) {
+            Some(url) => save_thumbnail(client, &generation_id, &url)
+                .await
+                .inspect_err(|err| tracing::warn!(?err, "failed to save thumbnail"))
+                .ok(),
+            None => None,
+        };
+        model.collider = load_or_decompose(&model.path, collider_quality)
+            .inspect_err(|err| tracing::warn!(?err, "failed to decompose generated model"))
+            .ok();
+
+        Ok(RefinedModel {
+            generation_id,
+            model,
+            refined_error: None,
+            thumbnail,
+        })
+    })
 }
 
-pub fn generate_prop(prompt: String) -> Result<GeneratedModelPaths> {
+/// Loads a model generated earlier, e.g. one from the prop library, with its collider. Call this
+/// from a task, as the collider is decomposed again if its cache is missing.
+pub fn load_model(path: &str, collider_quality: ColliderQuality) -> Result<GeneratedModel> {
+    let file_path = Path::new("assets").join(path);
+    let bytes =
+        fs::read(&file_path).with_context(|| format!("failed to read model {file_path:?}"))?;
+    let lod_count = Gltf::from_slice(&bytes)
+        .context("failed to parse GLB")?
+        .scenes()
+        .count();
+    if lod_count == 0 {
+        bail!("model {file_path:?} has no scenes");
+    }
+    let bounds = measure_glb(&bytes)
+        .inspect_err(|err| tracing::warn!(?err, path, "failed to measure generated model"))
+        .ok();
+    let collider = load_or_decompose(path, collider_quality)
+        .inspect_err(|err| tracing::warn!(?err, path, "failed to decompose generated model"))
+        .ok();
+    Ok(GeneratedModel {
+        path: path.to_string(),
+        lod_count,
+        bounds,
+        triangles: count_triangles(&bytes).unwrap_or_default(),
+        collider,
+    })
+}
+
+/// Runs a stage of the Meshy pipeline to completion on a runtime of its own.
+fn run_meshy_pipeline<T>(stage: impl AsyncFnOnce(&Client) -> Result<T>) -> Result<T> {
     let runtime = Builder::new_current_thread()
         .enable_all()
         .build()
@@ -31,45 +245,63 @@ pub fn generate_prop(prompt: String) -> Result<GeneratedModelPaths> {
             .context("environment variable MESHY_API_KEY is not set")?;
 
         let client = build_client(&api_key)?;
+        stage(&client).await
+    })
+}
 
-        let preview_task_id = create_preview_task(&client, &prompt).await?;
-        let preview_task = poll_task_until_finished(&client, &preview_task_id).await?;
-        let preview_url =
-            extract_glb_url(&preview_task).context("Meshy preview response missing glb URL")?;
-        let preview_bytes = download_model(&client, &preview_url).await?;
+/// Optimizes a validated model and writes it to the folder of its generation.
+fn write_model(generation_id: &str, filename: &str, bytes: &[u8]) -> Result<GeneratedModel> {
+    // A model that can't be optimized is still better than none, so fall back to it as-is.
+    let (bytes, lod_count) = match optimize_glb(bytes) {
+        Ok(optimized) => optimized,
+        Err(err) => {
+            tracing::warn!(?err, filename, "failed to optimize generated model");
+            (bytes.to_vec(), 1)
+        }
+    };
+    let bounds = measure_glb(&bytes)
+        .inspect_err(|err| tracing::warn!(?err, filename, "failed to measure generated model"))
+        .ok();
+    let triangles = count_triangles(&bytes).unwrap_or_default();
+
+    let generated_dir = generation_dir(generation_id);
+    fs::create_dir_all(&generated_dir)
+        .with_context(|| format!("failed to create directory {:?}", generated_dir))?;
+    let path = generated_dir.join(filename);
+    fs::write(&path, &bytes).with_context(|| format!("failed to write model to {:?}", path))?;
+    tracing::info!(?path, "Generated Meshy 3D model written to disk");
+
+    Ok(GeneratedModel {
+        path: format!("{GENERATED_MODEL_DIR}/{generation_id}/{filename}"),
+        lod_count,
+        bounds,
+        triangles,
+        collider: None,
+    })
+}
 
-        let refined_task_id = create_refine_task(&client, &preview_task_id).await?;
-        let refined_task = poll_task_until_finished(&client, &refined_task_id).await?;
-        let refined_url =
-            extract_glb_url(&refined_task).context("Meshy refine response missing glb URL")?;
-        let refined_bytes = download_model(&client, &refined_url).await?;
+/// Downloads the thumbnail Meshy rendered of a model and saves it downscaled as a PNG, whatever
+/// format it came in.
+async fn save_thumbnail(client: &Client, generation_id: &str, url: &str) -> Result<String> {
+    let bytes = download_model(client, url).await?;
+    let thumbnail = image::load_from_memory(&bytes)
+        .context("failed to decode thumbnail")?
+        .thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE);
+    let path = generation_dir(generation_id).join(THUMBNAIL_FILENAME);
+    thumbnail
+        .save_with_format(&path, ImageFormat::Png)
+        .with_context(|| format!("failed to write thumbnail to {path:?}"))?;
+    Ok(format!(
+        "{GENERATED_MODEL_DIR}/{generation_id}/{THUMBNAIL_FILENAME}"
+    ))
+}
 
-        let generation_id = Uuid::new_v4().to_string();
-        let generated_dir = Path::new("assets")
-            .join(GENERATED_MODEL_DIR)
-            .join(&generation_id);
-        fs::create_dir_all(&generated_dir)
-            .with_context(|| format!("failed to create directory {:?}", generated_dir))?;
-
-        let preview_path = generated_dir.join(PREVIEW_FILENAME);
-        fs::write(&preview_path, &preview_bytes)
-            .with_context(|| format!("failed to write preview model to {:?}", preview_path))?;
-
-        let refined_path = generated_dir.join(REFINED_FILENAME);
-        fs::write(&refined_path, &refined_bytes)
-            .with_context(|| format!("failed to write refined model to {:?}", refined_path))?;
-
-        tracing::info!(
-            preview = ?preview_path,
-            refined = ?refined_path,
-            "Generated Meshy 3D model written to disk"
-        );
-
-        Ok(GeneratedModelPaths {
-            preview_path: format!("{GENERATED_MODEL_DIR}/{generation_id}/{PREVIEW_FILENAME}"),
-            refined_path: format!("{GENERATED_MODEL_DIR}/{generation_id}/{REFINED_FILENAME}"),
-        })
-    })
+/// The folder holding everything generated for a prop: its models, their colliders and its
+/// thumbnail.
+pub fn generation_dir(generation_id: &str) -> PathBuf {
+    Path::new("assets")
+        .join(GENERATED_MODEL_DIR)
+        .join(generation_id)
 }
 
 fn build_client(api_key: &str) -> Result<Client> {
@@ -84,52 +316,75 @@ fn build_client(api_key: &str) -> Result<Client> {
         .context("failed to build reqwest client for Meshy")
 }
 
-async fn create_preview_task(client: &Client, prompt: &str) -> Result<String> {
+async fn create_preview_task(
+    client: &Client,
+    prompt: &str,
+    options: &ModelOptions,
+) -> Result<String> {
     let body = json!({
         "mode": "preview",
         "prompt": prompt,
         "negative_prompt": "low quality, low resolution, low poly, ugly",
-        "art_style": "realistic",
+        "art_style": options.art_style.name(),
+        // The topology and polycount only apply to remeshed models.
         "should_remesh": true,
+        "topology": options.topology.name(),
+        "target_polycount": options.target_polycount,
+        "symmetry_mode": options.symmetry.name(),
     });
-
-    let response = client
-        .post(MESHY_BASE_URL)
-        .json(&body)
-        .send()
-        .await
-        .context("failed to send preview creation request to Meshy")?;
-
-    let status = response.status();
-    if !status.is_success() {
-        let body = response
-            .text()
-            .await
-            .unwrap_or_else(|_| "<failed to read error body>".into());
-        bail!("Meshy preview creation request failed ({status}): {body}");
-    }
-
-    let value: serde_json::Value = response
-        .json()
-        .await
-        .context("failed to deserialize Meshy preview creation response")?;
-
-    extract_task_id(&value)
-        .with_context(|| format!("Meshy preview creation response missing task id: {value}"))
+    create_task(client, MESHY_BASE_URL, &body, "preview").await
 }
 
-async fn create_refine_task(client: &Client, preview_task_id: &str) -> Result<String> {
-    let body = json!({
+async fn create_refine_task(
+    client: &Client,
+    preview_task_id: &str,
+    options: &ModelOptions,
+) -> Result<String> {
+    let mut body = json!({
         "mode": "refine",
         "preview_task_id": preview_task_id,
+        "enable_pbr": options.pbr,
     });
+    if let Some(texture_prompt) = &options.texture_prompt {
+        body["texture_prompt"] = json!(texture_prompt);
+    }
+    create_task(client, MESHY_BASE_URL, &body, "refine").await
+}
 
+async fn create_image_to_3d_task(
+    client: &Client,
+    image_url: &str,
+    options: &ModelOptions,
+) -> Result<String> {
+    // The art style comes from the image itself.
+    let mut body = json!({
+        "image_url": image_url,
+        "should_remesh": true,
+        "topology": options.topology.name(),
+        "target_polycount": options.target_polycount,
+        "symmetry_mode": options.symmetry.name(),
+        "should_texture": true,
+        "enable_pbr": options.pbr,
+    });
+    if let Some(texture_prompt) = &options.texture_prompt {
+        body["texture_prompt"] = json!(texture_prompt);
+    }
+    create_task(client, MESHY_IMAGE_TO_3D_URL, &body, "image to 3D").await
+}
+
+/// Creates a Meshy task and returns its ID. `kind` names the task in errors.
+async fn create_task(
+    client: &Client,
+    endpoint: &str,
+    body: &serde_json::Value,
+    kind: &str,
+) -> Result<String> {
     let response = client
-        .post(MESHY_BASE_URL)
-        .json(&body)
+        .post(endpoint)
+        .json(body)
         .send()
         .await
-        .context("failed to send refine creation request to Meshy")?;
+        .with_context(|| format!("failed to send {kind} creation request to Meshy"))?;
 
     let status = response.status();
     if !status.is_success() {
@@ -137,16 +392,16 @@ async fn create_refine_task(client: &Client, preview_task_id: &str) -> Result<St
             .text()
             .await
             .unwrap_or_else(|_| "<failed to read error body>".into());
-        bail!("Meshy refine creation request failed ({status}): {body}");
+        bail!("Meshy {kind} creation request failed ({status}): {body}");
     }
 
     let value: serde_json::Value = response
         .json()
         .await
-        .context("failed to deserialize Meshy refine creation response")?;
+        .with_context(|| format!("failed to deserialize Meshy {kind} creation response"))?;
 
     extract_task_id(&value)
-        .with_context(|| format!("Meshy refine creation response missing task id: {value}"))
+        .with_context(|| format!("Meshy {kind} creation response missing task id: {value}"))
 }
 
 fn extract_task_id(value: &serde_json::Value) -> Option<String> {
@@ -156,10 +411,14 @@ fn extract_task_id(value: &serde_json::Value) -> Option<String> {
         .map(|id| id.to_string())
 }
 
-async fn poll_task_until_finished(client: &Client, task_id: &str) -> Result<serde_json::Value> {
+async fn poll_task_until_finished(
+    client: &Client,
+    endpoint: &str,
+    task_id: &str,
+) -> Result<serde_json::Value> {
     loop {
         let response = client
-            .get(format!("{MESHY_BASE_URL}/{task_id}"))
+            .get(format!("{endpoint}/{task_id}"))
             .send()
             .await
             .with_context(|| format!("failed to poll Meshy task status for {task_id}"))?;
@@ -186,7 +445,24 @@ async fn poll_task_until_finished(client: &Client, task_id: &str) -> Result<serd
                     .or_else(|| payload.get("message"))
                     .and_then(|reason| reason.as_str())
                     .unwrap_or("Meshy task failed for unspecified reason");
-                bail!("Meshy task {task_id} failed: {reason}");
+                return Err(MeshyTaskFailed {
+                    task_id: task_id.to_string(),
+                    reason: reason.to_string(),
+                }
+                .into());
+            }
+            // Neither will ever finish, so don't wait for them after resuming from the journal.
+            "CANCELED" | "EXPIRED" => {
+                let reason = if status == "CANCELED" {
+                    "the task was canceled"
+                } else {
+                    "the task expired"
+                };
+                return Err(MeshyTaskFailed {
+                    task_id: task_id.to_string(),
+                    reason: reason.to_string(),
+                }
+                .into());
             }
             other => {
                 let progress = payload
@@ -224,6 +500,14 @@ async fn download_model(client: &Client, url: &str) -> Result<Vec<u8>> {
     Ok(bytes.to_vec())
 }
 
+fn extract_thumbnail_url(payload: &serde_json::Value) -> Option<String> {
+    payload
+        .get("thumbnail_url")
+        .and_then(|url| url.as_str())
+        .filter(|url| !url.is_empty())
+        .map(|url| url.to_string())
+}
+
 fn extract_glb_url(payload: &serde_json::Value) -> Option<String> {
     payload
         .get("model_urls")
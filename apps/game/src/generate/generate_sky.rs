@@ -9,9 +9,20 @@ use image::{RgbaImage, imageops, load_from_memory};
 use ktx2_rw::{Ktx2Texture, VkFormat};
 use tokio::runtime::Builder;
 
+use super::sky_analysis::{SkyAnalysis, analyze_sky};
+
 const GENERATED_TEXTURE_PATH: &str = "cubemaps/generated/sky.ktx2";
 
-fn convert_to_ktx2(bytes: &[u8]) -> Result<Ktx2Texture> {
+/// A sky cubemap that was generated and saved to the assets directory.
+#[derive(Debug, Clone)]
+pub struct GeneratedSkyTexture {
+    /// The asset path of the cubemap.
+    pub path: String,
+    /// Lighting information estimated from the sky panorama.
+    pub analysis: SkyAnalysis,
+}
+
+fn decode_panorama(bytes: &[u8]) -> Result<RgbaImage> {
     let mut image = load_from_memory(bytes)
         .context("failed to decode image from downloaded bytes")?
         .to_rgba8();
@@ -38,6 +49,10 @@ fn convert_to_ktx2(bytes: &[u8]) -> Result<Ktx2Texture> {
         );
     }
 
+    Ok(image)
+}
+
+fn convert_to_ktx2(image: &RgbaImage) -> Result<Ktx2Texture> {
     let width = image.width();
     let height = image.height();
     let face_size = height;
@@ -48,7 +63,7 @@ fn convert_to_ktx2(bytes: &[u8]) -> Result<Ktx2Texture> {
     let mut face_pixels = vec![0u8; (face_size * face_size * 4) as usize];
 
     for face in 0..6 {
-        fill_cubemap_face(&image, face, face_size, &mut face_pixels);
+        fill_cubemap_face(image, face, face_size, &mut face_pixels);
         texture
             .set_image_data(0, 0, face, &face_pixels)
             .context("failed to set image data on Ktx2Texture")?;
@@ -155,7 +170,7 @@ fn sample_bilinear(
     result
 }
 
-pub fn generate_sky_texture(prompt: String) -> Result<String> {
+pub fn generate_sky_texture(prompt: String) -> Result<GeneratedSkyTexture> {
     let full_prompt = format!(
         "a 360-degree seamless equirectangular sky panorama, 8k resolution, no seams skybox texture for a world described as: {}",
         prompt
@@ -188,8 +203,11 @@ pub fn generate_sky_texture(prompt: String) -> Result<String> {
             .await
             .context("failed to read generated image bytes")?;
 
+        let panorama = decode_panorama(&bytes)?;
         let ktx2_texture =
-            convert_to_ktx2(&bytes).context("failed to convert downloaded image to KTX2")?;
+            convert_to_ktx2(&panorama).context("failed to convert downloaded image to KTX2")?;
+        let analysis = analyze_sky(&panorama);
+        tracing::info!(?analysis, "Analysed generated sky");
 
         let generated_dir = Path::new("assets").join("cubemaps/generated");
         fs::create_dir_all(&generated_dir)
@@ -201,6 +219,9 @@ pub fn generate_sky_texture(prompt: String) -> Result<String> {
 
         tracing::info!("Generated sky texture saved to {:?}", file_path);
 
-        Ok(GENERATED_TEXTURE_PATH.to_string())
+        Ok(GeneratedSkyTexture {
+            path: GENERATED_TEXTURE_PATH.to_string(),
+            analysis,
+        })
     })
 }
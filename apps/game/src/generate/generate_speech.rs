@@ -0,0 +1,68 @@
+use std::{fs, path::Path};
+
+use anyhow::{Context, Result};
+use generative::{
+    OpenAiSpeechGenerator, SpeechGenerationRequest, SpeechGenerator, SpeechVoice,
+    StubSpeechGenerator,
+};
+use tokio::runtime::Builder;
+
+const SPEECH_CACHE_DIR: &str = "audio/generated/speech";
+
+/// Synthesises a spoken line and returns its asset path. Lines are cached on disk by a hash of
+/// their text and voice, so repeated lines are only synthesised once.
+///
+/// Uses OpenAI's text-to-speech if `OPENAI_API_KEY` is set and an offline stub otherwise.
+pub fn synthesize_line(text: &str, voice: SpeechVoice) -> Result<String> {
+    let use_openai = std::env::var_os("OPENAI_API_KEY").is_some();
+    let backend = if use_openai { "openai" } else { "stub" };
+    let file_name = format!(
+        "{}-{:016x}.wav",
+        voice.name(),
+        cache_key(backend, voice, text)
+    );
+    let asset_path = format!("{SPEECH_CACHE_DIR}/{file_name}");
+    let cache_dir = Path::new("assets").join(SPEECH_CACHE_DIR);
+    let cache_path = cache_dir.join(&file_name);
+    if cache_path.exists() {
+        return Ok(asset_path);
+    }
+
+    let runtime = Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .context("failed to build tokio runtime for speech synthesis")?;
+    let request = SpeechGenerationRequest::new(text, voice);
+    let speech = runtime.block_on(async {
+        if use_openai {
+            OpenAiSpeechGenerator::default()
+                .generate_speech(&request)
+                .await
+        } else {
+            StubSpeechGenerator::new().generate_speech(&request).await
+        }
+    })?;
+
+    fs::create_dir_all(&cache_dir)
+        .with_context(|| format!("failed to create directory {cache_dir:?}"))?;
+    // Write to a temporary file first, so that a half-written line is never mistaken for a cached one.
+    let partial_path = cache_path.with_extension("wav.partial");
+    fs::write(&partial_path, &speech.wav)
+        .with_context(|| format!("failed to write speech to {partial_path:?}"))?;
+    fs::rename(&partial_path, &cache_path)
+        .with_context(|| format!("failed to move speech to {cache_path:?}"))?;
+
+    tracing::debug!(path = ?cache_path, backend, "synthesised dialogue line");
+    Ok(asset_path)
+}
+
+/// A 64-bit FNV-1a hash. Unlike `std`'s hashers it is stable across Rust versions, which matters
+/// for a cache that outlives the build.
+fn cache_key(backend: &str, voice: SpeechVoice, text: &str) -> u64 {
+    [backend, voice.name(), text]
+        .iter()
+        .flat_map(|part| part.bytes().chain([0]))
+        .fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+        })
+}
@@ -1,3 +1,14 @@
+pub mod generate_audio;
+pub mod generate_collider;
+pub mod generate_concept;
+pub mod generate_conversation;
+pub mod generate_dialogue;
 pub mod generate_ground;
 pub mod generate_model;
 pub mod generate_sky;
+pub mod generate_speech;
+pub mod model_bounds;
+pub mod model_options;
+pub mod optimize_model;
+pub mod sky_analysis;
+pub mod validate_model;
@@ -0,0 +1,127 @@
+use anyhow::{Context, Result};
+use bevy::math::{Mat4, Vec3};
+use gltf::{Gltf, Node, Semantic, mesh::Mode};
+
+/// The axis-aligned bounding box of a model's first scene, in the model's own units.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ModelBounds {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl ModelBounds {
+    pub fn size(&self) -> Vec3 {
+        self.max - self.min
+    }
+
+    /// The centre of the bottom face, which is where a prop stands on the ground.
+    pub fn base(&self) -> Vec3 {
+        let center = (self.min + self.max) / 2.0;
+        Vec3::new(center.x, self.min.y, center.z)
+    }
+
+    fn extend(&mut self, other: ModelBounds) {
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+    }
+}
+
+/// Measures a GLB without decoding its buffers. glTF requires position accessors to store their
+/// bounds, so reading the JSON chunk is enough.
+pub fn measure_glb(bytes: &[u8]) -> Result<ModelBounds> {
+    let gltf = Gltf::from_slice(bytes).context("failed to parse GLB")?;
+    // Bevy's `#Scene0` is the first scene, not necessarily the default one.
+    let scene = gltf.scenes().next().context("GLB contains no scene")?;
+    let mut bounds = None;
+    for node in scene.nodes() {
+        measure_node(&node, Mat4::IDENTITY, &mut bounds)?;
+    }
+    bounds.context("GLB scene contains no meshes")
+}
+
+/// Counts the triangles of a GLB's first scene, which is its finest level of detail. Like
+/// [`measure_glb`], this only reads the JSON chunk.
+pub fn count_triangles(bytes: &[u8]) -> Result<usize> {
+    let gltf = Gltf::from_slice(bytes).context("failed to parse GLB")?;
+    let scene = gltf.scenes().next().context("GLB contains no scene")?;
+    let mut triangles = 0;
+    let mut nodes = scene.nodes().collect::<Vec<_>>();
+    while let Some(node) = nodes.pop() {
+        if let Some(mesh) = node.mesh() {
+            triangles += mesh
+                .primitives()
+                .filter(|primitive| primitive.mode() == Mode::Triangles)
+                .filter_map(|primitive| {
+                    let vertices = primitive
+                        .indices()
+                        .or_else(|| primitive.get(&Semantic::Positions))?;
+                    Some(vertices.count() / 3)
+                })
+                .sum::<usize>();
+        }
+        nodes.extend(node.children());
+    }
+    Ok(triangles)
+}
+
+fn measure_node(node: &Node, parent: Mat4, bounds: &mut Option<ModelBounds>) -> Result<()> {
+    let transform = parent * Mat4::from_cols_array_2d(&node.transform().matrix());
+    if let Some(mesh) = node.mesh() {
+        for primitive in mesh.primitives() {
+            let Some(positions) = primitive.get(&Semantic::Positions) else {
+                continue;
+            };
+            let corner = |value: Option<serde_json::Value>| -> Result<Vec3> {
+                let value = value.context("position accessor has no bounds")?;
+                let corner: [f32; 3] = serde_json::from_value(value)
+                    .context("position accessor has invalid bounds")?;
+                Ok(Vec3::from_array(corner))
+            };
+            let (min, max) = (corner(positions.min())?, corner(positions.max())?);
+
+            // Transform all corners, as rotated nodes have their extremes at any of them.
+            for x in [min.x, max.x] {
+                for y in [min.y, max.y] {
+                    for z in [min.z, max.z] {
+                        let point = transform.transform_point3(Vec3::new(x, y, z));
+                        let point_bounds = ModelBounds {
+                            min: point,
+                            max: point,
+                        };
+                        match bounds {
+                            Some(bounds) => bounds.extend(point_bounds),
+                            None => *bounds = Some(point_bounds),
+                        }
+                    }
+                }
+            }
+        }
+    }
+    for child in node.children() {
+        measure_node(&child, transform, bounds)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate::optimize_model::test_glb;
+
+    #[test]
+    fn measures_the_first_scene_with_node_transforms() {
+        let (positions, indices) = test_glb::cuboid(Vec3::ZERO, Vec3::new(2.0, 1.0, 3.0));
+        let glb = test_glb::mesh(&positions, &indices, Vec3::X, None);
+
+        let bounds = measure_glb(&glb).unwrap();
+        assert_eq!(bounds.min, Vec3::new(1.0, 0.0, 0.0));
+        assert_eq!(bounds.max, Vec3::new(3.0, 1.0, 3.0));
+        assert_eq!(bounds.base(), Vec3::new(2.0, 0.0, 1.5));
+        assert_eq!(count_triangles(&glb).unwrap(), 12);
+    }
+
+    #[test]
+    fn rejects_invalid_glbs() {
+        assert!(measure_glb(b"not a model").is_err());
+    }
+}
@@ -0,0 +1,213 @@
+use serde::{Deserialize, Serialize};
+
+/// How Meshy should generate a model. Worlds have defaults for these, so that their props look
+/// alike, which the player can override per prop.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ModelOptions {
+    pub art_style: ArtStyle,
+    /// How many polygons the preview is remeshed to. The finest level of detail is optimized from
+    /// it, so this is an upper bound for the triangles of the prop.
+    pub target_polycount: u32,
+    pub topology: Topology,
+    pub symmetry: SymmetryMode,
+    /// Whether the refined model gets metallic, roughness and normal maps besides its colors.
+    pub pbr: bool,
+    /// Describes the texture separately from the shape, e.g. "weathered green paint". `None`
+    /// textures the model after its prompt.
+    pub texture_prompt: Option<String>,
+}
+
+impl Default for ModelOptions {
+    fn default() -> Self {
+        Self {
+            art_style: ArtStyle::default(),
+            target_polycount: Self::POLYCOUNTS[2],
+            topology: Topology::default(),
+            symmetry: SymmetryMode::default(),
+            pbr: true,
+            texture_prompt: None,
+        }
+    }
+}
+
+impl ModelOptions {
+    /// The polycounts offered to the player, within the range Meshy accepts.
+    pub const POLYCOUNTS: [u32; 5] = [3_000, 10_000, 30_000, 100_000, 300_000];
+
+    /// Changes one of the options for the player, either up or down. Options without an order
+    /// cycle through their values.
+    pub fn step(&mut self, option: ModelOption, up: bool) {
+        match option {
+            ModelOption::ArtStyle => self.art_style = self.art_style.next(),
+            ModelOption::Polycount => {
+                let mut polycounts = Self::POLYCOUNTS.into_iter();
+                let polycount = if up {
+                    polycounts.find(|&polycount| polycount > self.target_polycount)
+                } else {
+                    polycounts.rfind(|&polycount| polycount < self.target_polycount)
+                };
+                self.target_polycount = polycount.unwrap_or(self.target_polycount);
+            }
+            ModelOption::Topology => self.topology = self.topology.next(),
+            ModelOption::Symmetry => {
+                self.symmetry = if up {
+                    self.symmetry.next()
+                } else {
+                    self.symmetry.next().next()
+                }
+            }
+            ModelOption::Pbr => self.pbr = up,
+        }
+    }
+
+    /// Describes the current value of one of the options to the player.
+    pub fn value_text(&self, option: ModelOption) -> String {
+        match option {
+            ModelOption::ArtStyle => self.art_style.name().to_string(),
+            ModelOption::Polycount => format!("{}k", self.target_polycount / 1000),
+            ModelOption::Topology => self.topology.name().to_string(),
+            ModelOption::Symmetry => self.symmetry.name().to_string(),
+            ModelOption::Pbr => if self.pbr { "on" } else { "off" }.to_string(),
+        }
+    }
+}
+
+/// The options the player changes with buttons. The texture prompt is typed instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ModelOption {
+    ArtStyle,
+    Polycount,
+    Topology,
+    Symmetry,
+    Pbr,
+}
+
+impl ModelOption {
+    pub const ALL: [Self; 5] = [
+        Self::ArtStyle,
+        Self::Polycount,
+        Self::Topology,
+        Self::Symmetry,
+        Self::Pbr,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::ArtStyle => "Art Style",
+            Self::Polycount => "Polycount",
+            Self::Topology => "Topology",
+            Self::Symmetry => "Symmetry",
+            Self::Pbr => "PBR Textures",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum ArtStyle {
+    #[default]
+    Realistic,
+    Sculpture,
+}
+
+impl ArtStyle {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Realistic => "realistic",
+            Self::Sculpture => "sculpture",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            Self::Realistic => Self::Sculpture,
+            Self::Sculpture => Self::Realistic,
+        }
+    }
+}
+
+/// Which polygons the remeshed model is made of.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum Topology {
+    Quad,
+    #[default]
+    Triangle,
+}
+
+impl Topology {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Quad => "quad",
+            Self::Triangle => "triangle",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            Self::Quad => Self::Triangle,
+            Self::Triangle => Self::Quad,
+        }
+    }
+}
+
+/// Whether the model is made symmetric. `Auto` lets Meshy decide from the prompt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum SymmetryMode {
+    Off,
+    #[default]
+    Auto,
+    On,
+}
+
+impl SymmetryMode {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Off => "off",
+            Self::Auto => "auto",
+            Self::On => "on",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            Self::Off => Self::Auto,
+            Self::Auto => Self::On,
+            Self::On => Self::Off,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn steps_polycount_within_bounds() {
+        let mut options = ModelOptions::default();
+        let mut step = |up| {
+            options.step(ModelOption::Polycount, up);
+            options.target_polycount
+        };
+        assert_eq!(step(true), 100_000);
+        assert_eq!(step(true), 300_000);
+        assert_eq!(step(true), 300_000);
+        assert_eq!(step(false), 100_000);
+
+        // Polycounts that aren't offered, e.g. from an older journal, step to the nearest one.
+        options.target_polycount = 20_000;
+        options.step(ModelOption::Polycount, false);
+        assert_eq!(options.target_polycount, 10_000);
+    }
+
+    #[test]
+    fn missing_options_fall_back_to_defaults() {
+        let options: ModelOptions = serde_json::from_str(r#"{ "pbr": false }"#).unwrap();
+        assert_eq!(
+            options,
+            ModelOptions {
+                pbr: false,
+                ..ModelOptions::default()
+            }
+        );
+    }
+}
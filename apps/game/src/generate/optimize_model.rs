@@ -0,0 +1,681 @@
+use std::{collections::HashMap, io::Cursor};
+
+use anyhow::{Context, Result, bail};
+use bevy::math::{Mat3, Mat4, Vec2, Vec3};
+use gltf::{Gltf, Node, buffer, image::Source, mesh::Mode};
+use image::{ImageFormat, imageops::FilterType};
+use serde_json::{Value, json};
+
+/// Triangle budgets of the levels of detail, from closest to farthest. Each level is a scene of the
+/// optimized GLB, so level `n` is loaded as `#Scene{n}`.
+pub const LOD_TRIANGLE_BUDGETS: [usize; 3] = [40_000, 10_000, 2_500];
+/// Textures larger than this along either side are scaled down.
+const MAX_TEXTURE_SIZE: u32 = 2048;
+/// Vertices closer than this fraction of the model's size are welded together.
+const WELD_TOLERANCE: f32 = 1e-5;
+
+const GLB_MAGIC: &[u8; 4] = b"glTF";
+const GLB_VERSION: u32 = 2;
+const CHUNK_JSON: u32 = 0x4E4F_534A;
+const CHUNK_BIN: u32 = 0x004E_4942;
+const COMPONENT_FLOAT: u32 = 5126;
+const COMPONENT_UNSIGNED_INT: u32 = 5125;
+const TARGET_ARRAY_BUFFER: u32 = 34962;
+const TARGET_ELEMENT_ARRAY_BUFFER: u32 = 34963;
+
+/// Rewrites a GLB so it is cheap to render: meshes are welded, flattened into one node, and
+/// decimated into a scene per level of detail, and oversized textures are scaled down. Returns the
+/// optimized GLB along with its number of levels of detail, which is lower than the number of
+/// budgets for models that decimate to nothing.
+pub fn optimize_glb(bytes: &[u8]) -> Result<(Vec<u8>, usize)> {
+    let Gltf { document, blob } = Gltf::from_slice(bytes).context("failed to parse GLB")?;
+    let buffers =
+        gltf::import_buffers(&document, None, blob).context("failed to read GLB buffers")?;
+
+    let scene = document.scenes().next().context("GLB contains no scene")?;
+    let mut primitives = Vec::new();
+    for node in scene.nodes() {
+        collect_primitives(&node, Mat4::IDENTITY, &buffers, &mut primitives);
+    }
+    if primitives.is_empty() {
+        bail!("GLB scene contains no triangle meshes");
+    }
+    let source_triangles = primitives
+        .iter()
+        .map(|(mesh, _)| mesh.triangle_count())
+        .sum::<usize>();
+
+    let mut bin = BinaryChunk::default();
+    let images = document
+        .images()
+        .map(|image| {
+            let (bytes, mime_type) = match image.source() {
+                Source::View { view, mime_type } => (view_bytes(&view, &buffers)?, mime_type),
+                Source::Uri { .. } => bail!("external images are not supported"),
+            };
+            let (bytes, mime_type) = downscale_texture(bytes, mime_type);
+            let view = bin.push_view(&bytes, None);
+            Ok(json!({ "bufferView": view, "mimeType": mime_type }))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut meshes = Vec::new();
+    for (lod, budget) in LOD_TRIANGLE_BUDGETS.into_iter().enumerate() {
+        let mut lod_primitives = Vec::new();
+        let mut lod_triangles = 0;
+        for (mesh, material) in &primitives {
+            // Share the budget between primitives by how detailed they are.
+            let share = mesh.triangle_count() as f32 / source_triangles as f32;
+            let mesh = mesh.decimate((budget as f32 * share) as usize);
+            if mesh.indices.is_empty() {
+                continue;
+            }
+            lod_triangles += mesh.triangle_count();
+            lod_primitives.push(bin.push_primitive(&mesh, *material));
+        }
+        if lod_primitives.is_empty() {
+            // glTF meshes need at least one primitive, and smaller budgets leave nothing either.
+            tracing::debug!(
+                lod,
+                "generated model has no triangles left at this level of detail"
+            );
+            break;
+        }
+        tracing::debug!(lod, triangles = lod_triangles, "decimated generated model");
+        meshes.push(json!({ "name": format!("LOD{lod}"), "primitives": lod_primitives }));
+    }
+
+    let lod_count = meshes.len();
+    if lod_count == 0 {
+        bail!("GLB has no triangles left after decimation");
+    }
+
+    let source = document.as_json();
+    // Materials, textures and samplers are kept as they are, so their indices stay valid.
+    let root = json!({
+        "asset": { "version": "2.0", "generator": "dreamsurf model optimizer" },
+        "extensionsUsed": kept_extensions(&source.extensions_used),
+        "extensionsRequired": kept_extensions(&source.extensions_required),
+        "buffers": [{ "byteLength": bin.data.len() }],
+        "bufferViews": bin.views,
+        "accessors": bin.accessors,
+        "images": images,
+        "samplers": serde_json::to_value(&source.samplers)?,
+        "textures": serde_json::to_value(&source.textures)?,
+        "materials": serde_json::to_value(&source.materials)?,
+        "meshes": meshes,
+        "nodes": (0..lod_count)
+            .map(|lod| json!({ "name": format!("LOD{lod}"), "mesh": lod }))
+            .collect::<Vec<_>>(),
+        "scenes": (0..lod_count)
+            .map(|lod| json!({ "name": format!("LOD{lod}"), "nodes": [lod] }))
+            .collect::<Vec<_>>(),
+        "scene": 0,
+    });
+    tracing::info!(
+        source_triangles,
+        lod_count,
+        budgets = ?LOD_TRIANGLE_BUDGETS,
+        "optimized generated model"
+    );
+    Ok((write_glb(&serde_json::to_vec(&root)?, bin.data), lod_count))
+}
+
+/// Collects the triangle meshes below a node with their node transforms applied, along with the
+/// index of their material.
+fn collect_primitives(
+    node: &Node,
+    parent: Mat4,
+    buffers: &[buffer::Data],
+    primitives: &mut Vec<(MeshData, Option<usize>)>,
+) {
+    let transform = parent * Mat4::from_cols_array_2d(&node.transform().matrix());
+    let normal_transform = Mat3::from_mat4(transform).inverse().transpose();
+    if let Some(mesh) = node.mesh() {
+        for primitive in mesh.primitives() {
+            if primitive.mode() != Mode::Triangles {
+                tracing::warn!(mode = ?primitive.mode(), "skipping non-triangle primitive");
+                continue;
+            }
+            let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
+            let Some(positions) = reader.read_positions() else {
+                continue;
+            };
+            let positions = positions
+                .map(|position| transform.transform_point3(Vec3::from_array(position)))
+                .collect::<Vec<_>>();
+            // Attributes that don't have a value for every vertex are dropped.
+            let normals = reader
+                .read_normals()
+                .map(|normals| {
+                    normals
+                        .map(|normal| {
+                            (normal_transform * Vec3::from_array(normal)).normalize_or_zero()
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .filter(|normals| normals.len() == positions.len())
+                .unwrap_or_else(|| vec![Vec3::ZERO; positions.len()]);
+            let uvs = reader
+                .read_tex_coords(0)
+                .map(|uvs| uvs.into_f32().map(Vec2::from_array).collect::<Vec<_>>())
+                .filter(|uvs| uvs.len() == positions.len())
+                .unwrap_or_else(|| vec![Vec2::ZERO; positions.len()]);
+            let indices = reader
+                .read_indices()
+                .map(|indices| indices.into_u32().collect::<Vec<_>>())
+                .unwrap_or_else(|| (0..positions.len() as u32).collect());
+            if indices
+                .iter()
+                .any(|&index| index as usize >= positions.len())
+            {
+                tracing::warn!("skipping primitive with out-of-range indices");
+                continue;
+            }
+
+            let mesh = MeshData {
+                positions,
+                normals,
+                uvs,
+                indices,
+            };
+            primitives.push((mesh.weld(), primitive.material().index()));
+        }
+    }
+    for child in node.children() {
+        collect_primitives(&child, transform, buffers, primitives);
+    }
+}
+
+fn view_bytes<'a>(view: &buffer::View, buffers: &'a [buffer::Data]) -> Result<&'a [u8]> {
+    let buffer = &buffers[view.buffer().index()];
+    view.offset()
+        .checked_add(view.length())
+        .and_then(|end| buffer.get(view.offset()..end))
+        .with_context(|| format!("buffer view {} lies outside its buffer", view.index()))
+}
+
+/// Scales textures down to [`MAX_TEXTURE_SIZE`]. Textures that are small enough, or that the
+/// `image` crate can't decode, are kept as they are.
+fn downscale_texture(bytes: &[u8], mime_type: &str) -> (Vec<u8>, String) {
+    let unchanged = || (bytes.to_vec(), mime_type.to_string());
+    let Ok(texture) = image::load_from_memory(bytes) else {
+        return unchanged();
+    };
+    if texture.width().max(texture.height()) <= MAX_TEXTURE_SIZE {
+        return unchanged();
+    }
+
+    let resized = texture.resize(MAX_TEXTURE_SIZE, MAX_TEXTURE_SIZE, FilterType::Triangle);
+    let mut png = Vec::new();
+    match resized.write_to(&mut Cursor::new(&mut png), ImageFormat::Png) {
+        Ok(()) => {
+            tracing::debug!(
+                from = ?(texture.width(), texture.height()),
+                to = ?(resized.width(), resized.height()),
+                "downscaled texture of generated model"
+            );
+            (png, "image/png".to_string())
+        }
+        Err(err) => {
+            tracing::warn!(?err, "failed to encode downscaled texture");
+            unchanged()
+        }
+    }
+}
+
+/// Extensions that still apply after optimizing. Mesh compression is dropped, as meshes are
+/// rewritten uncompressed.
+fn kept_extensions(extensions: &[String]) -> Vec<&String> {
+    extensions
+        .iter()
+        .filter(|extension| {
+            !matches!(
+                extension.as_str(),
+                "KHR_draco_mesh_compression" | "EXT_meshopt_compression" | "KHR_mesh_quantization"
+            )
+        })
+        .collect()
+}
+
+/// A triangle mesh with the vertex attributes Bevy needs. Tangents are left out, as Bevy
+/// generates them on load for materials with normal maps.
+#[derive(Debug, Clone, PartialEq)]
+struct MeshData {
+    positions: Vec<Vec3>,
+    normals: Vec<Vec3>,
+    uvs: Vec<Vec2>,
+    indices: Vec<u32>,
+}
+
+impl MeshData {
+    fn triangle_count(&self) -> usize {
+        self.indices.len() / 3
+    }
+
+    /// Merges vertices that share position, normal and UV. Meshy splits vertices liberally, and
+    /// welding them lets decimation treat the surface as connected.
+    fn weld(&self) -> Self {
+        let tolerance = (self.size() * WELD_TOLERANCE).max(f32::EPSILON);
+        let quantize = |value: f32, step: f32| (value / step).round() as i64;
+        self.remap(|i| {
+            let (position, normal, uv) = (self.positions[i], self.normals[i], self.uvs[i]);
+            [
+                quantize(position.x, tolerance),
+                quantize(position.y, tolerance),
+                quantize(position.z, tolerance),
+                quantize(normal.x, 1e-3),
+                quantize(normal.y, 1e-3),
+                quantize(normal.z, 1e-3),
+                quantize(uv.x, 1e-5),
+                quantize(uv.y, 1e-5),
+            ]
+        })
+    }
+
+    /// Reduces the mesh to at most `budget` triangles by clustering vertices on a grid, using the
+    /// finest grid that fits the budget.
+    fn decimate(&self, budget: usize) -> Self {
+        if self.triangle_count() <= budget {
+            return self.clone();
+        }
+
+        let islands = self.islands();
+        // A single cell collapses every island to a point, so it always fits the budget.
+        let (mut coarse, mut fine) = (1_u32, 1024_u32);
+        let mut best = self.cluster(coarse, &islands);
+        while coarse + 1 < fine {
+            let cells = (coarse + fine) / 2;
+            let candidate = self.cluster(cells, &islands);
+            if candidate.triangle_count() <= budget {
+                coarse = cells;
+                best = candidate;
+            } else {
+                fine = cells;
+            }
+        }
+        best
+    }
+
+    /// Labels each vertex with the island of connected triangles it belongs to. After welding,
+    /// islands end at UV seams and hard edges, whose vertices must not be merged.
+    fn islands(&self) -> Vec<usize> {
+        fn root(parents: &mut [usize], mut vertex: usize) -> usize {
+            while parents[vertex] != vertex {
+                parents[vertex] = parents[parents[vertex]];
+                vertex = parents[vertex];
+            }
+            vertex
+        }
+
+        let mut parents = (0..self.positions.len()).collect::<Vec<_>>();
+        for triangle in self.indices.chunks_exact(3) {
+            let first = root(&mut parents, triangle[0] as usize);
+            for &vertex in &triangle[1..] {
+                let other = root(&mut parents, vertex as usize);
+                parents[other] = first;
+            }
+        }
+        (0..parents.len())
+            .map(|vertex| root(&mut parents, vertex))
+            .collect()
+    }
+
+    /// Merges the vertices of each island that fall into the same cell of a grid with `cells`
+    /// cells along the longest side of the mesh.
+    fn cluster(&self, cells: u32, islands: &[usize]) -> Self {
+        let min = self
+            .positions
+            .iter()
+            .copied()
+            .fold(Vec3::INFINITY, Vec3::min);
+        let cell_size = (self.size() / cells as f32).max(f32::EPSILON);
+        self.remap(|i| {
+            let cell = ((self.positions[i] - min) / cell_size).floor();
+            [
+                cell.x as i64,
+                cell.y as i64,
+                cell.z as i64,
+                islands[i] as i64,
+                0,
+                0,
+                0,
+                0,
+            ]
+        })
+    }
+
+    /// Merges vertices with the same key into their average and rebuilds the triangles, dropping
+    /// degenerate and duplicate ones.
+    fn remap(&self, key: impl Fn(usize) -> [i64; 8]) -> Self {
+        let mut clusters = HashMap::new();
+        let mut merged = Self {
+            positions: Vec::new(),
+            normals: Vec::new(),
+            uvs: Vec::new(),
+            indices: Vec::new(),
+        };
+        let mut counts = Vec::new();
+        let mut vertex_map = Vec::with_capacity(self.positions.len());
+        for i in 0..self.positions.len() {
+            let index = *clusters.entry(key(i)).or_insert_with(|| {
+                merged.positions.push(Vec3::ZERO);
+                merged.normals.push(Vec3::ZERO);
+                merged.uvs.push(Vec2::ZERO);
+                counts.push(0.0);
+                merged.positions.len() - 1
+            });
+            merged.positions[index] += self.positions[i];
+            merged.normals[index] += self.normals[i];
+            merged.uvs[index] += self.uvs[i];
+            counts[index] += 1.0;
+            vertex_map.push(index as u32);
+        }
+        for (index, count) in counts.into_iter().enumerate() {
+            merged.positions[index] /= count;
+            merged.normals[index] = merged.normals[index].normalize_or_zero();
+            merged.uvs[index] /= count;
+        }
+
+        let mut seen = HashMap::new();
+        for triangle in self.indices.chunks_exact(3) {
+            let [a, b, c] = [0, 1, 2].map(|corner| vertex_map[triangle[corner] as usize]);
+            if a == b || b == c || a == c {
+                continue;
+            }
+            // Rotate the triangle to start at its smallest index, which keeps its winding.
+            let rotation = [[a, b, c], [b, c, a], [c, a, b]]
+                .into_iter()
+                .min()
+                .expect("a triangle has three rotations");
+            if seen.insert(rotation, ()).is_none() {
+                merged.indices.extend(rotation);
+            }
+        }
+        merged
+    }
+
+    /// The length of the longest side of the bounding box.
+    fn size(&self) -> f32 {
+        let (min, max) = self.positions.iter().fold(
+            (Vec3::INFINITY, Vec3::NEG_INFINITY),
+            |(min, max), position| (min.min(*position), max.max(*position)),
+        );
+        (max - min).max_element().max(0.0)
+    }
+}
+
+/// The binary chunk of the GLB being written, with the views and accessors into it.
+#[derive(Default)]
+struct BinaryChunk {
+    data: Vec<u8>,
+    views: Vec<Value>,
+    accessors: Vec<Value>,
+}
+
+impl BinaryChunk {
+    fn push_view(&mut self, bytes: &[u8], target: Option<u32>) -> usize {
+        // Accessors need their data aligned to the size of their components.
+        while self.data.len() % 4 != 0 {
+            self.data.push(0);
+        }
+        let mut view =
+            json!({ "buffer": 0, "byteOffset": self.data.len(), "byteLength": bytes.len() });
+        if let Some(target) = target {
+            view["target"] = json!(target);
+        }
+        self.data.extend_from_slice(bytes);
+        self.views.push(view);
+        self.views.len() - 1
+    }
+
+    fn push_accessor(&mut self, accessor: Value) -> usize {
+        self.accessors.push(accessor);
+        self.accessors.len() - 1
+    }
+
+    fn push_floats<const N: usize>(
+        &mut self,
+        items: impl Iterator<Item = [f32; N]>,
+        type_name: &str,
+    ) -> usize {
+        let items = items.collect::<Vec<_>>();
+        let bytes = items
+            .iter()
+            .flatten()
+            .flat_map(|value| value.to_le_bytes())
+            .collect::<Vec<_>>();
+        let view = self.push_view(&bytes, Some(TARGET_ARRAY_BUFFER));
+        self.push_accessor(json!({
+            "bufferView": view,
+            "componentType": COMPONENT_FLOAT,
+            "count": items.len(),
+            "type": type_name,
+        }))
+    }
+
+    fn push_primitive(&mut self, mesh: &MeshData, material: Option<usize>) -> Value {
+        let positions = self.push_floats(mesh.positions.iter().map(|p| p.to_array()), "VEC3");
+        // glTF requires position accessors to store their bounds.
+        let (min, max) = mesh.positions.iter().fold(
+            (Vec3::INFINITY, Vec3::NEG_INFINITY),
+            |(min, max), position| (min.min(*position), max.max(*position)),
+        );
+        self.accessors[positions]["min"] = json!(min.to_array());
+        self.accessors[positions]["max"] = json!(max.to_array());
+
+        let normals = self.push_floats(mesh.normals.iter().map(|n| n.to_array()), "VEC3");
+        let uvs = self.push_floats(mesh.uvs.iter().map(|uv| uv.to_array()), "VEC2");
+        let index_bytes = mesh
+            .indices
+            .iter()
+            .flat_map(|index| index.to_le_bytes())
+            .collect::<Vec<_>>();
+        let index_view = self.push_view(&index_bytes, Some(TARGET_ELEMENT_ARRAY_BUFFER));
+        let indices = self.push_accessor(json!({
+            "bufferView": index_view,
+            "componentType": COMPONENT_UNSIGNED_INT,
+            "count": mesh.indices.len(),
+            "type": "SCALAR",
+        }));
+
+        let mut primitive = json!({
+            "attributes": { "POSITION": positions, "NORMAL": normals, "TEXCOORD_0": uvs },
+            "indices": indices,
+        });
+        if let Some(material) = material {
+            primitive["material"] = json!(material);
+        }
+        primitive
+    }
+}
+
+fn write_glb(json: &[u8], mut bin: Vec<u8>) -> Vec<u8> {
+    let mut json = json.to_vec();
+    // Chunks are 4-byte aligned, the JSON one padded with spaces and the binary one with zeros.
+    while json.len() % 4 != 0 {
+        json.push(b' ');
+    }
+    while bin.len() % 4 != 0 {
+        bin.push(0);
+    }
+
+    let length = 12 + 8 + json.len() + 8 + bin.len();
+    let mut glb = Vec::with_capacity(length);
+    glb.extend_from_slice(GLB_MAGIC);
+    glb.extend_from_slice(&GLB_VERSION.to_le_bytes());
+    glb.extend_from_slice(&(length as u32).to_le_bytes());
+    for (kind, data) in [(CHUNK_JSON, json), (CHUNK_BIN, bin)] {
+        glb.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        glb.extend_from_slice(&kind.to_le_bytes());
+        glb.extend_from_slice(&data);
+    }
+    glb
+}
+
+/// GLBs for the tests of the modules that read generated models.
+#[cfg(test)]
+pub(crate) mod test_glb {
+    use super::*;
+
+    /// A GLB with a single mesh under a node with the given translation. A texture, if given, is
+    /// stored as a PNG and used by the mesh's material.
+    pub(crate) fn mesh(
+        positions: &[Vec3],
+        indices: &[u32],
+        translation: Vec3,
+        texture: Option<&[u8]>,
+    ) -> Vec<u8> {
+        let mesh = MeshData {
+            positions: positions.to_vec(),
+            normals: vec![Vec3::Y; positions.len()],
+            uvs: vec![Vec2::ZERO; positions.len()],
+            indices: indices.to_vec(),
+        };
+        let mut bin = BinaryChunk::default();
+        let mut root = json!({ "asset": { "version": "2.0" } });
+        let material = texture.map(|texture| {
+            let view = bin.push_view(texture, None);
+            root["images"] = json!([{ "bufferView": view, "mimeType": "image/png" }]);
+            root["textures"] = json!([{ "source": 0 }]);
+            root["materials"] =
+                json!([{ "pbrMetallicRoughness": { "baseColorTexture": { "index": 0 } } }]);
+            0
+        });
+        let primitive = bin.push_primitive(&mesh, material);
+        root["buffers"] = json!([{ "byteLength": bin.data.len() }]);
+        root["bufferViews"] = json!(bin.views);
+        root["accessors"] = json!(bin.accessors);
+        root["meshes"] = json!([{ "primitives": [primitive] }]);
+        root["nodes"] = json!([{ "mesh": 0, "translation": translation.to_array() }]);
+        root["scenes"] = json!([{ "nodes": [0] }]);
+        write_glb(&serde_json::to_vec(&root).unwrap(), bin.data)
+    }
+
+    /// The positions and indices of a box from `min` to `max`, with its faces pointing outwards.
+    pub(crate) fn cuboid(min: Vec3, max: Vec3) -> (Vec<Vec3>, Vec<u32>) {
+        let positions = (0..8)
+            .map(|corner| {
+                Vec3::new(
+                    if corner & 1 == 0 { min.x } else { max.x },
+                    if corner & 2 == 0 { min.y } else { max.y },
+                    if corner & 4 == 0 { min.z } else { max.z },
+                )
+            })
+            .collect();
+        let indices = vec![
+            0, 4, 6, 0, 6, 2, // -X
+            1, 3, 7, 1, 7, 5, // +X
+            0, 1, 5, 0, 5, 4, // -Y
+            2, 6, 7, 2, 7, 3, // +Y
+            0, 2, 3, 0, 3, 1, // -Z
+            4, 5, 7, 4, 7, 6, // +Z
+        ];
+        (positions, indices)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::Vec3Swizzles;
+
+    use super::*;
+
+    /// A flat grid of `size` by `size` quads, with each quad's vertices split like Meshy does.
+    fn split_grid(size: u32) -> MeshData {
+        let mut mesh = MeshData {
+            positions: Vec::new(),
+            normals: Vec::new(),
+            uvs: Vec::new(),
+            indices: Vec::new(),
+        };
+        for x in 0..size {
+            for z in 0..size {
+                let start = mesh.positions.len() as u32;
+                for (dx, dz) in [(0, 0), (0, 1), (1, 1), (1, 0)] {
+                    let position = Vec3::new((x + dx) as f32, 0.0, (z + dz) as f32);
+                    mesh.positions.push(position);
+                    mesh.normals.push(Vec3::Y);
+                    mesh.uvs.push(position.xz() / size as f32 * 0.5);
+                }
+                mesh.indices
+                    .extend([start, start + 1, start + 2, start, start + 2, start + 3]);
+            }
+        }
+        mesh
+    }
+
+    #[test]
+    fn welding_merges_split_vertices() {
+        let welded = split_grid(4).weld();
+        assert_eq!(welded.positions.len(), 5 * 5);
+        assert_eq!(welded.triangle_count(), 4 * 4 * 2);
+    }
+
+    #[test]
+    fn decimation_fits_the_budget() {
+        let mesh = split_grid(64).weld();
+        for budget in LOD_TRIANGLE_BUDGETS.map(|budget| budget / 20) {
+            let decimated = mesh.decimate(budget);
+            assert!(decimated.triangle_count() <= budget);
+            assert!(decimated.triangle_count() > budget / 8);
+        }
+        assert_eq!(mesh.decimate(usize::MAX), mesh);
+    }
+
+    #[test]
+    fn written_glb_can_be_read_back() {
+        let mut bin = BinaryChunk::default();
+        let primitive = bin.push_primitive(&split_grid(2).weld(), None);
+        let root = json!({
+            "asset": { "version": "2.0" },
+            "buffers": [{ "byteLength": bin.data.len() }],
+            "bufferViews": bin.views,
+            "accessors": bin.accessors,
+            "meshes": [{ "primitives": [primitive] }],
+            "nodes": [{ "mesh": 0 }],
+            "scenes": [{ "nodes": [0] }],
+        });
+        let glb = write_glb(&serde_json::to_vec(&root).unwrap(), bin.data);
+
+        let gltf = Gltf::from_slice(&glb).unwrap();
+        let mesh = gltf.meshes().next().unwrap();
+        let primitive = mesh.primitives().next().unwrap();
+        let bounds = primitive.bounding_box();
+        assert_eq!(bounds.min, [0.0, 0.0, 0.0]);
+        assert_eq!(bounds.max, [2.0, 0.0, 2.0]);
+    }
+
+    #[test]
+    fn optimized_glb_has_a_scene_per_level_of_detail() {
+        let (positions, indices) = test_glb::cuboid(Vec3::ZERO, Vec3::ONE);
+        let glb = test_glb::mesh(&positions, &indices, Vec3::ZERO, None);
+
+        let (optimized, lod_count) = optimize_glb(&glb).unwrap();
+        assert_eq!(lod_count, LOD_TRIANGLE_BUDGETS.len());
+        let gltf = Gltf::from_slice(&optimized).unwrap();
+        assert_eq!(gltf.scenes().count(), lod_count);
+        assert!(gltf.meshes().all(|mesh| mesh.primitives().count() > 0));
+    }
+
+    #[test]
+    fn attributes_without_a_value_per_vertex_are_dropped() {
+        let mut bin = BinaryChunk::default();
+        let mut primitive = bin.push_primitive(&split_grid(1), None);
+        // Two normals are too few for the four vertices of the quad.
+        let normals = bin.push_floats([Vec3::Y, Vec3::Y].map(|n| n.to_array()).into_iter(), "VEC3");
+        primitive["attributes"]["NORMAL"] = json!(normals);
+        let root = json!({
+            "asset": { "version": "2.0" },
+            "buffers": [{ "byteLength": bin.data.len() }],
+            "bufferViews": bin.views,
+            "accessors": bin.accessors,
+            "meshes": [{ "primitives": [primitive] }],
+            "nodes": [{ "mesh": 0 }],
+            "scenes": [{ "nodes": [0] }],
+        });
+        let glb = write_glb(&serde_json::to_vec(&root).unwrap(), bin.data);
+
+        assert!(optimize_glb(&glb).is_ok());
+    }
+}
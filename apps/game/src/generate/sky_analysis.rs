@@ -0,0 +1,124 @@
+use std::f32::consts::PI;
+
+use bevy::{
+    color::{Alpha, Color, LinearRgba, Luminance, Srgba},
+    math::Vec3,
+};
+use image::{RgbaImage, imageops};
+
+/// Size of the downscaled panorama that is analysed. Downscaling also blurs the sun disc
+/// into a patch that is easy to find.
+const ANALYSIS_WIDTH: u32 = 128;
+const ANALYSIS_HEIGHT: u32 = 64;
+
+/// Lighting information estimated from an equirectangular sky panorama.
+#[derive(Debug, Clone, Copy)]
+pub struct SkyAnalysis {
+    /// Direction pointing from the world towards the sun, or whatever the brightest part of the sky is.
+    pub sun_direction: Vec3,
+    /// Color of the sun, normalized so that its brightest channel is 1.
+    pub sun_color: Color,
+    /// How much the sun stands out from the rest of the sky,
+    /// from 0 (overcast, no visible sun) to 1 (a bright sun in a clear sky).
+    pub sun_strength: f32,
+    /// Average linear luminance of the sky above the horizon, from 0 (black) to 1 (white).
+    pub sky_brightness: f32,
+    /// Average color of the sky right above the horizon.
+    pub horizon_color: Color,
+    /// Average color of the sky above the horizon.
+    pub ambient_color: Color,
+}
+
+/// Estimates the sun and the dominant sky colors from a 2:1 equirectangular panorama.
+pub fn analyze_sky(panorama: &RgbaImage) -> SkyAnalysis {
+    let image = imageops::resize(
+        panorama,
+        ANALYSIS_WIDTH,
+        ANALYSIS_HEIGHT,
+        imageops::FilterType::Triangle,
+    );
+    let pixel = |x: u32, y: u32| -> LinearRgba {
+        let [r, g, b, _] = image.get_pixel(x, y).0;
+        Srgba::rgb_u8(r, g, b).into()
+    };
+    // Only the upper half of the panorama is sky; the lower half is usually ground or haze.
+    let sky_rows = ANALYSIS_HEIGHT / 2;
+    let horizon_rows = sky_rows - sky_rows / 5..sky_rows;
+
+    let mut sky_sum = LinearRgba::NONE;
+    let mut sky_weight = 0.0;
+    let mut horizon_sum = LinearRgba::NONE;
+    let mut horizon_weight = 0.0;
+    let mut peak = (0.0, 0, 0);
+    for y in 0..sky_rows {
+        // Rows near the zenith cover a much smaller part of the sky than rows near the horizon.
+        let (_, v) = texel_center(0, y);
+        let weight = ((0.5 - v) * PI).cos();
+        for x in 0..ANALYSIS_WIDTH {
+            let color = pixel(x, y);
+            let luminance = color.luminance();
+            sky_sum += color * weight;
+            sky_weight += weight;
+            if horizon_rows.contains(&y) {
+                horizon_sum += color * weight;
+                horizon_weight += weight;
+            }
+            if luminance > peak.0 {
+                peak = (luminance, x, y);
+            }
+        }
+    }
+    let ambient = sky_sum * (1.0 / sky_weight.max(f32::EPSILON));
+    let horizon = horizon_sum * (1.0 / horizon_weight.max(f32::EPSILON));
+    let (peak_luminance, peak_x, peak_y) = peak;
+    let sky_brightness = ambient.luminance();
+
+    // The sun color is the average of everything nearly as bright as the brightest spot.
+    let mut sun_sum = LinearRgba::NONE;
+    for y in 0..sky_rows {
+        for x in 0..ANALYSIS_WIDTH {
+            let color = pixel(x, y);
+            if color.luminance() >= peak_luminance * 0.85 {
+                sun_sum += color;
+            }
+        }
+    }
+    let sun_max_channel = sun_sum.red.max(sun_sum.green).max(sun_sum.blue);
+    let sun_color = if sun_max_channel > 0.0 {
+        sun_sum * (1.0 / sun_max_channel)
+    } else {
+        LinearRgba::WHITE
+    };
+
+    let contrast = peak_luminance / sky_brightness.max(1e-4);
+    let sun_strength = ((contrast - 1.5) / 2.5).clamp(0.0, 1.0);
+
+    let (u, v) = texel_center(peak_x, peak_y);
+    SkyAnalysis {
+        sun_direction: panorama_direction(u, v),
+        sun_color: sun_color.with_alpha(1.0).into(),
+        sun_strength,
+        sky_brightness,
+        horizon_color: horizon.with_alpha(1.0).into(),
+        ambient_color: ambient.with_alpha(1.0).into(),
+    }
+}
+
+fn texel_center(x: u32, y: u32) -> (f32, f32) {
+    (
+        (x as f32 + 0.5) / ANALYSIS_WIDTH as f32,
+        (y as f32 + 0.5) / ANALYSIS_HEIGHT as f32,
+    )
+}
+
+/// The direction a point of the panorama is seen in.
+/// This is the inverse of the mapping used to build the sky cubemap in `generate_sky`.
+fn panorama_direction(u: f32, v: f32) -> Vec3 {
+    let longitude = (u - 0.5) * 2.0 * PI;
+    let latitude = (0.5 - v) * PI;
+    Vec3::new(
+        latitude.cos() * longitude.cos(),
+        latitude.sin(),
+        latitude.cos() * longitude.sin(),
+    )
+}
@@ -0,0 +1,218 @@
+use std::io::Cursor;
+
+use gltf::{Gltf, image::Source, mesh::Mode};
+use image::ImageReader;
+use thiserror::Error;
+
+/// Models larger than this are rejected before parsing.
+const MAX_FILE_SIZE: usize = 100 * 1024 * 1024;
+/// Models are decimated after validation, but beyond this they would take too long to process.
+const MAX_TRIANGLES: usize = 2_000_000;
+const MAX_TEXTURES: usize = 32;
+const MAX_TEXTURE_SIZE: u32 = 8192;
+
+/// Why a downloaded model can't be spawned.
+#[derive(Debug, Error)]
+pub enum ModelValidationError {
+    #[error("the model is {size} bytes, more than the limit of {limit}", limit = MAX_FILE_SIZE)]
+    TooLarge { size: usize },
+
+    #[error("the model is not a valid GLB: {0}")]
+    InvalidGlb(#[from] gltf::Error),
+
+    #[error("the model has no scene")]
+    NoScene,
+
+    #[error("the model has no triangle meshes")]
+    NoMeshes,
+
+    #[error("the model has {triangles} triangles, more than the limit of {limit}", limit = MAX_TRIANGLES)]
+    TooManyTriangles { triangles: usize },
+
+    #[error("mesh {mesh} has vertices that are not finite numbers")]
+    NonFiniteVertices { mesh: usize },
+
+    #[error("the model has {textures} textures, more than the limit of {limit}", limit = MAX_TEXTURES)]
+    TooManyTextures { textures: usize },
+
+    #[error("texture {texture} is {width}x{height}, larger than the limit of {limit}", limit = MAX_TEXTURE_SIZE)]
+    TextureTooLarge {
+        texture: usize,
+        width: u32,
+        height: u32,
+    },
+
+    #[error("texture {texture} could not be read: {reason}")]
+    InvalidTexture { texture: usize, reason: String },
+}
+
+/// Checks that a GLB is something Bevy can load and we can afford to spawn, without decoding
+/// its textures.
+pub fn validate_glb(bytes: &[u8]) -> Result<(), ModelValidationError> {
+    if bytes.len() > MAX_FILE_SIZE {
+        return Err(ModelValidationError::TooLarge { size: bytes.len() });
+    }
+    let Gltf { document, blob } = Gltf::from_slice(bytes)?;
+    let buffers = gltf::import_buffers(&document, None, blob)?;
+    if document.scenes().next().is_none() {
+        return Err(ModelValidationError::NoScene);
+    }
+
+    let mut triangles = 0;
+    for mesh in document.meshes() {
+        for primitive in mesh.primitives() {
+            if primitive.mode() != Mode::Triangles {
+                continue;
+            }
+            let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
+            let Some(positions) = reader.read_positions() else {
+                continue;
+            };
+            let mut vertices = 0;
+            for position in positions {
+                if !position.iter().all(|coordinate| coordinate.is_finite()) {
+                    return Err(ModelValidationError::NonFiniteVertices { mesh: mesh.index() });
+                }
+                vertices += 1;
+            }
+            triangles += match reader.read_indices() {
+                Some(indices) => indices.into_u32().count() / 3,
+                None => vertices / 3,
+            };
+        }
+    }
+    if triangles == 0 {
+        return Err(ModelValidationError::NoMeshes);
+    }
+    if triangles > MAX_TRIANGLES {
+        return Err(ModelValidationError::TooManyTriangles { triangles });
+    }
+
+    let textures = document.images().count();
+    if textures > MAX_TEXTURES {
+        return Err(ModelValidationError::TooManyTextures { textures });
+    }
+    for image in document.images() {
+        let texture = image.index();
+        let bytes = match image.source() {
+            Source::View { view, .. } => {
+                let buffer = &buffers[view.buffer().index()];
+                let bytes = view
+                    .offset()
+                    .checked_add(view.length())
+                    .and_then(|end| buffer.get(view.offset()..end));
+                let Some(bytes) = bytes else {
+                    return Err(ModelValidationError::InvalidTexture {
+                        texture,
+                        reason: "its buffer view lies outside the buffer".to_string(),
+                    });
+                };
+                bytes
+            }
+            Source::Uri { .. } => {
+                return Err(ModelValidationError::InvalidTexture {
+                    texture,
+                    reason: "external images are not supported".to_string(),
+                });
+            }
+        };
+        let (width, height) = ImageReader::new(Cursor::new(bytes))
+            .with_guessed_format()
+            .map_err(|err| err.to_string())
+            .and_then(|reader| reader.into_dimensions().map_err(|err| err.to_string()))
+            .map_err(|reason| ModelValidationError::InvalidTexture { texture, reason })?;
+        if width.max(height) > MAX_TEXTURE_SIZE {
+            return Err(ModelValidationError::TextureTooLarge {
+                texture,
+                width,
+                height,
+            });
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::Vec3;
+    use image::{GrayImage, ImageFormat};
+
+    use super::*;
+    use crate::generate::optimize_model::test_glb;
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let mut png = Vec::new();
+        GrayImage::new(width, height)
+            .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+            .unwrap();
+        png
+    }
+
+    #[test]
+    fn accepts_a_small_textured_model() {
+        let (positions, indices) = test_glb::cuboid(Vec3::ZERO, Vec3::ONE);
+        let glb = test_glb::mesh(&positions, &indices, Vec3::ZERO, Some(&png(4, 4)));
+        assert!(validate_glb(&glb).is_ok());
+    }
+
+    #[test]
+    fn rejects_vertices_that_are_not_finite() {
+        // Positions are only written to the binary chunk, so swap a marker value there for NaN.
+        let marker = 123.5_f32.to_le_bytes();
+        let (positions, indices) = test_glb::cuboid(Vec3::ZERO, Vec3::splat(123.5));
+        let mut glb = test_glb::mesh(&positions, &indices, Vec3::ZERO, None);
+        for offset in 0..glb.len() - marker.len() {
+            if glb[offset..offset + marker.len()] == marker {
+                glb[offset..offset + marker.len()].copy_from_slice(&f32::NAN.to_le_bytes());
+            }
+        }
+
+        let result = validate_glb(&glb);
+        assert!(matches!(
+            result,
+            Err(ModelValidationError::NonFiniteVertices { mesh: 0 })
+        ));
+    }
+
+    #[test]
+    fn rejects_models_with_too_many_triangles() {
+        let positions = [Vec3::ZERO, Vec3::X, Vec3::Y];
+        let indices = [0, 1, 2].repeat(MAX_TRIANGLES + 1);
+        let glb = test_glb::mesh(&positions, &indices, Vec3::ZERO, None);
+
+        let result = validate_glb(&glb);
+        assert!(matches!(
+            result,
+            Err(ModelValidationError::TooManyTriangles { triangles }) if triangles == MAX_TRIANGLES + 1
+        ));
+    }
+
+    #[test]
+    fn rejects_oversized_textures() {
+        let (positions, indices) = test_glb::cuboid(Vec3::ZERO, Vec3::ONE);
+        let texture = png(MAX_TEXTURE_SIZE + 1, 1);
+        let glb = test_glb::mesh(&positions, &indices, Vec3::ZERO, Some(&texture));
+
+        let result = validate_glb(&glb);
+        assert!(matches!(
+            result,
+            Err(ModelValidationError::TextureTooLarge { texture: 0, width, height: 1 })
+                if width == MAX_TEXTURE_SIZE + 1
+        ));
+    }
+
+    #[test]
+    fn rejects_files_that_are_not_glbs() {
+        let result = validate_glb(b"<html>502 Bad Gateway</html>");
+        assert!(matches!(result, Err(ModelValidationError::InvalidGlb(_))));
+    }
+
+    #[test]
+    fn rejects_oversized_files_before_parsing() {
+        let result = validate_glb(&vec![0; MAX_FILE_SIZE + 1]);
+        assert!(matches!(
+            result,
+            Err(ModelValidationError::TooLarge { size }) if size == MAX_FILE_SIZE + 1
+        ));
+    }
+}
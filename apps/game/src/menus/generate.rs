@@ -1,9 +1,11 @@
 use crate::{
+    gameplay::world_settings::{TimeOfDaySettings, WorldSettings},
+    generate::model_options::ModelOption,
     menus::Menu,
     screens::Screen,
     theme::{palette::SCREEN_BACKGROUND, prelude::*},
 };
-use bevy::{prelude::*, window::CursorGrabMode};
+use bevy::{prelude::*, ui::Val::*, window::CursorGrabMode};
 use bevy_simple_text_input::{TextInput, TextInputPlugin, TextInputSubmitEvent, TextInputSystem};
 
 pub(super) fn plugin(app: &mut App) {
@@ -15,7 +17,25 @@ pub(super) fn plugin(app: &mut App) {
             listener
                 .after(TextInputSystem)
                 .run_if(in_state(Menu::Generate)),
+        )
+        .add_systems(
+            Update,
+            (
+                update_day_night_cycle_label,
+                update_start_hour_label,
+                update_day_length_label,
+                update_water_level_label,
+                update_npc_conversations_label,
+                update_prop_option_labels,
+            )
+                .run_if(in_state(Menu::Generate)),
         );
+
+    app.register_type::<DayNightCycleLabel>();
+    app.register_type::<StartHourLabel>();
+    app.register_type::<DayLengthLabel>();
+    app.register_type::<WaterLevelLabel>();
+    app.register_type::<NpcConversationsLabel>();
 }
 
 fn spawn_generate_menu(mut commands: Commands) {
@@ -24,7 +44,94 @@ fn spawn_generate_menu(mut commands: Commands) {
         BackgroundColor(SCREEN_BACKGROUND),
         StateScoped(Menu::Generate),
         GlobalZIndex(2),
-        children![widget::header("Generate World"), TextInput],
+        children![
+            widget::header("Generate World"),
+            TextInput,
+            (
+                Name::new("World Settings Grid"),
+                Node {
+                    display: Display::Grid,
+                    row_gap: Px(10.0),
+                    column_gap: Px(30.0),
+                    grid_template_columns: RepeatedGridTrack::px(2, 400.0),
+                    ..default()
+                },
+                children![
+                    (
+                        widget::label("Day/Night Cycle"),
+                        Node {
+                            justify_self: JustifySelf::End,
+                            ..default()
+                        }
+                    ),
+                    widget::plus_minus_bar(
+                        DayNightCycleLabel,
+                        disable_day_night_cycle,
+                        enable_day_night_cycle
+                    ),
+                    (
+                        widget::label("Start Time"),
+                        Node {
+                            justify_self: JustifySelf::End,
+                            ..default()
+                        }
+                    ),
+                    widget::plus_minus_bar(StartHourLabel, lower_start_hour, raise_start_hour),
+                    (
+                        widget::label("Day Length"),
+                        Node {
+                            justify_self: JustifySelf::End,
+                            ..default()
+                        }
+                    ),
+                    widget::plus_minus_bar(DayLengthLabel, lower_day_length, raise_day_length),
+                    (
+                        widget::label("Water Level"),
+                        Node {
+                            justify_self: JustifySelf::End,
+                            ..default()
+                        }
+                    ),
+                    widget::plus_minus_bar(WaterLevelLabel, lower_water_level, raise_water_level),
+                    (
+                        widget::label("NPC Conversations"),
+                        Node {
+                            justify_self: JustifySelf::End,
+                            ..default()
+                        }
+                    ),
+                    widget::plus_minus_bar(
+                        NpcConversationsLabel,
+                        disable_npc_conversations,
+                        enable_npc_conversations
+                    ),
+                    (
+                        widget::label("Prop Style"),
+                        Node {
+                            justify_self: JustifySelf::End,
+                            ..default()
+                        }
+                    ),
+                    prop_option_bar(ModelOption::ArtStyle),
+                    (
+                        widget::label("Prop Polycount"),
+                        Node {
+                            justify_self: JustifySelf::End,
+                            ..default()
+                        }
+                    ),
+                    prop_option_bar(ModelOption::Polycount),
+                    (
+                        widget::label("Prop PBR Textures"),
+                        Node {
+                            justify_self: JustifySelf::End,
+                            ..default()
+                        }
+                    ),
+                    prop_option_bar(ModelOption::Pbr),
+                ],
+            ),
+        ],
     ));
 }
 
@@ -43,3 +150,150 @@ fn listener(
 
 #[derive(Resource, Default)]
 pub struct GenerationPrompt(pub String);
+
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+struct DayNightCycleLabel;
+
+fn enable_day_night_cycle(_trigger: Trigger<Pointer<Click>>, mut settings: ResMut<WorldSettings>) {
+    settings.time_of_day.enabled = true;
+}
+
+fn disable_day_night_cycle(_trigger: Trigger<Pointer<Click>>, mut settings: ResMut<WorldSettings>) {
+    settings.time_of_day.enabled = false;
+}
+
+fn update_day_night_cycle_label(
+    mut label: Single<&mut Text, With<DayNightCycleLabel>>,
+    settings: Res<WorldSettings>,
+) {
+    label.0 = if settings.time_of_day.enabled {
+        "On".into()
+    } else {
+        "Off".into()
+    };
+}
+
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+struct StartHourLabel;
+
+fn lower_start_hour(_trigger: Trigger<Pointer<Click>>, mut settings: ResMut<WorldSettings>) {
+    let time_of_day = &mut settings.time_of_day;
+    time_of_day.start_hour = (time_of_day.start_hour - 1.0).rem_euclid(24.0);
+}
+
+fn raise_start_hour(_trigger: Trigger<Pointer<Click>>, mut settings: ResMut<WorldSettings>) {
+    let time_of_day = &mut settings.time_of_day;
+    time_of_day.start_hour = (time_of_day.start_hour + 1.0).rem_euclid(24.0);
+}
+
+fn update_start_hour_label(
+    mut label: Single<&mut Text, With<StartHourLabel>>,
+    settings: Res<WorldSettings>,
+) {
+    label.0 = format!("{:02.0}:00", settings.time_of_day.start_hour.floor());
+}
+
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+struct DayLengthLabel;
+
+fn lower_day_length(_trigger: Trigger<Pointer<Click>>, mut settings: ResMut<WorldSettings>) {
+    let time_of_day = &mut settings.time_of_day;
+    time_of_day.day_length_minutes =
+        (time_of_day.day_length_minutes - 5.0).max(TimeOfDaySettings::MIN_DAY_LENGTH_MINUTES);
+}
+
+fn raise_day_length(_trigger: Trigger<Pointer<Click>>, mut settings: ResMut<WorldSettings>) {
+    let time_of_day = &mut settings.time_of_day;
+    time_of_day.day_length_minutes =
+        (time_of_day.day_length_minutes + 5.0).min(TimeOfDaySettings::MAX_DAY_LENGTH_MINUTES);
+}
+
+fn update_day_length_label(
+    mut label: Single<&mut Text, With<DayLengthLabel>>,
+    settings: Res<WorldSettings>,
+) {
+    label.0 = format!("{:.0} min", settings.time_of_day.day_length_minutes);
+}
+
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+struct WaterLevelLabel;
+
+fn lower_water_level(_trigger: Trigger<Pointer<Click>>, mut settings: ResMut<WorldSettings>) {
+    // Lowering the water below the minimum removes it.
+    settings.water_level = settings
+        .water_level
+        .map(|level| level - 1.0)
+        .filter(|&level| level >= WorldSettings::MIN_WATER_LEVEL);
+}
+
+fn raise_water_level(_trigger: Trigger<Pointer<Click>>, mut settings: ResMut<WorldSettings>) {
+    settings.water_level = Some(match settings.water_level {
+        Some(level) => (level + 1.0).min(WorldSettings::MAX_WATER_LEVEL),
+        None => WorldSettings::MIN_WATER_LEVEL,
+    });
+}
+
+fn update_water_level_label(
+    mut label: Single<&mut Text, With<WaterLevelLabel>>,
+    settings: Res<WorldSettings>,
+) {
+    label.0 = match settings.water_level {
+        Some(level) => format!("{level:.1} m"),
+        None => "Off".into(),
+    };
+}
+
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+struct NpcConversationsLabel;
+
+fn enable_npc_conversations(
+    _trigger: Trigger<Pointer<Click>>,
+    mut settings: ResMut<WorldSettings>,
+) {
+    settings.conversational_npcs = true;
+}
+
+fn disable_npc_conversations(
+    _trigger: Trigger<Pointer<Click>>,
+    mut settings: ResMut<WorldSettings>,
+) {
+    settings.conversational_npcs = false;
+}
+
+fn update_npc_conversations_label(
+    mut label: Single<&mut Text, With<NpcConversationsLabel>>,
+    settings: Res<WorldSettings>,
+) {
+    label.0 = if settings.conversational_npcs {
+        "On".into()
+    } else {
+        "Off".into()
+    };
+}
+
+/// Labels the world's default for one of the options of generated props.
+#[derive(Component)]
+struct PropOptionLabel(ModelOption);
+
+fn prop_option_bar(option: ModelOption) -> impl Bundle {
+    let step = move |up| {
+        move |_trigger: Trigger<Pointer<Click>>, mut settings: ResMut<WorldSettings>| {
+            settings.prop_options.step(option, up);
+        }
+    };
+    widget::plus_minus_bar(PropOptionLabel(option), step(false), step(true))
+}
+
+fn update_prop_option_labels(
+    mut labels: Query<(&mut Text, &PropOptionLabel)>,
+    settings: Res<WorldSettings>,
+) {
+    for (mut label, PropOptionLabel(option)) in &mut labels {
+        label.0 = settings.prop_options.value_text(*option);
+    }
+}
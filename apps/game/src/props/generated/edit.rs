@@ -0,0 +1,672 @@
+//! Edit mode for generated props. Props are selected with the crosshair and can then be deleted,
+//! duplicated, locked in place or reset. Every change to the world is recorded as a [`WorldEdit`],
+//! which can be undone and redone, and serialized to save it with the world.
+
+use std::collections::VecDeque;
+
+use avian3d::prelude::*;
+use bevy::{
+    ecs::system::SystemParam,
+    input::common_conditions::{input_just_pressed, input_just_released},
+    prelude::*,
+    tasks::{IoTaskPool, Task},
+    ui::Val::*,
+};
+#[cfg(feature = "hot_patch")]
+use bevy_simple_subsecond_system::hot;
+use futures_lite::future;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    Pause,
+    gameplay::player::camera::PlayerCamera,
+    generate::{
+        generate_collider::ColliderQuality,
+        generate_model::{GeneratedModel, load_model},
+    },
+    props::generated::{
+        placement::{PlacementGhost, input_unblocked, placement_inactive},
+        spawn::{
+            GeneratedProp, PropId, PropModelPath, SpawnLocation, spawn_generated_prop,
+            spawn_prop_model,
+        },
+    },
+    screens::Screen,
+    theme::{palette::SCREEN_BACKGROUND, widget},
+    third_party::avian3d::CollisionLayer,
+};
+
+/// How far from the camera props can be selected.
+const MAX_SELECTION_DISTANCE: f32 = 10.0;
+/// How far to the side of the original a duplicate spawns.
+const DUPLICATE_OFFSET: f32 = 1.0;
+/// How many edits can be undone.
+const MAX_HISTORY: usize = 100;
+const SELECTION_COLOR: Color = Color::srgb(1.0, 0.8, 0.2);
+const HOVER_COLOR: Color = Color::srgba(1.0, 1.0, 1.0, 0.4);
+
+pub(super) fn plugin(app: &mut App) {
+    app.init_resource::<EditMode>();
+    app.init_resource::<EditHistory>();
+    app.add_systems(
+        Update,
+        (
+            (
+                toggle_edit_mode.run_if(input_just_pressed(KeyCode::KeyB)),
+                (
+                    select_prop.run_if(input_just_released(MouseButton::Left)),
+                    delete_selected.run_if(
+                        input_just_pressed(KeyCode::Delete)
+                            .or(input_just_pressed(KeyCode::Backspace)),
+                    ),
+                    duplicate_selected.run_if(input_just_pressed(KeyCode::KeyC)),
+                    toggle_lock_selected.run_if(input_just_pressed(KeyCode::KeyK)),
+                    reset_selected.run_if(input_just_pressed(KeyCode::KeyT)),
+                    undo_or_redo.run_if(
+                        input_just_pressed(KeyCode::KeyZ).or(input_just_pressed(KeyCode::KeyY)),
+                    ),
+                )
+                    .run_if(edit_mode_active.and(placement_inactive)),
+            )
+                .chain()
+                .run_if(in_state(Pause(false)).and(input_unblocked)),
+            (finish_respawns, update_edit_hint, draw_selection),
+        )
+            .chain()
+            .run_if(in_state(Screen::ProceduralGameplay)),
+    );
+    app.add_systems(OnExit(Screen::ProceduralGameplay), reset_editing);
+}
+
+fn edit_mode_active(edit_mode: Res<EditMode>) -> bool {
+    edit_mode.active
+}
+
+/// Whether edit mode is on, and which prop is selected.
+#[derive(Resource, Debug, Default)]
+pub(crate) struct EditMode {
+    active: bool,
+    selected: Option<Entity>,
+}
+
+/// Whether the mouse buttons place or edit props instead of picking them up.
+#[derive(SystemParam)]
+pub(crate) struct PropEditingInput<'w, 's> {
+    placement: Query<'w, 's, (), With<PlacementGhost>>,
+    edit_mode: Res<'w, EditMode>,
+}
+
+impl PropEditingInput<'_, '_> {
+    pub(crate) fn uses_mouse(&self) -> bool {
+        self.edit_mode.active || !self.placement.is_empty()
+    }
+}
+
+/// A transform in a form that can be saved.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub(crate) struct PropTransform {
+    translation: [f32; 3],
+    rotation: [f32; 4],
+    scale: [f32; 3],
+}
+
+impl From<Transform> for PropTransform {
+    fn from(transform: Transform) -> Self {
+        Self {
+            translation: transform.translation.to_array(),
+            rotation: transform.rotation.to_array(),
+            scale: transform.scale.to_array(),
+        }
+    }
+}
+
+impl From<PropTransform> for Transform {
+    fn from(transform: PropTransform) -> Self {
+        Transform {
+            translation: Vec3::from_array(transform.translation),
+            rotation: Quat::from_array(transform.rotation),
+            scale: Vec3::from_array(transform.scale),
+        }
+    }
+}
+
+/// Everything needed to spawn a generated prop again.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub(crate) struct PropRecord {
+    id: PropId,
+    prompt: String,
+    /// The asset path of the model.
+    model: String,
+    collider_quality: ColliderQuality,
+    transform: PropTransform,
+    /// Whether the prop is static instead of dynamic.
+    locked: bool,
+}
+
+impl PropRecord {
+    /// A prop that was just spawned with the given model, which is dynamic like every new prop.
+    pub(crate) fn new(
+        id: PropId,
+        prompt: &str,
+        model: &GeneratedModel,
+        collider_quality: ColliderQuality,
+        transform: Transform,
+    ) -> Self {
+        Self {
+            id,
+            prompt: prompt.to_string(),
+            model: model.path.clone(),
+            collider_quality,
+            transform: transform.into(),
+            locked: false,
+        }
+    }
+}
+
+/// A change to the generated props of a world.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub(crate) enum WorldEdit {
+    Spawn(PropRecord),
+    Delete(PropRecord),
+    SetTransform {
+        prop: PropId,
+        from: PropTransform,
+        to: PropTransform,
+    },
+    SetLocked {
+        prop: PropId,
+        locked: bool,
+    },
+}
+
+impl WorldEdit {
+    /// The edit that undoes this one.
+    pub(crate) fn inverse(&self) -> Self {
+        match self {
+            Self::Spawn(record) => Self::Delete(record.clone()),
+            Self::Delete(record) => Self::Spawn(record.clone()),
+            Self::SetTransform { prop, from, to } => Self::SetTransform {
+                prop: *prop,
+                from: *to,
+                to: *from,
+            },
+            Self::SetLocked { prop, locked } => Self::SetLocked {
+                prop: *prop,
+                locked: !locked,
+            },
+        }
+    }
+}
+
+/// The edits made to the world, oldest first, and the undone ones that can be redone.
+#[derive(Resource, Serialize, Deserialize, Debug, Default)]
+pub(crate) struct EditHistory {
+    done: VecDeque<WorldEdit>,
+    undone: Vec<WorldEdit>,
+}
+
+impl EditHistory {
+    /// Records an edit that was just applied. Edits that were undone can't be redone after this.
+    pub(crate) fn push(&mut self, edit: WorldEdit) {
+        self.undone.clear();
+        if self.done.len() == MAX_HISTORY {
+            self.done.pop_front();
+        }
+        self.done.push_back(edit);
+    }
+}
+
+/// Applies [`WorldEdit`]s to the props in the world.
+#[derive(SystemParam)]
+pub(crate) struct WorldEditor<'w, 's> {
+    commands: Commands<'w, 's>,
+    props: Query<
+        'w,
+        's,
+        (
+            Entity,
+            &'static PropId,
+            &'static GeneratedProp,
+            Option<&'static PropModelPath>,
+            &'static RigidBody,
+        ),
+    >,
+    transforms: Query<
+        'w,
+        's,
+        (
+            &'static mut Transform,
+            &'static mut LinearVelocity,
+            &'static mut AngularVelocity,
+        ),
+        With<GeneratedProp>,
+    >,
+    respawns: Query<'w, 's, (Entity, &'static mut RespawnTask)>,
+}
+
+impl WorldEditor<'_, '_> {
+    fn find(&self, id: PropId) -> Option<Entity> {
+        self.props
+            .iter()
+            .find(|(_, prop_id, ..)| **prop_id == id)
+            .map(|(entity, ..)| entity)
+    }
+
+    pub(crate) fn id(&self, prop: Entity) -> Option<PropId> {
+        self.props.get(prop).ok().map(|(_, id, ..)| *id)
+    }
+
+    pub(crate) fn transform(&self, prop: Entity) -> Option<Transform> {
+        self.transforms
+            .get(prop)
+            .ok()
+            .map(|(transform, ..)| *transform)
+    }
+
+    /// The respawn of a prop whose model is still loading. Edits to the prop go to its record, so
+    /// that they aren't lost when it spawns.
+    fn pending_respawn(&mut self, id: PropId) -> Option<Mut<'_, RespawnTask>> {
+        self.respawns
+            .iter_mut()
+            .map(|(_, respawn)| respawn)
+            .find(|respawn| respawn.record.id == id)
+    }
+
+    /// Everything needed to spawn the prop again. `None` if it has no model yet.
+    pub(crate) fn record(&self, prop: Entity) -> Option<PropRecord> {
+        let (_, id, generated, model, rigid_body) = self.props.get(prop).ok()?;
+        let (transform, ..) = self.transforms.get(prop).ok()?;
+        Some(PropRecord {
+            id: *id,
+            prompt: generated.prompt.clone(),
+            model: model?.0.clone(),
+            collider_quality: generated.collider_quality,
+            transform: (*transform).into(),
+            locked: *rigid_body == RigidBody::Static,
+        })
+    }
+
+    pub(crate) fn apply(&mut self, edit: &WorldEdit) {
+        match edit {
+            WorldEdit::Spawn(record) => {
+                let task = IoTaskPool::get().spawn({
+                    let model = record.model.clone();
+                    let collider_quality = record.collider_quality;
+                    async move { load_model(&model, collider_quality) }
+                });
+                self.commands.spawn((
+                    Name::new(format!("Respawn {}", record.prompt)),
+                    RespawnTask {
+                        record: record.clone(),
+                        task,
+                    },
+                    StateScoped(Screen::ProceduralGameplay),
+                ));
+            }
+            WorldEdit::Delete(record) => {
+                if let Some(prop) = self.find(record.id) {
+                    self.commands.entity(prop).despawn();
+                }
+                // The prop may still be loading, if its deletion was undone a moment ago.
+                for (entity, respawn) in &self.respawns {
+                    if respawn.record.id == record.id {
+                        self.commands.entity(entity).despawn();
+                    }
+                }
+            }
+            WorldEdit::SetTransform { prop: id, to, .. } => {
+                let Some(prop) = self.find(*id) else {
+                    if let Some(mut respawn) = self.pending_respawn(*id) {
+                        respawn.record.transform = *to;
+                    }
+                    return;
+                };
+                if let Ok((mut transform, mut linear, mut angular)) = self.transforms.get_mut(prop)
+                {
+                    *transform = (*to).into();
+                    // Don't let the prop fly off with the momentum it had before.
+                    *linear = LinearVelocity::ZERO;
+                    *angular = AngularVelocity::ZERO;
+                }
+            }
+            WorldEdit::SetLocked { prop: id, locked } => {
+                if let Some(prop) = self.find(*id) {
+                    self.commands.entity(prop).insert(rigid_body(*locked));
+                } else if let Some(mut respawn) = self.pending_respawn(*id) {
+                    respawn.record.locked = *locked;
+                }
+            }
+        }
+    }
+}
+
+fn rigid_body(locked: bool) -> RigidBody {
+    if locked {
+        RigidBody::Static
+    } else {
+        RigidBody::Dynamic
+    }
+}
+
+/// A prop that is being spawned again, waiting for its model to load.
+#[derive(Component)]
+pub(crate) struct RespawnTask {
+    record: PropRecord,
+    task: Task<anyhow::Result<GeneratedModel>>,
+}
+
+#[cfg_attr(feature = "hot_patch", hot)]
+fn finish_respawns(
+    mut commands: Commands,
+    mut respawns: Query<(Entity, &mut RespawnTask)>,
+    asset_server: Res<AssetServer>,
+) {
+    for (entity, mut respawn) in &mut respawns {
+        let Some(result) = future::block_on(future::poll_once(&mut respawn.task)) else {
+            continue;
+        };
+        commands.entity(entity).despawn();
+        let record = &respawn.record;
+        let model = match result {
+            Ok(model) => model,
+            Err(err) => {
+                error!(prompt = record.prompt, ?err, "failed to respawn prop");
+                continue;
+            }
+        };
+        let prop = spawn_generated_prop(
+            &mut commands,
+            record.id,
+            SpawnLocation {
+                transform: record.transform.into(),
+            },
+            &record.prompt,
+            record.collider_quality,
+        );
+        commands.entity(prop).insert(rigid_body(record.locked));
+        spawn_prop_model(&mut commands, &asset_server, prop, &model, &record.prompt);
+    }
+}
+
+#[derive(Component)]
+struct EditHint;
+
+#[cfg_attr(feature = "hot_patch", hot)]
+fn toggle_edit_mode(
+    mut commands: Commands,
+    mut edit_mode: ResMut<EditMode>,
+    hints: Query<Entity, With<EditHint>>,
+) {
+    edit_mode.active = !edit_mode.active;
+    edit_mode.selected = None;
+    for hint in &hints {
+        commands.entity(hint).despawn();
+    }
+    if !edit_mode.active {
+        return;
+    }
+    commands.spawn((
+        Name::new("Edit Hint"),
+        Node {
+            position_type: PositionType::Absolute,
+            bottom: Px(40.0),
+            left: Percent(15.0),
+            width: Percent(70.0),
+            flex_direction: FlexDirection::Column,
+            align_items: AlignItems::Center,
+            padding: UiRect::all(Px(10.0)),
+            ..default()
+        },
+        BackgroundColor(SCREEN_BACKGROUND.with_alpha(0.8)),
+        Pickable::IGNORE,
+        StateScoped(Screen::ProceduralGameplay),
+        EditHint,
+        children![
+            widget::label("Edit mode: nothing selected"),
+            widget::label_small(
+                "Click: select. Del: delete. C: duplicate. K: lock or unlock. T: reset rotation \
+                 and scale. R: move. Ctrl + Z / Ctrl + Y: undo / redo. B: leave edit mode."
+            ),
+        ],
+    ));
+}
+
+/// Shows what is selected.
+fn update_edit_hint(
+    edit_mode: Res<EditMode>,
+    hints: Query<&Children, With<EditHint>>,
+    props: Query<(&GeneratedProp, &RigidBody)>,
+    mut texts: Query<&mut Text>,
+) {
+    let Ok(children) = hints.single() else {
+        return;
+    };
+    let Some(mut text) = children
+        .first()
+        .and_then(|label| texts.get_mut(*label).ok())
+    else {
+        return;
+    };
+    let status = match edit_mode.selected.and_then(|prop| props.get(prop).ok()) {
+        Some((prop, RigidBody::Static)) => format!("Edit mode: {} (locked)", prop.prompt),
+        Some((prop, _)) => format!("Edit mode: {}", prop.prompt),
+        None => "Edit mode: nothing selected".to_string(),
+    };
+    if text.0 != status {
+        text.0 = status;
+    }
+}
+
+/// The generated prop under the crosshair.
+fn targeted_prop(
+    camera: &GlobalTransform,
+    spatial_query: &SpatialQuery,
+    colliders: &Query<&ColliderOf>,
+    props: &Query<(), With<GeneratedProp>>,
+) -> Option<Entity> {
+    let camera_transform = camera.compute_transform();
+    let hit = spatial_query.cast_ray(
+        camera_transform.translation,
+        camera_transform.forward(),
+        MAX_SELECTION_DISTANCE,
+        true,
+        &SpatialQueryFilter::from_mask(CollisionLayer::Prop),
+    )?;
+    let &ColliderOf { body } = colliders.get(hit.entity).ok()?;
+    props.contains(body).then_some(body)
+}
+
+#[cfg_attr(feature = "hot_patch", hot)]
+fn select_prop(
+    mut edit_mode: ResMut<EditMode>,
+    camera: Single<&GlobalTransform, With<PlayerCamera>>,
+    spatial_query: SpatialQuery,
+    colliders: Query<&ColliderOf>,
+    props: Query<(), With<GeneratedProp>>,
+) {
+    edit_mode.selected = targeted_prop(&camera, &spatial_query, &colliders, &props);
+}
+
+#[cfg_attr(feature = "hot_patch", hot)]
+fn delete_selected(
+    mut edit_mode: ResMut<EditMode>,
+    mut editor: WorldEditor,
+    mut history: ResMut<EditHistory>,
+) {
+    let Some(record) = edit_mode.selected.and_then(|prop| editor.record(prop)) else {
+        return;
+    };
+    let edit = WorldEdit::Delete(record);
+    editor.apply(&edit);
+    history.push(edit);
+    edit_mode.selected = None;
+}
+
+#[cfg_attr(feature = "hot_patch", hot)]
+fn duplicate_selected(
+    edit_mode: Res<EditMode>,
+    mut editor: WorldEditor,
+    mut history: ResMut<EditHistory>,
+    camera: Single<&GlobalTransform, With<PlayerCamera>>,
+) {
+    let Some(mut record) = edit_mode.selected.and_then(|prop| editor.record(prop)) else {
+        return;
+    };
+    // Put the duplicate next to the original from the player's point of view, and let it drop
+    // onto whatever is below.
+    let mut transform = Transform::from(record.transform);
+    transform.translation += camera.right() * DUPLICATE_OFFSET + Vec3::Y * 0.1;
+    record.id = PropId::new();
+    record.transform = transform.into();
+
+    let edit = WorldEdit::Spawn(record);
+    editor.apply(&edit);
+    history.push(edit);
+}
+
+#[cfg_attr(feature = "hot_patch", hot)]
+fn toggle_lock_selected(
+    edit_mode: Res<EditMode>,
+    mut editor: WorldEditor,
+    mut history: ResMut<EditHistory>,
+) {
+    let Some(record) = edit_mode.selected.and_then(|prop| editor.record(prop)) else {
+        return;
+    };
+    let edit = WorldEdit::SetLocked {
+        prop: record.id,
+        locked: !record.locked,
+    };
+    editor.apply(&edit);
+    history.push(edit);
+}
+
+/// Stands the selected prop upright at its original size, where it is.
+#[cfg_attr(feature = "hot_patch", hot)]
+fn reset_selected(
+    edit_mode: Res<EditMode>,
+    mut editor: WorldEditor,
+    mut history: ResMut<EditHistory>,
+) {
+    let Some(record) = edit_mode.selected.and_then(|prop| editor.record(prop)) else {
+        return;
+    };
+    let from = record.transform;
+    let to = Transform::from_translation(Transform::from(from).translation).into();
+    if from == to {
+        return;
+    }
+    let edit = WorldEdit::SetTransform {
+        prop: record.id,
+        from,
+        to,
+    };
+    editor.apply(&edit);
+    history.push(edit);
+}
+
+#[cfg_attr(feature = "hot_patch", hot)]
+fn undo_or_redo(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut editor: WorldEditor,
+    mut history: ResMut<EditHistory>,
+) {
+    if !keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]) {
+        return;
+    }
+    let shift = keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+    if keys.just_pressed(KeyCode::KeyZ) && !shift {
+        let Some(edit) = history.done.pop_back() else {
+            return;
+        };
+        editor.apply(&edit.inverse());
+        history.undone.push(edit);
+    } else {
+        let Some(edit) = history.undone.pop() else {
+            return;
+        };
+        editor.apply(&edit);
+        history.done.push_back(edit);
+    }
+}
+
+/// Outlines the selected prop, and the prop that would be selected by clicking.
+fn draw_selection(
+    edit_mode: Res<EditMode>,
+    camera: Option<Single<&GlobalTransform, With<PlayerCamera>>>,
+    spatial_query: SpatialQuery,
+    colliders: Query<&ColliderOf>,
+    aabbs: Query<(&ColliderAabb, &ColliderOf)>,
+    props: Query<(), With<GeneratedProp>>,
+    mut gizmos: Gizmos,
+) {
+    let Some(camera) = camera.filter(|_| edit_mode.active) else {
+        return;
+    };
+    let hovered = targeted_prop(&camera, &spatial_query, &colliders, &props);
+    for (prop, color) in [
+        (
+            hovered.filter(|prop| Some(*prop) != edit_mode.selected),
+            HOVER_COLOR,
+        ),
+        (edit_mode.selected, SELECTION_COLOR),
+    ] {
+        let Some(prop) = prop else {
+            continue;
+        };
+        let Some(aabb) = aabbs
+            .iter()
+            .filter(|(_, collider_of)| collider_of.body == prop)
+            .map(|(aabb, _)| *aabb)
+            .reduce(|a, b| a.merged(b))
+        else {
+            continue;
+        };
+        gizmos.cuboid(
+            Transform::from_translation(aabb.center()).with_scale(aabb.size()),
+            color,
+        );
+    }
+}
+
+/// Edits only make sense in the world they were made in.
+fn reset_editing(mut edit_mode: ResMut<EditMode>, mut history: ResMut<EditHistory>) {
+    *edit_mode = EditMode::default();
+    *history = EditHistory::default();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edits_round_trip_through_their_inverse_and_json() {
+        let record = PropRecord {
+            id: PropId::new(),
+            prompt: "a wooden chair".to_string(),
+            model: "models/generated/0/refined.glb".to_string(),
+            collider_quality: ColliderQuality::Medium,
+            transform: Transform::from_xyz(1.0, 2.0, 3.0).into(),
+            locked: false,
+        };
+        let edits = [
+            WorldEdit::Spawn(record.clone()),
+            WorldEdit::Delete(record.clone()),
+            WorldEdit::SetTransform {
+                prop: record.id,
+                from: record.transform,
+                to: Transform::from_xyz(4.0, 5.0, 6.0).into(),
+            },
+            WorldEdit::SetLocked {
+                prop: record.id,
+                locked: true,
+            },
+        ];
+        for edit in edits {
+            assert_ne!(edit.inverse(), edit);
+            assert_eq!(edit.inverse().inverse(), edit);
+            let json = serde_json::to_string(&edit).unwrap();
+            assert_eq!(serde_json::from_str::<WorldEdit>(&json).unwrap(), edit);
+        }
+    }
+}
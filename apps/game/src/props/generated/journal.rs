@@ -0,0 +1,220 @@
+//! The journal of Meshy tasks that are still running. Meshy keeps working on a task whether or not
+//! the game waits for it, so the task IDs are written down as soon as they are known, and the
+//! tasks are resumed the next time the player enters the same world.
+
+use std::{fs, path::Path};
+
+use anyhow::{Context as _, Result};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    generate::{
+        generate_collider::ColliderQuality, generate_concept::ConceptArt,
+        model_options::ModelOptions,
+    },
+    menus::generate::GenerationPrompt,
+    props::generated::{
+        edit::PropTransform,
+        queue::{GenerationQueue, JobPriority},
+        spawn::SpawnLocation,
+    },
+    screens::Screen,
+};
+
+const JOURNAL_PATH: &str = "assets/models/generated/journal.json";
+
+pub(super) fn plugin(app: &mut App) {
+    app.add_systems(Startup, load_generation_journal);
+    app.add_systems(OnEnter(Screen::ProceduralGameplay), resume_journaled_jobs);
+}
+
+/// How far a Meshy task got before the game stopped waiting for it.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub(crate) enum RemoteJob {
+    /// Meshy is generating the preview.
+    Preview { task_id: String },
+    /// The preview is on disk and Meshy is texturing it.
+    Refine {
+        generation_id: String,
+        preview_task_id: String,
+        /// The asset path of the preview model.
+        preview_model: String,
+        task_id: String,
+    },
+    /// Meshy is modeling approved concept art, which takes a single task.
+    ImageTo3d { task_id: String },
+}
+
+impl RemoteJob {
+    /// Identifies the job for its whole life, as its first task is the first thing created.
+    fn first_task_id(&self) -> &str {
+        match self {
+            Self::Preview { task_id } | Self::ImageTo3d { task_id } => task_id,
+            Self::Refine {
+                preview_task_id, ..
+            } => preview_task_id,
+        }
+    }
+}
+
+/// A Meshy task that was still running when the journal was last written.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub(crate) struct JournalEntry {
+    /// The prompt of the world the prop was placed in.
+    world: String,
+    prompt: String,
+    collider_quality: ColliderQuality,
+    options: ModelOptions,
+    /// The concept art the prop is modeled from, so that a failed job can be retried from it.
+    concept: Option<ConceptArt>,
+    /// Where the player placed the prop.
+    transform: PropTransform,
+    remote: RemoteJob,
+}
+
+/// The running Meshy tasks of all worlds, kept in sync with [`JOURNAL_PATH`].
+#[derive(Resource, Serialize, Deserialize, Debug, Default)]
+pub(crate) struct GenerationJournal {
+    entries: Vec<JournalEntry>,
+}
+
+impl GenerationJournal {
+    /// Writes down how far a job got, replacing what was written about it before.
+    pub(crate) fn record(
+        &mut self,
+        world: &str,
+        prompt: &str,
+        collider_quality: ColliderQuality,
+        options: &ModelOptions,
+        concept: Option<&ConceptArt>,
+        spawn_location: &SpawnLocation,
+        remote: RemoteJob,
+    ) {
+        self.insert(JournalEntry {
+            world: world.to_string(),
+            prompt: prompt.to_string(),
+            collider_quality,
+            options: options.clone(),
+            concept: concept.cloned(),
+            transform: spawn_location.transform.into(),
+            remote,
+        });
+        self.save();
+    }
+
+    fn insert(&mut self, entry: JournalEntry) {
+        self.entries
+            .retain(|existing| existing.remote.first_task_id() != entry.remote.first_task_id());
+        self.entries.push(entry);
+    }
+
+    /// Forgets a job that finished, failed or was cancelled.
+    pub(crate) fn remove(&mut self, first_task_id: &str) {
+        let len = self.entries.len();
+        self.entries
+            .retain(|entry| entry.remote.first_task_id() != first_task_id);
+        if self.entries.len() != len {
+            self.save();
+        }
+    }
+
+    /// Writes the journal. It is small, so this happens right away instead of in a task.
+    fn save(&self) {
+        if let Err(err) = self.try_save() {
+            error!(?err, "failed to save generation journal");
+        }
+    }
+
+    fn try_save(&self) -> Result<()> {
+        let path = Path::new(JOURNAL_PATH);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).with_context(|| format!("failed to create {dir:?}"))?;
+        }
+        let json =
+            serde_json::to_vec_pretty(self).context("failed to serialize generation journal")?;
+        // Write to a temporary file first, so that a crash never leaves a half-written journal.
+        let partial_path = path.with_extension("json.partial");
+        fs::write(&partial_path, json)
+            .with_context(|| format!("failed to write {partial_path:?}"))?;
+        fs::rename(&partial_path, path).with_context(|| format!("failed to move {path:?}"))?;
+        Ok(())
+    }
+}
+
+fn load_generation_journal(mut commands: Commands) {
+    let journal = match fs::read(JOURNAL_PATH) {
+        Ok(bytes) => serde_json::from_slice(&bytes).unwrap_or_else(|err| {
+            error!(
+                ?err,
+                "generation journal is corrupt; running Meshy tasks are lost"
+            );
+            GenerationJournal::default()
+        }),
+        Err(_) => GenerationJournal::default(),
+    };
+    commands.insert_resource(journal);
+}
+
+/// Queues the jobs that were still running when the player last left this world. Jobs that
+/// Meshy finished meanwhile are only downloaded.
+fn resume_journaled_jobs(
+    journal: Res<GenerationJournal>,
+    world: Res<GenerationPrompt>,
+    mut queue: ResMut<GenerationQueue>,
+    time: Res<Time<Real>>,
+) {
+    for entry in journal
+        .entries
+        .iter()
+        .filter(|entry| entry.world == world.0)
+    {
+        info!(prompt = entry.prompt, "resuming Meshy generation");
+        let id = queue.push(
+            entry.prompt.clone(),
+            entry.collider_quality,
+            entry.options.clone(),
+            entry.concept.clone(),
+            SpawnLocation {
+                transform: entry.transform.into(),
+            },
+            // The credits for these are spent already, so don't keep the player waiting on them.
+            JobPriority::High,
+            time.elapsed(),
+        );
+        queue.set_remote(id, Some(entry.remote.clone()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn later_stages_replace_earlier_ones() {
+        let entry = |remote| JournalEntry {
+            world: "a forest".to_string(),
+            prompt: "a mossy log".to_string(),
+            collider_quality: ColliderQuality::default(),
+            options: ModelOptions::default(),
+            concept: None,
+            transform: Transform::from_xyz(1.0, 2.0, 3.0).into(),
+            remote,
+        };
+        let mut journal = GenerationJournal::default();
+        journal.insert(entry(RemoteJob::Preview {
+            task_id: "preview".to_string(),
+        }));
+        journal.insert(entry(RemoteJob::Refine {
+            generation_id: "0".to_string(),
+            preview_task_id: "preview".to_string(),
+            preview_model: "models/generated/0/preview.glb".to_string(),
+            task_id: "refine".to_string(),
+        }));
+        assert_eq!(journal.entries.len(), 1);
+
+        let json = serde_json::to_string(&journal).unwrap();
+        let loaded: GenerationJournal = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.entries, journal.entries);
+    }
+}
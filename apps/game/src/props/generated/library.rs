@@ -0,0 +1,316 @@
+//! The library of every prop generated so far. Generating a prop takes minutes and costs money,
+//! so finished props are recorded in an index next to their models and can be spawned again
+//! from the library browser.
+
+use std::{
+    fs,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context as _, Result};
+use bevy::{
+    prelude::*,
+    tasks::{IoTaskPool, Task},
+};
+#[cfg(feature = "hot_patch")]
+use bevy_simple_subsecond_system::hot;
+use futures_lite::future;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    generate::{
+        generate_collider::ColliderQuality,
+        generate_model::{GeneratedModel, generation_dir, load_model},
+    },
+    props::generated::placement::{BeginPlacement, PlacementTarget},
+    screens::Screen,
+};
+
+const LIBRARY_INDEX_PATH: &str = "assets/models/generated/library.json";
+
+pub(super) fn plugin(app: &mut App) {
+    app.add_systems(Startup, load_prop_library);
+    app.add_systems(
+        Update,
+        finish_library_spawns.run_if(in_state(Screen::ProceduralGameplay)),
+    );
+}
+
+/// The index of all generated props, kept in sync with [`LIBRARY_INDEX_PATH`].
+#[derive(Resource, Serialize, Deserialize, Debug, Default)]
+pub(crate) struct PropLibrary {
+    entries: Vec<LibraryEntry>,
+}
+
+/// A generated prop in the library.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct LibraryEntry {
+    /// The name of the prop's folder in `assets/models/generated`.
+    pub(crate) generation_id: String,
+    pub(crate) prompt: String,
+    /// The asset path of the model.
+    pub(crate) model: String,
+    /// The asset path of the thumbnail, if Meshy rendered one.
+    pub(crate) thumbnail: Option<String>,
+    /// Width, height and depth of the prop in meters. `None` if the model could not be measured.
+    pub(crate) dimensions: Option<[f32; 3]>,
+    /// How many triangles the finest level of detail has.
+    pub(crate) triangles: usize,
+    pub(crate) collider_quality: ColliderQuality,
+    /// When the prop was generated, in seconds since the Unix epoch.
+    pub(crate) created_at: u64,
+}
+
+impl LibraryEntry {
+    pub(crate) fn new(
+        generation_id: String,
+        prompt: String,
+        model: &GeneratedModel,
+        dimensions: Option<Vec3>,
+        thumbnail: Option<String>,
+        collider_quality: ColliderQuality,
+    ) -> Self {
+        let created_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since_epoch| since_epoch.as_secs());
+        Self {
+            generation_id,
+            prompt,
+            model: model.path.clone(),
+            thumbnail,
+            dimensions: dimensions.map(Vec3::to_array),
+            triangles: model.triangles,
+            collider_quality,
+            created_at,
+        }
+    }
+
+    /// A one-line summary of the prop's size, polycount and date.
+    pub(crate) fn details(&self) -> String {
+        let size = match self.dimensions {
+            Some([width, height, depth]) => format!("{width:.2} x {height:.2} x {depth:.2} m"),
+            None => "unknown size".to_string(),
+        };
+        format!(
+            "{size}, {} triangles, generated {}",
+            self.triangles,
+            format_date(self.created_at)
+        )
+    }
+}
+
+impl PropLibrary {
+    pub(crate) fn get(&self, generation_id: &str) -> Option<&LibraryEntry> {
+        self.entries
+            .iter()
+            .find(|entry| entry.generation_id == generation_id)
+    }
+
+    /// The entries whose prompt contains every word of the query, newest first.
+    pub(crate) fn search(&self, query: &str) -> Vec<&LibraryEntry> {
+        let words = query
+            .split_whitespace()
+            .map(str::to_lowercase)
+            .collect::<Vec<_>>();
+        let mut matches = self
+            .entries
+            .iter()
+            .filter(|entry| {
+                let prompt = entry.prompt.to_lowercase();
+                words.iter().all(|word| prompt.contains(word.as_str()))
+            })
+            .collect::<Vec<_>>();
+        matches.sort_by_key(|entry| std::cmp::Reverse(entry.created_at));
+        matches
+    }
+
+    /// Adds a prop to the library, replacing its previous entry if it has one, and saves the index.
+    pub(crate) fn record(&mut self, entry: LibraryEntry) {
+        self.entries
+            .retain(|existing| existing.generation_id != entry.generation_id);
+        info!(
+            prompt = entry.prompt,
+            id = entry.generation_id,
+            "added prop to library"
+        );
+        self.entries.push(entry);
+        self.save();
+    }
+
+    /// Removes a prop from the library and deletes its folder. Props already spawned from it keep
+    /// their loaded model.
+    pub(crate) fn remove(&mut self, generation_id: &str) {
+        let Some(index) = self
+            .entries
+            .iter()
+            .position(|entry| entry.generation_id == generation_id)
+        else {
+            return;
+        };
+        let entry = self.entries.remove(index);
+        self.save();
+
+        let dir = generation_dir(&entry.generation_id);
+        match fs::remove_dir_all(&dir) {
+            Ok(()) => info!(prompt = entry.prompt, ?dir, "deleted prop from library"),
+            Err(err) => error!(?err, ?dir, "failed to delete generated prop"),
+        }
+    }
+
+    /// Writes the index. It is small, so this happens right away instead of in a task.
+    fn save(&self) {
+        if let Err(err) = self.try_save() {
+            error!(?err, "failed to save prop library");
+        }
+    }
+
+    fn try_save(&self) -> Result<()> {
+        let path = Path::new(LIBRARY_INDEX_PATH);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).with_context(|| format!("failed to create {dir:?}"))?;
+        }
+        let json = serde_json::to_vec_pretty(self).context("failed to serialize prop library")?;
+        // Write to a temporary file first, so that a crash never leaves a half-written index.
+        let partial_path = path.with_extension("json.partial");
+        fs::write(&partial_path, json)
+            .with_context(|| format!("failed to write {partial_path:?}"))?;
+        fs::rename(&partial_path, path).with_context(|| format!("failed to move {path:?}"))?;
+        Ok(())
+    }
+}
+
+fn load_prop_library(mut commands: Commands) {
+    let library = match fs::read(LIBRARY_INDEX_PATH) {
+        Ok(bytes) => serde_json::from_slice(&bytes).unwrap_or_else(|err| {
+            error!(
+                ?err,
+                "prop library index is corrupt; starting an empty library"
+            );
+            PropLibrary::default()
+        }),
+        Err(_) => PropLibrary::default(),
+    };
+    info!(props = library.entries.len(), "loaded prop library");
+    commands.insert_resource(library);
+}
+
+/// Loads a library prop's model and collider in the background. The prop can be placed once they
+/// are ready, which takes a moment unless its collider has to be decomposed again.
+pub(crate) fn spawn_from_library(commands: &mut Commands, entry: &LibraryEntry) {
+    let model = entry.model.clone();
+    let collider_quality = entry.collider_quality;
+    let task = IoTaskPool::get().spawn(async move { load_model(&model, collider_quality) });
+    commands.spawn((
+        Name::new(format!("Library Spawn {}", entry.prompt)),
+        LibrarySpawnTask {
+            prompt: entry.prompt.clone(),
+            collider_quality,
+            task,
+        },
+        StateScoped(Screen::ProceduralGameplay),
+    ));
+}
+
+#[derive(Component)]
+struct LibrarySpawnTask {
+    prompt: String,
+    collider_quality: ColliderQuality,
+    task: Task<Result<GeneratedModel>>,
+}
+
+#[cfg_attr(feature = "hot_patch", hot)]
+fn finish_library_spawns(
+    mut commands: Commands,
+    mut tasks: Query<(Entity, &mut LibrarySpawnTask)>,
+) {
+    for (entity, mut spawn) in &mut tasks {
+        let Some(result) = future::block_on(future::poll_once(&mut spawn.task)) else {
+            continue;
+        };
+        commands.entity(entity).despawn();
+        let model = match result {
+            Ok(model) => model,
+            Err(err) => {
+                error!(
+                    prompt = spawn.prompt,
+                    ?err,
+                    "failed to load prop from library"
+                );
+                continue;
+            }
+        };
+        commands.trigger(BeginPlacement(PlacementTarget::Model {
+            prompt: spawn.prompt.clone(),
+            collider_quality: spawn.collider_quality,
+            model,
+        }));
+    }
+}
+
+/// Formats seconds since the Unix epoch as a UTC date like "2024-03-09".
+fn format_date(unix_seconds: u64) -> String {
+    // Converts days since the epoch to a civil date, following
+    // https://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let days = (unix_seconds / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_from_march = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_from_march + 2) / 5 + 1;
+    let month = if month_from_march < 10 {
+        month_from_march + 3
+    } else {
+        month_from_march - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(generation_id: &str, prompt: &str, created_at: u64) -> LibraryEntry {
+        LibraryEntry {
+            generation_id: generation_id.to_string(),
+            prompt: prompt.to_string(),
+            model: format!("models/generated/{generation_id}/refined.glb"),
+            thumbnail: None,
+            dimensions: None,
+            triangles: 0,
+            collider_quality: ColliderQuality::default(),
+            created_at,
+        }
+    }
+
+    #[test]
+    fn search_matches_all_words_newest_first() {
+        let library = PropLibrary {
+            entries: vec![
+                entry("a", "A wooden chair", 10),
+                entry("b", "An iron lantern", 20),
+                entry("c", "A tall wooden chair with a cushion", 30),
+            ],
+        };
+        let ids = |query| {
+            library
+                .search(query)
+                .iter()
+                .map(|entry| entry.generation_id.as_str())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(ids(""), ["c", "b", "a"]);
+        assert_eq!(ids("WOODEN chair"), ["c", "a"]);
+        assert_eq!(ids("wooden lantern"), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn formats_dates() {
+        assert_eq!(format_date(0), "1970-01-01");
+        assert_eq!(format_date(951_782_400), "2000-02-29");
+        assert_eq!(format_date(1_700_000_000), "2023-11-14");
+    }
+}
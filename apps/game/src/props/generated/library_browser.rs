@@ -0,0 +1,274 @@
+//! An overlay to search the prop library, spawn props from it and delete the ones nobody wants.
+
+use std::any::TypeId;
+
+use bevy::{input::common_conditions::input_just_pressed, prelude::*, ui::Val::*};
+#[cfg(feature = "hot_patch")]
+use bevy_simple_subsecond_system::hot;
+use bevy_simple_text_input::{TextInput, TextInputValue};
+
+use crate::{
+    Pause,
+    gameplay::{
+        crosshair::CrosshairState,
+        player::{default_input::BlocksInput, dialogue::conversation::is_conversation_open},
+    },
+    props::generated::{
+        library::{LibraryEntry, PropLibrary, spawn_from_library},
+        queue_panel::generation_queue_closed,
+    },
+    screens::{Screen, procedural_gameplay::model_prompt_closed},
+    theme::{palette::SCREEN_BACKGROUND, widget},
+};
+
+/// How many matching props are listed at once. Searching narrows down the rest.
+const MAX_LISTED_PROPS: usize = 6;
+const THUMBNAIL_SIZE: f32 = 64.0;
+
+pub(super) fn plugin(app: &mut App) {
+    app.init_resource::<PropLibraryBrowser>();
+    app.add_systems(
+        Update,
+        (
+            open_prop_library.run_if(
+                prop_library_closed
+                    .and(model_prompt_closed)
+                    .and(generation_queue_closed)
+                    .and(not(is_conversation_open))
+                    .and(input_just_pressed(KeyCode::KeyL)),
+            ),
+            close_prop_library.run_if(
+                not(prop_library_closed)
+                    .and(input_just_pressed(KeyCode::Escape).or(close_requested)),
+            ),
+            list_library_entries,
+        )
+            .chain()
+            .run_if(in_state(Screen::ProceduralGameplay)),
+    );
+    app.add_systems(OnExit(Screen::ProceduralGameplay), close_prop_library);
+}
+
+pub(crate) fn prop_library_closed(browser: Res<PropLibraryBrowser>) -> bool {
+    browser.root.is_none()
+}
+
+fn close_requested(browser: Res<PropLibraryBrowser>) -> bool {
+    browser.close_requested
+}
+
+#[derive(Resource, Default)]
+pub(crate) struct PropLibraryBrowser {
+    root: Option<Entity>,
+    paused_game: bool,
+    /// Set by the buttons of the overlay, which can't close it themselves.
+    close_requested: bool,
+}
+
+#[derive(Component)]
+struct PropLibraryOverlay;
+
+#[derive(Component)]
+struct LibrarySearchInput;
+
+#[derive(Component)]
+struct LibraryEntryList;
+
+#[cfg_attr(feature = "hot_patch", hot)]
+fn open_prop_library(
+    mut commands: Commands,
+    mut browser: ResMut<PropLibraryBrowser>,
+    mut crosshair: Single<&mut CrosshairState>,
+    paused: Res<State<Pause>>,
+    mut next_pause: ResMut<NextState<Pause>>,
+    mut blocks_input: ResMut<BlocksInput>,
+) {
+    let root = commands
+        .spawn((
+            widget::ui_root("Prop Library"),
+            BackgroundColor(SCREEN_BACKGROUND.with_alpha(0.9)),
+            GlobalZIndex(3),
+            StateScoped(Screen::ProceduralGameplay),
+            PropLibraryOverlay,
+            children![
+                widget::header("Prop Library"),
+                (
+                    Name::new("Library Search Input"),
+                    Node {
+                        width: Px(520.0),
+                        ..default()
+                    },
+                    children![(TextInput, TextInputValue(String::new()), LibrarySearchInput)],
+                ),
+                (
+                    Name::new("Library Entries"),
+                    Node {
+                        flex_direction: FlexDirection::Column,
+                        row_gap: Px(10.0),
+                        ..default()
+                    },
+                    LibraryEntryList,
+                ),
+                widget::label_small("Type to search. Press Esc to close."),
+            ],
+        ))
+        .id();
+
+    // Spawning from the library is instant, so there is no reason to let the world run meanwhile.
+    *browser = PropLibraryBrowser {
+        root: Some(root),
+        paused_game: !paused.get().0,
+        close_requested: false,
+    };
+    if browser.paused_game {
+        next_pause.set(Pause(true));
+    }
+    let overlay_id = TypeId::of::<PropLibraryOverlay>();
+    blocks_input.insert(overlay_id);
+    crosshair.wants_free_cursor.insert(overlay_id);
+}
+
+#[cfg_attr(feature = "hot_patch", hot)]
+fn close_prop_library(
+    mut commands: Commands,
+    mut browser: ResMut<PropLibraryBrowser>,
+    crosshair: Option<Single<&mut CrosshairState>>,
+    mut next_pause: ResMut<NextState<Pause>>,
+    mut blocks_input: ResMut<BlocksInput>,
+    mut keys: ResMut<ButtonInput<KeyCode>>,
+) {
+    let Some(root) = browser.root.take() else {
+        return;
+    };
+    // Don't let the same press open the pause menu.
+    keys.clear_just_pressed(KeyCode::Escape);
+    commands.entity(root).try_despawn();
+    if browser.paused_game {
+        next_pause.set(Pause(false));
+    }
+    *browser = PropLibraryBrowser::default();
+
+    let overlay_id = TypeId::of::<PropLibraryOverlay>();
+    blocks_input.remove(&overlay_id);
+    if let Some(mut crosshair) = crosshair {
+        crosshair.wants_free_cursor.remove(&overlay_id);
+    }
+}
+
+/// Lists the props matching the search whenever it or the library changes.
+#[cfg_attr(feature = "hot_patch", hot)]
+fn list_library_entries(
+    mut commands: Commands,
+    library: Res<PropLibrary>,
+    lists: Query<(Entity, Ref<LibraryEntryList>)>,
+    searches: Query<Ref<TextInputValue>, With<LibrarySearchInput>>,
+    asset_server: Res<AssetServer>,
+) {
+    let (Ok((list, list_marker)), Ok(search)) = (lists.single(), searches.single()) else {
+        return;
+    };
+    if !(library.is_changed() || list_marker.is_added() || search.is_changed()) {
+        return;
+    }
+
+    commands.entity(list).despawn_related::<Children>();
+    let matches = library.search(&search.0);
+    if matches.is_empty() {
+        let text = if search.0.trim().is_empty() {
+            "No props generated yet. Press M to generate one."
+        } else {
+            "No props match your search."
+        };
+        commands.spawn((widget::label(text), ChildOf(list)));
+        return;
+    }
+    for entry in matches.iter().take(MAX_LISTED_PROPS) {
+        spawn_library_row(&mut commands, &asset_server, list, entry);
+    }
+    if matches.len() > MAX_LISTED_PROPS {
+        commands.spawn((
+            widget::label_small(format!(
+                "{} more, search to narrow them down.",
+                matches.len() - MAX_LISTED_PROPS
+            )),
+            ChildOf(list),
+        ));
+    }
+}
+
+fn spawn_library_row(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    list: Entity,
+    entry: &LibraryEntry,
+) {
+    let row = commands
+        .spawn((
+            Name::new(format!("Library Entry {}", entry.prompt)),
+            Node {
+                flex_direction: FlexDirection::Row,
+                align_items: AlignItems::Center,
+                column_gap: Px(16.0),
+                ..default()
+            },
+            ChildOf(list),
+        ))
+        .id();
+
+    let mut thumbnail = commands.spawn((
+        Name::new("Thumbnail"),
+        Node {
+            width: Px(THUMBNAIL_SIZE),
+            height: Px(THUMBNAIL_SIZE),
+            ..default()
+        },
+        BackgroundColor(Color::BLACK.with_alpha(0.4)),
+        ChildOf(row),
+    ));
+    if let Some(path) = &entry.thumbnail {
+        thumbnail.insert(ImageNode::new(asset_server.load(path.clone())));
+    }
+
+    commands.spawn((
+        Name::new("Description"),
+        Node {
+            width: Px(480.0),
+            flex_direction: FlexDirection::Column,
+            row_gap: Px(4.0),
+            ..default()
+        },
+        ChildOf(row),
+        children![
+            widget::label(entry.prompt.clone()),
+            widget::label_small(entry.details()),
+        ],
+    ));
+
+    let generation_id = entry.generation_id.clone();
+    commands.spawn((
+        widget::button_medium(
+            "Spawn",
+            move |_: Trigger<Pointer<Click>>,
+                  mut commands: Commands,
+                  mut browser: ResMut<PropLibraryBrowser>,
+                  library: Res<PropLibrary>| {
+                if let Some(entry) = library.get(&generation_id) {
+                    spawn_from_library(&mut commands, entry);
+                    browser.close_requested = true;
+                }
+            },
+        ),
+        ChildOf(row),
+    ));
+
+    let generation_id = entry.generation_id.clone();
+    commands.spawn((
+        widget::button_medium(
+            "Delete",
+            move |_: Trigger<Pointer<Click>>, mut library: ResMut<PropLibrary>| {
+                library.remove(&generation_id);
+            },
+        ),
+        ChildOf(row),
+    ));
+}
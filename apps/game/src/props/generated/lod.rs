@@ -0,0 +1,75 @@
+//! Levels of detail of generated props. The optimized model of a prop has a scene per level, each
+//! spawned as its own child that is only visible within its range of distances to the camera.
+
+use std::iter;
+
+use bevy::{prelude::*, render::view::VisibilityRange, scene::SceneInstanceReady};
+#[cfg(feature = "hot_patch")]
+use bevy_simple_subsecond_system::hot;
+
+pub(super) fn plugin(app: &mut App) {
+    app.add_observer(apply_visibility_range);
+}
+
+/// Distances to the camera at which a one meter prop switches to the next level of detail. Larger
+/// props switch proportionally later.
+const LOD_DISTANCES: [f32; 2] = [12.0, 30.0];
+/// How far levels of detail crossfade into each other, as a fraction of the switching distance.
+const CROSSFADE: f32 = 0.15;
+
+/// A level of detail of a generated prop. Its range is applied to the meshes of its scene once
+/// they have spawned.
+#[derive(Component, Debug, Clone)]
+pub(crate) struct PropLod(pub(crate) VisibilityRange);
+
+/// The visibility ranges of `count` levels of detail of a prop of the given size in meters.
+/// A single level has no range, as it should always be visible.
+pub(crate) fn lod_visibility_ranges(count: usize, size: f32) -> Vec<Option<VisibilityRange>> {
+    if count <= 1 {
+        return vec![None; count];
+    }
+    let switches = LOD_DISTANCES
+        .iter()
+        .take(count - 1)
+        .map(|distance| {
+            let distance = distance * size.max(1.0);
+            let fade = distance * CROSSFADE;
+            distance - fade..distance + fade
+        })
+        .collect::<Vec<_>>();
+
+    (0..count)
+        .map(|lod| {
+            let start_margin = lod
+                .checked_sub(1)
+                .and_then(|previous| switches.get(previous).cloned())
+                .unwrap_or(0.0..0.0);
+            let end_margin = switches.get(lod).cloned().unwrap_or(f32::MAX..f32::MAX);
+            Some(VisibilityRange {
+                start_margin,
+                end_margin,
+                use_aabb: false,
+            })
+        })
+        .collect()
+}
+
+#[cfg_attr(feature = "hot_patch", hot)]
+fn apply_visibility_range(
+    trigger: Trigger<SceneInstanceReady>,
+    lods: Query<&PropLod>,
+    children: Query<&Children>,
+    meshes: Query<(), With<Mesh3d>>,
+    mut commands: Commands,
+) {
+    let root = trigger.target();
+    let Ok(lod) = lods.get(root) else {
+        return;
+    };
+    for mesh in iter::once(root)
+        .chain(children.iter_descendants(root))
+        .filter(|entity| meshes.contains(*entity))
+    {
+        commands.entity(mesh).insert(lod.0.clone());
+    }
+}
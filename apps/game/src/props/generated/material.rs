@@ -0,0 +1,325 @@
+//! The material a generated prop is made of, which decides how it behaves physically and how it
+//! sounds when picked up or bumped into something.
+
+use avian3d::prelude::*;
+use bevy::prelude::*;
+#[cfg(feature = "hot_patch")]
+use bevy_simple_subsecond_system::hot;
+
+use crate::gameplay::player::pickup::sound::PropSoundPool;
+
+pub(super) fn plugin(app: &mut App) {
+    app.register_type::<PropMaterial>();
+    app.add_observer(apply_surface_properties);
+}
+
+#[derive(Component, Reflect, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[reflect(Component)]
+pub(crate) enum PropMaterial {
+    #[default]
+    Wood,
+    Metal,
+    Stone,
+    Glass,
+    Cloth,
+    Plastic,
+    Rubber,
+    Paper,
+}
+
+/// Words naming a material outright, like "iron" in "an iron lantern". These win over object hints.
+const MATERIAL_WORDS: &[(PropMaterial, &[&str])] = &[
+    (
+        PropMaterial::Wood,
+        &[
+            "wood", "oak", "pine", "maple", "timber", "plank", "bamboo", "wicker",
+        ],
+    ),
+    (
+        PropMaterial::Metal,
+        &[
+            "metal",
+            "iron",
+            "steel",
+            "copper",
+            "brass",
+            "bronze",
+            "gold",
+            "silver",
+            "tin",
+            "chrome",
+            "aluminium",
+            "aluminum",
+        ],
+    ),
+    (
+        PropMaterial::Stone,
+        &[
+            "stone", "rock", "marble", "granite", "concrete", "brick", "clay", "ceramic",
+        ],
+    ),
+    (PropMaterial::Glass, &["glass", "crystal", "porcelain"]),
+    (
+        PropMaterial::Cloth,
+        &[
+            "cloth", "fabric", "wool", "cotton", "leather", "silk", "plush", "fur",
+        ],
+    ),
+    (PropMaterial::Plastic, &["plastic", "vinyl", "acrylic"]),
+    (PropMaterial::Rubber, &["rubber", "latex", "foam"]),
+    (PropMaterial::Paper, &["paper", "cardboard", "carton"]),
+];
+
+/// Endings a word may have and still match a keyword, like "boxes" or "wooden". Keywords only
+/// match whole words otherwise, so "tiny" isn't made of tin.
+const KEYWORD_SUFFIXES: &[&str] = &["", "s", "es", "en"];
+
+/// Objects that are usually made of a certain material.
+const OBJECT_HINTS: &[(PropMaterial, &[&str])] = &[
+    (
+        PropMaterial::Wood,
+        &[
+            "chair", "table", "barrel", "crate", "log", "bench", "shelf", "stool", "door",
+            "cabinet", "branch", "boat", "cart", "carriage", "wagon",
+        ],
+    ),
+    (
+        PropMaterial::Metal,
+        &[
+            "anvil", "sword", "shield", "armor", "armour", "helmet", "pan", "pot", "kettle", "can",
+            "robot", "key", "bell", "lantern", "engine", "gear", "hammer", "axe", "hatchet", "car",
+            "bike",
+        ],
+    ),
+    (
+        PropMaterial::Stone,
+        &[
+            "statue",
+            "boulder",
+            "pillar",
+            "column",
+            "gravestone",
+            "tombstone",
+            "vase",
+            "brick",
+        ],
+    ),
+    (
+        PropMaterial::Glass,
+        &[
+            "bottle", "jar", "window", "mirror", "lens", "orb", "potion", "goblet",
+        ],
+    ),
+    (
+        PropMaterial::Cloth,
+        &[
+            "pillow", "cushion", "teddy", "bag", "sack", "rug", "carpet", "blanket", "hat",
+            "shirt", "doll", "feather", "scarf",
+        ],
+    ),
+    (
+        PropMaterial::Plastic,
+        &[
+            "toy",
+            "bucket",
+            "phone",
+            "controller",
+            "cone",
+            "lego",
+            "keyboard",
+        ],
+    ),
+    (
+        PropMaterial::Rubber,
+        &["ball", "tire", "tyre", "duck", "balloon"],
+    ),
+    (
+        PropMaterial::Paper,
+        &[
+            "book",
+            "box",
+            "letter",
+            "map",
+            "scroll",
+            "newspaper",
+            "lampshade",
+        ],
+    ),
+];
+
+impl PropMaterial {
+    /// Infers the material from the prompt a prop was generated from.
+    pub(crate) fn from_prompt(prompt: &str) -> Self {
+        let prompt = prompt.to_lowercase();
+        let prompt_words = prompt
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .collect::<Vec<_>>();
+        let find = |table: &[(PropMaterial, &[&str])]| {
+            // The first match in the prompt wins, so "a wooden box with metal hinges" is wooden.
+            prompt_words.iter().find_map(|word| {
+                // Of the keywords a word matches, the longest one is the most specific.
+                table
+                    .iter()
+                    .flat_map(|(material, keywords)| {
+                        keywords.iter().map(move |keyword| (*material, *keyword))
+                    })
+                    .filter(|(_, keyword)| {
+                        word.strip_prefix(keyword)
+                            .is_some_and(|suffix| KEYWORD_SUFFIXES.contains(&suffix))
+                    })
+                    .max_by_key(|(_, keyword)| keyword.len())
+                    .map(|(material, _)| material)
+            })
+        };
+        find(MATERIAL_WORDS)
+            .or_else(|| find(OBJECT_HINTS))
+            .unwrap_or_default()
+    }
+
+    /// Density in kg/m³. Generated meshes are solid while real objects are often hollow, so these
+    /// are somewhat lower than the real-world values.
+    pub(crate) fn density(self) -> f32 {
+        match self {
+            Self::Wood => 500.0,
+            Self::Metal => 4_000.0,
+            Self::Stone => 2_000.0,
+            Self::Glass => 1_500.0,
+            Self::Cloth => 150.0,
+            Self::Plastic => 400.0,
+            Self::Rubber => 600.0,
+            Self::Paper => 250.0,
+        }
+    }
+
+    pub(crate) fn friction(self) -> Friction {
+        let coefficient = match self {
+            Self::Wood => 0.5,
+            Self::Metal => 0.35,
+            Self::Stone => 0.65,
+            Self::Glass => 0.25,
+            Self::Cloth => 0.9,
+            Self::Plastic => 0.35,
+            Self::Rubber => 1.0,
+            Self::Paper => 0.6,
+        };
+        Friction::new(coefficient)
+    }
+
+    pub(crate) fn restitution(self) -> Restitution {
+        let coefficient = match self {
+            Self::Wood => 0.2,
+            Self::Metal => 0.15,
+            Self::Stone => 0.05,
+            Self::Glass => 0.25,
+            Self::Cloth => 0.0,
+            Self::Plastic => 0.4,
+            Self::Rubber => 0.8,
+            Self::Paper => 0.05,
+        };
+        Restitution::new(coefficient)
+    }
+
+    /// How the prop sounds when it is picked up.
+    pub(crate) fn pickup_sound(self) -> PropSound {
+        let (speed, volume) = match self {
+            Self::Metal | Self::Stone => (0.8, 1.2),
+            Self::Cloth | Self::Paper => (1.4, 0.5),
+            _ => (1.0, 1.0),
+        };
+        PropSound {
+            pool: PropSoundPool::Scuffs,
+            speed,
+            volume,
+        }
+    }
+
+    /// How the prop sounds when it hits something.
+    pub(crate) fn impact_sound(self) -> PropSound {
+        let (pool, speed, volume) = match self {
+            Self::Wood => (PropSoundPool::Landing, 1.1, 1.0),
+            Self::Metal => (PropSoundPool::Landing, 1.7, 1.2),
+            Self::Stone => (PropSoundPool::Landing, 0.8, 1.3),
+            Self::Glass => (PropSoundPool::Steps, 1.9, 0.9),
+            Self::Cloth => (PropSoundPool::Scuffs, 0.7, 0.4),
+            Self::Plastic => (PropSoundPool::Steps, 1.4, 0.8),
+            Self::Rubber => (PropSoundPool::Steps, 0.9, 0.7),
+            Self::Paper => (PropSoundPool::Scuffs, 1.3, 0.5),
+        };
+        PropSound {
+            pool,
+            speed,
+            volume,
+        }
+    }
+}
+
+/// A sound from one of the pickup sound pools, played at the given speed and volume.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct PropSound {
+    pub(crate) pool: PropSoundPool,
+    pub(crate) speed: f64,
+    pub(crate) volume: f32,
+}
+
+/// Friction and restitution on a rigid body apply to all of its colliders that don't override them.
+#[cfg_attr(feature = "hot_patch", hot)]
+fn apply_surface_properties(
+    trigger: Trigger<OnAdd, PropMaterial>,
+    materials: Query<&PropMaterial>,
+    mut commands: Commands,
+) {
+    let Ok(material) = materials.get(trigger.target()) else {
+        return;
+    };
+    commands
+        .entity(trigger.target())
+        .insert((material.friction(), material.restitution()));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn infers_material_from_prompt() {
+        let cases = [
+            ("a wooden box with metal hinges", PropMaterial::Wood),
+            ("an iron lantern", PropMaterial::Metal),
+            ("two golden goblets", PropMaterial::Metal),
+            ("a stack of boxes", PropMaterial::Paper),
+            ("a red potion", PropMaterial::Glass),
+            ("a rusty pot", PropMaterial::Metal),
+            ("a keyboard", PropMaterial::Plastic),
+            ("a persian carpet", PropMaterial::Cloth),
+            ("a royal carriage", PropMaterial::Wood),
+            ("a maple leaf", PropMaterial::Wood),
+            ("a hatchet", PropMaterial::Metal),
+            ("a man's hat", PropMaterial::Cloth),
+            ("a mysterious thing", PropMaterial::Wood),
+        ];
+        for (prompt, material) in cases {
+            assert_eq!(PropMaterial::from_prompt(prompt), material, "{prompt}");
+        }
+    }
+
+    #[test]
+    fn keywords_only_match_whole_words() {
+        let cases = [
+            "a tiny wooden chair",
+            "a candle",
+            "a panda",
+            "a wall panel",
+            "a maple syrup",
+            "potions",
+        ];
+        for prompt in cases {
+            assert_ne!(
+                PropMaterial::from_prompt(prompt),
+                PropMaterial::Metal,
+                "{prompt}"
+            );
+        }
+        assert_ne!(PropMaterial::from_prompt("a maple"), PropMaterial::Paper);
+    }
+}
@@ -0,0 +1,30 @@
+//! Props generated from a text prompt at runtime. Unlike the props placed in TrenchBroom, we know
+//! nothing about them but the prompt, so their physical properties are inferred from it.
+
+use bevy::prelude::*;
+
+pub(crate) mod edit;
+pub(crate) mod journal;
+pub(crate) mod library;
+pub(crate) mod library_browser;
+pub(crate) mod lod;
+pub(crate) mod material;
+pub(crate) mod placement;
+pub(crate) mod queue;
+pub(crate) mod queue_panel;
+pub(crate) mod size;
+pub(crate) mod spawn;
+
+pub(super) fn plugin(app: &mut App) {
+    app.add_plugins((
+        edit::plugin,
+        journal::plugin,
+        library::plugin,
+        library_browser::plugin,
+        lod::plugin,
+        material::plugin,
+        placement::plugin,
+        queue::plugin,
+        queue_panel::plugin,
+    ));
+}
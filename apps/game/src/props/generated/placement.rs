@@ -0,0 +1,511 @@
+//! Placing generated props. A translucent ghost of the prop follows the crosshair over whatever
+//! surface it hits, and can be rotated, scaled and snapped to a grid before the prop is placed.
+//! The same mode moves props that are already in the world.
+
+use std::f32::consts::PI;
+
+use avian3d::prelude::*;
+use bevy::{
+    input::{
+        common_conditions::{input_just_pressed, input_just_released},
+        mouse::AccumulatedMouseScroll,
+    },
+    pbr::NotShadowCaster,
+    prelude::*,
+    scene::SceneInstanceReady,
+    ui::Val::*,
+};
+#[cfg(feature = "hot_patch")]
+use bevy_simple_subsecond_system::hot;
+
+use crate::{
+    Pause,
+    gameplay::{
+        player::{camera::PlayerCamera, default_input::BlocksInput},
+        procedural_level::sample_terrain_height,
+    },
+    generate::{
+        generate_collider::ColliderQuality, generate_concept::ConceptArt,
+        generate_model::GeneratedModel, model_options::ModelOptions,
+    },
+    props::generated::{
+        edit::{EditHistory, PropRecord, WorldEdit, WorldEditor},
+        size::PropSize,
+        spawn::{GeneratedProp, GeneratedPropModel, PropId, fit_model},
+    },
+    screens::Screen,
+    theme::{palette::SCREEN_BACKGROUND, widget},
+    third_party::avian3d::CollisionLayer,
+};
+
+/// How far from the camera props can be placed.
+const MAX_PLACEMENT_DISTANCE: f32 = 15.0;
+/// How far from the camera a prop can be to be picked up for moving.
+const MAX_MOVE_DISTANCE: f32 = 5.0;
+/// The spacing of the grid props snap to.
+const GRID_SIZE: f32 = 0.25;
+/// How far one scroll step rotates the ghost, with and without snapping.
+const SNAP_ROTATION_STEP: f32 = PI / 12.0;
+const FREE_ROTATION_STEP: f32 = PI / 36.0;
+/// How much one scroll step with Shift held scales the ghost.
+const SCALE_STEP: f32 = 1.1;
+const SCALE_RANGE: (f32, f32) = (0.25, 4.0);
+
+pub(super) fn plugin(app: &mut App) {
+    app.init_resource::<PlacementSettings>();
+    app.add_systems(Startup, create_ghost_material);
+    app.add_observer(begin_placement);
+    app.add_observer(place_prop);
+    app.add_observer(make_ghost_translucent);
+    app.add_systems(
+        Update,
+        (
+            (
+                start_moving_prop.run_if(placement_inactive.and(input_just_pressed(KeyCode::KeyR))),
+                toggle_snapping.run_if(input_just_pressed(KeyCode::KeyG)),
+                rotate_and_scale_ghost,
+                confirm_placement.run_if(input_just_released(MouseButton::Left)),
+                cancel_placement.run_if(
+                    input_just_pressed(MouseButton::Right).or(input_just_pressed(KeyCode::Escape)),
+                ),
+            )
+                .run_if(in_state(Pause(false)).and(input_unblocked)),
+            move_ghost,
+        )
+            .chain()
+            .run_if(in_state(Screen::ProceduralGameplay)),
+    );
+}
+
+pub(crate) fn placement_inactive(ghosts: Query<(), With<PlacementGhost>>) -> bool {
+    ghosts.is_empty()
+}
+
+/// Placement is controlled with keys that mean something else while typing in an overlay.
+pub(crate) fn input_unblocked(blocks_input: Res<BlocksInput>) -> bool {
+    blocks_input.is_empty()
+}
+
+/// What is being placed.
+#[derive(Debug, Clone)]
+pub(crate) enum PlacementTarget {
+    /// A prop that will be generated from a prompt once it is placed.
+    Prompt {
+        prompt: String,
+        collider_quality: ColliderQuality,
+        options: ModelOptions,
+        /// The concept art the player approved, to model the prop from instead of its prompt.
+        concept: Option<ConceptArt>,
+    },
+    /// A model that is ready to spawn, e.g. one from the prop library.
+    Model {
+        prompt: String,
+        collider_quality: ColliderQuality,
+        model: GeneratedModel,
+    },
+    /// A generated prop that is already in the world.
+    Existing(Entity),
+}
+
+/// Starts placing a prop, replacing the current placement if there is one.
+#[derive(Event, Debug)]
+pub(crate) struct BeginPlacement(pub(crate) PlacementTarget);
+
+/// A prop was placed with the given transform. Props that still have to be generated are left to
+/// the observer that generates them.
+#[derive(Event, Debug)]
+pub(crate) struct PropPlaced {
+    pub(crate) target: PlacementTarget,
+    pub(crate) transform: Transform,
+}
+
+/// Whether placement snaps to the grid, which is kept between placements.
+#[derive(Resource, Debug)]
+struct PlacementSettings {
+    snap: bool,
+}
+
+impl Default for PlacementSettings {
+    fn default() -> Self {
+        Self { snap: true }
+    }
+}
+
+#[derive(Component, Debug)]
+pub(crate) struct PlacementGhost {
+    target: PlacementTarget,
+    yaw: f32,
+    scale: f32,
+    hint: Entity,
+}
+
+/// A scene of a ghost, whose materials are replaced by the ghost material once it has spawned.
+#[derive(Component)]
+struct GhostScene;
+
+#[derive(Component)]
+struct PlacementHint;
+
+#[derive(Resource)]
+struct GhostMaterial(Handle<StandardMaterial>);
+
+fn create_ghost_material(mut commands: Commands, mut materials: ResMut<Assets<StandardMaterial>>) {
+    commands.insert_resource(GhostMaterial(materials.add(StandardMaterial {
+        base_color: Color::srgba(0.45, 0.75, 1.0, 0.4),
+        alpha_mode: AlphaMode::Blend,
+        unlit: true,
+        ..default()
+    })));
+}
+
+fn placement_hint(snap: bool) -> String {
+    format!(
+        "Click to place, right click to cancel. Scroll to rotate, Shift + scroll to scale. \
+         G: snap to grid ({}).",
+        if snap { "on" } else { "off" }
+    )
+}
+
+#[cfg_attr(feature = "hot_patch", hot)]
+fn begin_placement(
+    trigger: Trigger<BeginPlacement>,
+    mut commands: Commands,
+    ghosts: Query<(Entity, &PlacementGhost)>,
+    props: Query<(&Transform, &Children), With<GeneratedProp>>,
+    model_parts: Query<(&SceneRoot, &Transform), With<GeneratedPropModel>>,
+    asset_server: Res<AssetServer>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    ghost_material: Res<GhostMaterial>,
+    settings: Res<PlacementSettings>,
+) {
+    for (entity, ghost) in &ghosts {
+        abandon_placement(&mut commands, entity, ghost);
+    }
+
+    let target = trigger.event().0.clone();
+    let (yaw, scale) = match &target {
+        PlacementTarget::Existing(prop) => {
+            let Ok((transform, _)) = props.get(*prop) else {
+                return;
+            };
+            (
+                transform.rotation.to_euler(EulerRot::YXZ).0,
+                transform.scale.x,
+            )
+        }
+        _ => (0.0, 1.0),
+    };
+
+    let hint = commands
+        .spawn((
+            Name::new("Placement Hint"),
+            Node {
+                position_type: PositionType::Absolute,
+                bottom: Px(40.0),
+                left: Percent(20.0),
+                width: Percent(60.0),
+                justify_content: JustifyContent::Center,
+                padding: UiRect::all(Px(10.0)),
+                ..default()
+            },
+            BackgroundColor(SCREEN_BACKGROUND.with_alpha(0.8)),
+            Pickable::IGNORE,
+            StateScoped(Screen::ProceduralGameplay),
+            children![(widget::label(placement_hint(settings.snap)), PlacementHint)],
+        ))
+        .id();
+    let ghost = commands
+        .spawn((
+            Name::new("Placement Ghost"),
+            Transform::default(),
+            // Hidden until it has moved to the crosshair.
+            Visibility::Hidden,
+            StateScoped(Screen::ProceduralGameplay),
+            PlacementGhost {
+                target: target.clone(),
+                yaw,
+                scale,
+                hint,
+            },
+        ))
+        .id();
+
+    match target {
+        PlacementTarget::Prompt { prompt, .. } => {
+            // There is no model yet, so show a box of the size the prop will have.
+            let size = PropSize::from_prompt(&prompt).meters;
+            commands.spawn((
+                Name::new("Ghost Box"),
+                Mesh3d(meshes.add(Cuboid::from_length(size))),
+                MeshMaterial3d(ghost_material.0.clone()),
+                NotShadowCaster,
+                Transform::from_xyz(0.0, size / 2.0, 0.0),
+                ChildOf(ghost),
+            ));
+        }
+        PlacementTarget::Model { prompt, model, .. } => {
+            // The coarsest level of detail is good enough for a translucent ghost.
+            let lod = model.lod_count.saturating_sub(1);
+            commands.spawn((
+                Name::new("Ghost Model"),
+                GhostScene,
+                SceneRoot(asset_server.load(format!("{}#Scene{lod}", model.path))),
+                fit_model(&model, &prompt).0,
+                ChildOf(ghost),
+            ));
+        }
+        PlacementTarget::Existing(prop) => {
+            let Ok((_, children)) = props.get(prop) else {
+                return;
+            };
+            // The levels of detail are spawned finest first.
+            if let Some((scene, transform)) = children
+                .iter()
+                .filter_map(|child| model_parts.get(child).ok())
+                .last()
+            {
+                commands.spawn((
+                    Name::new("Ghost Model"),
+                    GhostScene,
+                    scene.clone(),
+                    *transform,
+                    ChildOf(ghost),
+                ));
+            }
+            commands.entity(prop).insert(Visibility::Hidden);
+        }
+    }
+}
+
+#[cfg_attr(feature = "hot_patch", hot)]
+fn make_ghost_translucent(
+    trigger: Trigger<SceneInstanceReady>,
+    ghost_scenes: Query<(), With<GhostScene>>,
+    children: Query<&Children>,
+    meshes: Query<(), With<MeshMaterial3d<StandardMaterial>>>,
+    ghost_material: Res<GhostMaterial>,
+    mut commands: Commands,
+) {
+    let root = trigger.target();
+    if !ghost_scenes.contains(root) {
+        return;
+    }
+    for mesh in children
+        .iter_descendants(root)
+        .filter(|entity| meshes.contains(*entity))
+    {
+        commands
+            .entity(mesh)
+            .insert((MeshMaterial3d(ghost_material.0.clone()), NotShadowCaster));
+    }
+}
+
+/// Picks up the generated prop under the crosshair for moving.
+#[cfg_attr(feature = "hot_patch", hot)]
+fn start_moving_prop(
+    mut commands: Commands,
+    camera: Single<&GlobalTransform, With<PlayerCamera>>,
+    spatial_query: SpatialQuery,
+    colliders: Query<&ColliderOf>,
+    props: Query<(), With<GeneratedProp>>,
+) {
+    let camera_transform = camera.compute_transform();
+    let Some(hit) = spatial_query.cast_ray(
+        camera_transform.translation,
+        camera_transform.forward(),
+        MAX_MOVE_DISTANCE,
+        true,
+        &SpatialQueryFilter::from_mask(CollisionLayer::Prop),
+    ) else {
+        return;
+    };
+    let Ok(&ColliderOf { body }) = colliders.get(hit.entity) else {
+        return;
+    };
+    if props.contains(body) {
+        commands.trigger(BeginPlacement(PlacementTarget::Existing(body)));
+    }
+}
+
+fn toggle_snapping(
+    mut settings: ResMut<PlacementSettings>,
+    mut hints: Query<&mut Text, With<PlacementHint>>,
+) {
+    settings.snap = !settings.snap;
+    for mut hint in &mut hints {
+        hint.0 = placement_hint(settings.snap);
+    }
+}
+
+#[cfg_attr(feature = "hot_patch", hot)]
+fn rotate_and_scale_ghost(
+    mut ghost: Single<&mut PlacementGhost>,
+    scroll: Res<AccumulatedMouseScroll>,
+    keys: Res<ButtonInput<KeyCode>>,
+    settings: Res<PlacementSettings>,
+) {
+    if scroll.delta.y == 0.0 {
+        return;
+    }
+    // Mice and touchpads scroll by very different amounts, so only the direction counts.
+    let steps = scroll.delta.y.signum();
+    if keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]) {
+        ghost.scale = (ghost.scale * SCALE_STEP.powf(steps)).clamp(SCALE_RANGE.0, SCALE_RANGE.1);
+    } else if settings.snap {
+        let yaw = ghost.yaw + steps * SNAP_ROTATION_STEP;
+        ghost.yaw = (yaw / SNAP_ROTATION_STEP).round() * SNAP_ROTATION_STEP;
+    } else {
+        ghost.yaw += steps * FREE_ROTATION_STEP;
+    }
+}
+
+/// Moves the ghost to the surface under the crosshair.
+#[cfg_attr(feature = "hot_patch", hot)]
+fn move_ghost(
+    ghost: Option<Single<(&PlacementGhost, &mut Transform, &mut Visibility)>>,
+    camera: Single<&GlobalTransform, With<PlayerCamera>>,
+    spatial_query: SpatialQuery,
+    colliders: Query<(Entity, &ColliderOf)>,
+    settings: Res<PlacementSettings>,
+) {
+    let Some(ghost) = ghost else {
+        return;
+    };
+    let (ghost, mut transform, mut visibility) = ghost.into_inner();
+
+    // Don't place a prop that is being moved on top of itself.
+    let excluded = match ghost.target {
+        PlacementTarget::Existing(prop) => colliders
+            .iter()
+            .filter(|(_, collider_of)| collider_of.body == prop)
+            .map(|(collider, _)| collider)
+            .collect(),
+        _ => Vec::new(),
+    };
+    let camera_transform = camera.compute_transform();
+    let origin = camera_transform.translation;
+    let direction = camera_transform.forward();
+    let mut position = match spatial_query.cast_ray(
+        origin,
+        direction,
+        MAX_PLACEMENT_DISTANCE,
+        true,
+        &SpatialQueryFilter::from_mask([CollisionLayer::Default, CollisionLayer::Prop])
+            .with_excluded_entities(excluded),
+    ) {
+        Some(hit) => origin + direction * hit.distance,
+        None => {
+            // Looking at the sky, so place the prop on the terrain as far away as allowed.
+            let position = origin + direction * MAX_PLACEMENT_DISTANCE;
+            position.with_y(sample_terrain_height(position.x, position.z))
+        }
+    };
+    if settings.snap {
+        position.x = (position.x / GRID_SIZE).round() * GRID_SIZE;
+        position.z = (position.z / GRID_SIZE).round() * GRID_SIZE;
+    }
+
+    *transform = Transform::from_translation(position)
+        .with_rotation(Quat::from_rotation_y(ghost.yaw))
+        .with_scale(Vec3::splat(ghost.scale));
+    *visibility = Visibility::Inherited;
+}
+
+#[cfg_attr(feature = "hot_patch", hot)]
+fn confirm_placement(
+    mut commands: Commands,
+    ghost: Option<Single<(Entity, &PlacementGhost, &Transform, &Visibility)>>,
+) {
+    let Some(ghost) = ghost else {
+        return;
+    };
+    let (entity, ghost, transform, visibility) = ghost.into_inner();
+    // The ghost hasn't found a spot yet.
+    if *visibility == Visibility::Hidden {
+        return;
+    }
+    commands.trigger(PropPlaced {
+        target: ghost.target.clone(),
+        transform: *transform,
+    });
+    end_placement(&mut commands, entity, ghost);
+}
+
+#[cfg_attr(feature = "hot_patch", hot)]
+fn cancel_placement(
+    mut commands: Commands,
+    ghost: Option<Single<(Entity, &PlacementGhost)>>,
+    mut keys: ResMut<ButtonInput<KeyCode>>,
+) {
+    let Some(ghost) = ghost else {
+        return;
+    };
+    let (entity, ghost) = ghost.into_inner();
+    // Don't let the same press open the pause menu.
+    keys.clear_just_pressed(KeyCode::Escape);
+    abandon_placement(&mut commands, entity, ghost);
+}
+
+/// Ends a placement without placing anything, so the approved concept art of a prompt is no
+/// longer needed either.
+fn abandon_placement(commands: &mut Commands, entity: Entity, ghost: &PlacementGhost) {
+    if let PlacementTarget::Prompt {
+        concept: Some(concept),
+        ..
+    } = &ghost.target
+    {
+        concept.delete();
+    }
+    end_placement(commands, entity, ghost);
+}
+
+/// Removes the ghost and shows the prop it was moving again, wherever it ended up.
+fn end_placement(commands: &mut Commands, entity: Entity, ghost: &PlacementGhost) {
+    commands.entity(entity).despawn();
+    commands.entity(ghost.hint).try_despawn();
+    if let PlacementTarget::Existing(prop) = ghost.target {
+        if let Ok(mut prop) = commands.get_entity(prop) {
+            prop.insert(Visibility::Inherited);
+        }
+    }
+}
+
+/// Spawns placed models and moves placed props. Prompts are placed by the generation pipeline.
+#[cfg_attr(feature = "hot_patch", hot)]
+fn place_prop(
+    trigger: Trigger<PropPlaced>,
+    mut editor: WorldEditor,
+    mut history: ResMut<EditHistory>,
+) {
+    let PropPlaced { target, transform } = trigger.event();
+    match target {
+        PlacementTarget::Prompt { .. } => {}
+        PlacementTarget::Model {
+            prompt,
+            collider_quality,
+            model,
+        } => {
+            let edit = WorldEdit::Spawn(PropRecord::new(
+                PropId::new(),
+                prompt,
+                model,
+                *collider_quality,
+                *transform,
+            ));
+            editor.apply(&edit);
+            history.push(edit);
+        }
+        PlacementTarget::Existing(prop) => {
+            let (Some(id), Some(from)) = (editor.id(*prop), editor.transform(*prop)) else {
+                return;
+            };
+            let edit = WorldEdit::SetTransform {
+                prop: id,
+                from: from.into(),
+                to: (*transform).into(),
+            };
+            editor.apply(&edit);
+            history.push(edit);
+        }
+    }
+}
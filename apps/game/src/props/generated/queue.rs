@@ -0,0 +1,409 @@
+//! The queue of props waiting to be generated. Meshy jobs take minutes and cost credits, so only a
+//! few of them run at once, and the rest wait their turn by priority.
+
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use crate::{
+    generate::{
+        generate_collider::ColliderQuality, generate_concept::ConceptArt,
+        model_options::ModelOptions,
+    },
+    props::generated::{journal::RemoteJob, spawn::SpawnLocation},
+    screens::Screen,
+};
+
+/// How many jobs run at once unless the player changes it.
+const DEFAULT_MAX_RUNNING_JOBS: usize = 2;
+/// How many jobs can run at once at most, to keep the player from burning through their credits.
+pub(crate) const MAX_RUNNING_JOBS_LIMIT: usize = 6;
+
+pub(super) fn plugin(app: &mut App) {
+    app.init_resource::<GenerationQueue>();
+    app.add_systems(OnExit(Screen::ProceduralGameplay), clear_generation_queue);
+}
+
+/// Identifies a job in the [`GenerationQueue`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) struct JobId(u64);
+
+/// Queued jobs with a higher priority start first. Jobs with the same priority start in the order
+/// they were queued.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub(crate) enum JobPriority {
+    Low,
+    #[default]
+    Normal,
+    High,
+}
+
+impl JobPriority {
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Self::Low => "Low",
+            Self::Normal => "Normal",
+            Self::High => "High",
+        }
+    }
+
+    pub(crate) fn next(self) -> Self {
+        match self {
+            Self::Low => Self::Normal,
+            Self::Normal => Self::High,
+            Self::High => Self::Low,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum JobStatus {
+    Queued,
+    /// Waiting for the untextured preview.
+    Previewing,
+    /// The prop is in the world with its preview, waiting for the textured model.
+    Refining,
+    /// Waiting for the model of the approved concept art, which has no preview.
+    Modeling,
+    Completed,
+    /// Why the job failed, in words the player understands.
+    Failed(String),
+    Cancelled,
+}
+
+impl JobStatus {
+    pub(crate) fn is_running(&self) -> bool {
+        matches!(self, Self::Previewing | Self::Refining | Self::Modeling)
+    }
+
+    pub(crate) fn is_finished(&self) -> bool {
+        matches!(self, Self::Completed | Self::Failed(_) | Self::Cancelled)
+    }
+
+    pub(crate) fn can_retry(&self) -> bool {
+        matches!(self, Self::Failed(_) | Self::Cancelled)
+    }
+}
+
+/// A prop to generate, and how far along it is.
+#[derive(Debug, Clone)]
+pub(crate) struct GenerationJob {
+    pub(crate) id: JobId,
+    pub(crate) prompt: String,
+    pub(crate) collider_quality: ColliderQuality,
+    pub(crate) options: ModelOptions,
+    /// The concept art the player approved, to model the prop from instead of its prompt.
+    pub(crate) concept: Option<ConceptArt>,
+    /// Where the player placed the prop.
+    pub(crate) spawn_location: SpawnLocation,
+    pub(crate) priority: JobPriority,
+    pub(crate) status: JobStatus,
+    /// The Meshy task to continue from instead of starting a new one, e.g. one that was running
+    /// when the game quit or when the connection to Meshy dropped.
+    pub(crate) remote: Option<RemoteJob>,
+    /// The prop the job spawned with its preview, while the textured model is still to come.
+    prop: Option<Entity>,
+    /// When the job was queued, started and finished, in real time since startup.
+    queued_at: Duration,
+    started_at: Option<Duration>,
+    finished_at: Option<Duration>,
+}
+
+impl GenerationJob {
+    /// How long the job has been waiting if it hasn't started, or how long it ran otherwise.
+    pub(crate) fn elapsed(&self, now: Duration) -> Duration {
+        let start = self.started_at.unwrap_or(self.queued_at);
+        self.finished_at.unwrap_or(now).saturating_sub(start)
+    }
+
+    /// The status of the job once it starts.
+    fn first_status(&self) -> JobStatus {
+        if self.concept.is_some() || matches!(self.remote, Some(RemoteJob::ImageTo3d { .. })) {
+            JobStatus::Modeling
+        } else {
+            JobStatus::Previewing
+        }
+    }
+}
+
+/// The props that are being generated or waiting to be, along with those that finished recently.
+#[derive(Resource, Debug)]
+pub(crate) struct GenerationQueue {
+    /// How many jobs run at once.
+    pub(crate) max_running: usize,
+    jobs: Vec<GenerationJob>,
+    next_id: u64,
+}
+
+impl Default for GenerationQueue {
+    fn default() -> Self {
+        Self {
+            max_running: DEFAULT_MAX_RUNNING_JOBS,
+            jobs: Vec::new(),
+            next_id: 0,
+        }
+    }
+}
+
+impl GenerationQueue {
+    /// All jobs, oldest first.
+    pub(crate) fn jobs(&self) -> &[GenerationJob] {
+        &self.jobs
+    }
+
+    pub(crate) fn get(&self, id: JobId) -> Option<&GenerationJob> {
+        self.jobs.iter().find(|job| job.id == id)
+    }
+
+    fn get_mut(&mut self, id: JobId) -> Option<&mut GenerationJob> {
+        self.jobs.iter_mut().find(|job| job.id == id)
+    }
+
+    pub(crate) fn push(
+        &mut self,
+        prompt: String,
+        collider_quality: ColliderQuality,
+        options: ModelOptions,
+        concept: Option<ConceptArt>,
+        spawn_location: SpawnLocation,
+        priority: JobPriority,
+        now: Duration,
+    ) -> JobId {
+        let id = JobId(self.next_id);
+        self.next_id += 1;
+        info!(prompt, ?priority, "queued prop generation");
+        self.jobs.push(GenerationJob {
+            id,
+            prompt,
+            collider_quality,
+            options,
+            concept,
+            spawn_location,
+            priority,
+            status: JobStatus::Queued,
+            remote: None,
+            prop: None,
+            queued_at: now,
+            started_at: None,
+            finished_at: None,
+        });
+        id
+    }
+
+    pub(crate) fn running(&self) -> usize {
+        self.jobs
+            .iter()
+            .filter(|job| job.status.is_running())
+            .count()
+    }
+
+    /// Starts the queued job that is next in line, if fewer than [`Self::max_running`] jobs are
+    /// running.
+    pub(crate) fn start_next(&mut self, now: Duration) -> Option<GenerationJob> {
+        if self.running() >= self.max_running {
+            return None;
+        }
+        let job = self
+            .jobs
+            .iter_mut()
+            .filter(|job| job.status == JobStatus::Queued)
+            // Jobs are stored oldest first, and `max_by_key` returns the last of equal elements.
+            .rev()
+            .max_by_key(|job| job.priority)?;
+        job.status = job.first_status();
+        job.started_at = Some(now);
+        Some(job.clone())
+    }
+
+    /// Moves a running job on to its next status.
+    pub(crate) fn set_status(&mut self, id: JobId, status: JobStatus, now: Duration) {
+        let Some(job) = self.get_mut(id) else {
+            return;
+        };
+        // A job that was cancelled meanwhile stays cancelled.
+        if !job.status.is_running() {
+            return;
+        }
+        if status.is_finished() {
+            job.finished_at = Some(now);
+        }
+        job.status = status;
+    }
+
+    /// Sets the Meshy task a job continues from when it starts, e.g. one that was running when the
+    /// game quit.
+    pub(crate) fn set_remote(&mut self, id: JobId, remote: Option<RemoteJob>) {
+        if let Some(job) = self.get_mut(id) {
+            job.remote = remote;
+        }
+    }
+
+    /// Remembers the prop a job spawned with its preview.
+    pub(crate) fn set_prop(&mut self, id: JobId, prop: Entity) {
+        if let Some(job) = self.get_mut(id) {
+            job.prop = Some(prop);
+        }
+    }
+
+    /// Cancels a job that hasn't finished. A running job stops waiting for Meshy, but keeps the
+    /// preview it already spawned.
+    pub(crate) fn cancel(&mut self, id: JobId, now: Duration) {
+        if let Some(job) = self.get_mut(id).filter(|job| !job.status.is_finished()) {
+            info!(prompt = job.prompt, "cancelled prop generation");
+            job.status = JobStatus::Cancelled;
+            job.finished_at = Some(now);
+        }
+    }
+
+    /// Queues a failed or cancelled job again, at the back of its priority. It continues from its
+    /// Meshy task if it still has one, and starts over otherwise. Returns the prop the job left in
+    /// the world with its preview, if any, which has to be despawned as the retried job spawns a
+    /// prop of its own.
+    #[must_use]
+    pub(crate) fn retry(&mut self, id: JobId, now: Duration) -> Option<Entity> {
+        let index = self
+            .jobs
+            .iter()
+            .position(|job| job.id == id && job.status.can_retry())?;
+        let mut job = self.jobs.remove(index);
+        info!(prompt = job.prompt, "retrying prop generation");
+        job.status = JobStatus::Queued;
+        job.queued_at = now;
+        job.started_at = None;
+        job.finished_at = None;
+        let prop = job.prop.take();
+        self.jobs.push(job);
+        prop
+    }
+
+    pub(crate) fn set_priority(&mut self, id: JobId, priority: JobPriority) {
+        if let Some(job) = self.get_mut(id) {
+            job.priority = priority;
+        }
+    }
+
+    /// Forgets the jobs that completed. Failed and cancelled ones are kept so they can be retried.
+    pub(crate) fn clear_completed(&mut self) {
+        self.jobs.retain(|job| job.status != JobStatus::Completed);
+    }
+}
+
+/// Jobs belong to the world they were placed in.
+fn clear_generation_queue(mut queue: ResMut<GenerationQueue>) {
+    queue.jobs.clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn push(queue: &mut GenerationQueue, prompt: &str, priority: JobPriority) -> JobId {
+        queue.push(
+            prompt.to_string(),
+            ColliderQuality::default(),
+            ModelOptions::default(),
+            None,
+            SpawnLocation {
+                transform: Transform::default(),
+            },
+            priority,
+            Duration::ZERO,
+        )
+    }
+
+    fn start_next(queue: &mut GenerationQueue) -> Option<String> {
+        queue.start_next(Duration::ZERO).map(|job| job.prompt)
+    }
+
+    #[test]
+    fn starts_jobs_by_priority_up_to_the_limit() {
+        let mut queue = GenerationQueue {
+            max_running: 2,
+            ..default()
+        };
+        push(&mut queue, "chair", JobPriority::Normal);
+        push(&mut queue, "table", JobPriority::Low);
+        let lamp = push(&mut queue, "lamp", JobPriority::High);
+        push(&mut queue, "rug", JobPriority::Normal);
+
+        assert_eq!(start_next(&mut queue).as_deref(), Some("lamp"));
+        assert_eq!(start_next(&mut queue).as_deref(), Some("chair"));
+        assert_eq!(start_next(&mut queue), None);
+
+        queue.set_status(lamp, JobStatus::Failed("oops".into()), Duration::ZERO);
+        assert_eq!(start_next(&mut queue).as_deref(), Some("rug"));
+
+        queue.max_running = 3;
+        assert_eq!(queue.retry(lamp, Duration::ZERO), None);
+        assert_eq!(start_next(&mut queue).as_deref(), Some("lamp"));
+        assert_eq!(queue.running(), 3);
+    }
+
+    #[test]
+    fn cancelled_jobs_stay_cancelled() {
+        let mut queue = GenerationQueue::default();
+        let chair = push(&mut queue, "chair", JobPriority::Normal);
+        start_next(&mut queue);
+        queue.cancel(chair, Duration::ZERO);
+        queue.set_status(chair, JobStatus::Completed, Duration::ZERO);
+        assert_eq!(queue.get(chair).unwrap().status, JobStatus::Cancelled);
+
+        queue.clear_completed();
+        assert!(queue.get(chair).is_some());
+    }
+
+    #[test]
+    fn concept_art_jobs_have_no_preview() {
+        let mut queue = GenerationQueue::default();
+        queue.push(
+            "chair".to_string(),
+            ColliderQuality::default(),
+            ModelOptions::default(),
+            Some(ConceptArt {
+                path: "models/generated/concepts/chair.png".to_string(),
+            }),
+            SpawnLocation {
+                transform: Transform::default(),
+            },
+            JobPriority::Normal,
+            Duration::ZERO,
+        );
+        let job = queue.start_next(Duration::ZERO).unwrap();
+        assert_eq!(job.status, JobStatus::Modeling);
+        assert_eq!(queue.running(), 1);
+    }
+
+    #[test]
+    fn retrying_hands_back_the_preview_prop() {
+        let mut queue = GenerationQueue::default();
+        let chair = push(&mut queue, "chair", JobPriority::Normal);
+        start_next(&mut queue);
+        let prop = Entity::from_raw(7);
+        queue.set_prop(chair, prop);
+        queue.set_status(chair, JobStatus::Refining, Duration::ZERO);
+        queue.cancel(chair, Duration::ZERO);
+
+        assert_eq!(queue.retry(chair, Duration::ZERO), Some(prop));
+        assert_eq!(queue.get(chair).unwrap().status, JobStatus::Queued);
+        // The prop belongs to the world now, so a second failure doesn't hand it back again.
+        start_next(&mut queue);
+        queue.set_status(chair, JobStatus::Failed("oops".into()), Duration::ZERO);
+        assert_eq!(queue.retry(chair, Duration::ZERO), None);
+    }
+
+    #[test]
+    fn retrying_continues_from_the_meshy_task() {
+        let mut queue = GenerationQueue::default();
+        let chair = push(&mut queue, "chair", JobPriority::Normal);
+        start_next(&mut queue);
+        let remote = RemoteJob::Preview {
+            task_id: "preview".to_string(),
+        };
+        queue.set_remote(chair, Some(remote.clone()));
+        queue.set_status(chair, JobStatus::Failed("oops".into()), Duration::ZERO);
+
+        let _ = queue.retry(chair, Duration::ZERO);
+        let job = queue.start_next(Duration::ZERO).unwrap();
+        assert_eq!(job.remote, Some(remote));
+    }
+}
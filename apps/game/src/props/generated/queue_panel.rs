@@ -0,0 +1,317 @@
+//! An overlay listing the jobs of the [`GenerationQueue`], to see how they are doing and to retry,
+//! cancel or reprioritize them.
+
+use std::{any::TypeId, time::Duration};
+
+use bevy::{input::common_conditions::input_just_pressed, prelude::*, ui::Val::*};
+#[cfg(feature = "hot_patch")]
+use bevy_simple_subsecond_system::hot;
+
+use crate::{
+    Pause,
+    gameplay::{
+        crosshair::CrosshairState,
+        player::{default_input::BlocksInput, dialogue::conversation::is_conversation_open},
+    },
+    props::generated::{
+        edit::{EditHistory, WorldEdit, WorldEditor},
+        library_browser::prop_library_closed,
+        queue::{GenerationJob, GenerationQueue, JobId, JobStatus, MAX_RUNNING_JOBS_LIMIT},
+    },
+    screens::{Screen, procedural_gameplay::model_prompt_closed},
+    theme::{palette::SCREEN_BACKGROUND, widget},
+};
+
+pub(super) fn plugin(app: &mut App) {
+    app.init_resource::<GenerationQueuePanel>();
+    app.add_systems(
+        Update,
+        (
+            close_generation_queue
+                .run_if(not(generation_queue_closed).and(
+                    input_just_pressed(KeyCode::Escape).or(input_just_pressed(KeyCode::KeyJ)),
+                )),
+            open_generation_queue.run_if(
+                generation_queue_closed
+                    .and(prop_library_closed)
+                    .and(model_prompt_closed)
+                    .and(not(is_conversation_open))
+                    .and(input_just_pressed(KeyCode::KeyJ)),
+            ),
+            (
+                list_generation_jobs,
+                update_elapsed_times,
+                update_max_running_label,
+            ),
+        )
+            .chain()
+            .run_if(in_state(Screen::ProceduralGameplay)),
+    );
+    app.add_systems(OnExit(Screen::ProceduralGameplay), close_generation_queue);
+}
+
+pub(crate) fn generation_queue_closed(panel: Res<GenerationQueuePanel>) -> bool {
+    panel.root.is_none()
+}
+
+#[derive(Resource, Default)]
+pub(crate) struct GenerationQueuePanel {
+    root: Option<Entity>,
+    paused_game: bool,
+}
+
+#[derive(Component)]
+struct GenerationQueueOverlay;
+
+#[derive(Component)]
+struct GenerationJobList;
+
+#[derive(Component)]
+struct ElapsedTimeLabel(JobId);
+
+#[derive(Component)]
+struct MaxRunningLabel;
+
+#[cfg_attr(feature = "hot_patch", hot)]
+fn open_generation_queue(
+    mut commands: Commands,
+    mut panel: ResMut<GenerationQueuePanel>,
+    mut crosshair: Single<&mut CrosshairState>,
+    paused: Res<State<Pause>>,
+    mut next_pause: ResMut<NextState<Pause>>,
+    mut blocks_input: ResMut<BlocksInput>,
+) {
+    let root = commands
+        .spawn((
+            widget::ui_root("Generation Queue"),
+            BackgroundColor(SCREEN_BACKGROUND.with_alpha(0.9)),
+            GlobalZIndex(3),
+            StateScoped(Screen::ProceduralGameplay),
+            GenerationQueueOverlay,
+            children![
+                widget::header("Generation Queue"),
+                widget::plus_minus_bar(MaxRunningLabel, lower_max_running, raise_max_running),
+                (
+                    Name::new("Generation Jobs"),
+                    Node {
+                        flex_direction: FlexDirection::Column,
+                        row_gap: Px(10.0),
+                        ..default()
+                    },
+                    GenerationJobList,
+                ),
+                widget::button_medium("Clear", clear_completed_jobs),
+                widget::label_small(
+                    "Jobs keep running while this is closed. Press J or Esc to close."
+                ),
+            ],
+        ))
+        .id();
+
+    *panel = GenerationQueuePanel {
+        root: Some(root),
+        paused_game: !paused.get().0,
+    };
+    if panel.paused_game {
+        next_pause.set(Pause(true));
+    }
+    let overlay_id = TypeId::of::<GenerationQueueOverlay>();
+    blocks_input.insert(overlay_id);
+    crosshair.wants_free_cursor.insert(overlay_id);
+}
+
+#[cfg_attr(feature = "hot_patch", hot)]
+fn close_generation_queue(
+    mut commands: Commands,
+    mut panel: ResMut<GenerationQueuePanel>,
+    crosshair: Option<Single<&mut CrosshairState>>,
+    mut next_pause: ResMut<NextState<Pause>>,
+    mut blocks_input: ResMut<BlocksInput>,
+    mut keys: ResMut<ButtonInput<KeyCode>>,
+) {
+    let Some(root) = panel.root.take() else {
+        return;
+    };
+    // Don't let the same press open the pause menu or the panel again.
+    keys.clear_just_pressed(KeyCode::Escape);
+    keys.clear_just_pressed(KeyCode::KeyJ);
+    commands.entity(root).try_despawn();
+    if panel.paused_game {
+        next_pause.set(Pause(false));
+    }
+    *panel = GenerationQueuePanel::default();
+
+    let overlay_id = TypeId::of::<GenerationQueueOverlay>();
+    blocks_input.remove(&overlay_id);
+    if let Some(mut crosshair) = crosshair {
+        crosshair.wants_free_cursor.remove(&overlay_id);
+    }
+}
+
+fn lower_max_running(_: Trigger<Pointer<Click>>, mut queue: ResMut<GenerationQueue>) {
+    queue.max_running = queue.max_running.saturating_sub(1).max(1);
+}
+
+fn raise_max_running(_: Trigger<Pointer<Click>>, mut queue: ResMut<GenerationQueue>) {
+    queue.max_running = (queue.max_running + 1).min(MAX_RUNNING_JOBS_LIMIT);
+}
+
+fn clear_completed_jobs(_: Trigger<Pointer<Click>>, mut queue: ResMut<GenerationQueue>) {
+    queue.clear_completed();
+}
+
+fn update_max_running_label(
+    queue: Res<GenerationQueue>,
+    mut labels: Query<&mut Text, With<MaxRunningLabel>>,
+) {
+    for mut label in &mut labels {
+        let text = format!("Jobs running at once: {}", queue.max_running);
+        if label.0 != text {
+            label.0 = text;
+        }
+    }
+}
+
+/// Lists the jobs whenever the queue changes. Their elapsed times are updated separately, so that
+/// the buttons aren't rebuilt every frame.
+#[cfg_attr(feature = "hot_patch", hot)]
+fn list_generation_jobs(
+    mut commands: Commands,
+    queue: Res<GenerationQueue>,
+    lists: Query<(Entity, Ref<GenerationJobList>)>,
+) {
+    let Ok((list, list_marker)) = lists.single() else {
+        return;
+    };
+    if !(queue.is_changed() || list_marker.is_added()) {
+        return;
+    }
+
+    commands.entity(list).despawn_related::<Children>();
+    if queue.jobs().is_empty() {
+        commands.spawn((
+            widget::label("No props are being generated. Press M to generate one."),
+            ChildOf(list),
+        ));
+        return;
+    }
+    // The newest jobs are the most interesting ones.
+    for job in queue.jobs().iter().rev() {
+        spawn_job_row(&mut commands, list, job);
+    }
+}
+
+fn spawn_job_row(commands: &mut Commands, list: Entity, job: &GenerationJob) {
+    let row = commands
+        .spawn((
+            Name::new(format!("Generation Job {}", job.prompt)),
+            Node {
+                flex_direction: FlexDirection::Row,
+                align_items: AlignItems::Center,
+                column_gap: Px(16.0),
+                ..default()
+            },
+            ChildOf(list),
+        ))
+        .id();
+
+    commands.spawn((
+        Name::new("Description"),
+        Node {
+            width: Px(480.0),
+            flex_direction: FlexDirection::Column,
+            row_gap: Px(4.0),
+            ..default()
+        },
+        ChildOf(row),
+        children![
+            widget::label(job.prompt.clone()),
+            (widget::label_small(""), ElapsedTimeLabel(job.id)),
+        ],
+    ));
+
+    let id = job.id;
+    if job.status == JobStatus::Queued {
+        commands.spawn((
+            widget::button_medium(
+                job.priority.name(),
+                move |_: Trigger<Pointer<Click>>, mut queue: ResMut<GenerationQueue>| {
+                    if let Some(priority) = queue.get(id).map(|job| job.priority.next()) {
+                        queue.set_priority(id, priority);
+                    }
+                },
+            ),
+            ChildOf(row),
+        ));
+    }
+    if job.status.can_retry() {
+        commands.spawn((
+            widget::button_medium(
+                "Retry",
+                move |_: Trigger<Pointer<Click>>,
+                      mut queue: ResMut<GenerationQueue>,
+                      mut editor: WorldEditor,
+                      mut history: ResMut<EditHistory>,
+                      time: Res<Time<Real>>| {
+                    // The retried job spawns the prop again, so don't leave its old preview.
+                    let preview = queue.retry(id, time.elapsed());
+                    if let Some(record) = preview.and_then(|prop| editor.record(prop)) {
+                        let edit = WorldEdit::Delete(record);
+                        editor.apply(&edit);
+                        history.push(edit);
+                    }
+                },
+            ),
+            ChildOf(row),
+        ));
+    } else if !job.status.is_finished() {
+        commands.spawn((
+            widget::button_medium(
+                "Cancel",
+                move |_: Trigger<Pointer<Click>>,
+                      mut queue: ResMut<GenerationQueue>,
+                      time: Res<Time<Real>>| {
+                    queue.cancel(id, time.elapsed());
+                },
+            ),
+            ChildOf(row),
+        ));
+    }
+}
+
+fn update_elapsed_times(
+    queue: Res<GenerationQueue>,
+    time: Res<Time<Real>>,
+    mut labels: Query<(&mut Text, &ElapsedTimeLabel)>,
+) {
+    for (mut label, ElapsedTimeLabel(id)) in &mut labels {
+        let Some(job) = queue.get(*id) else {
+            continue;
+        };
+        let text = format!(
+            "{}, {}",
+            status_text(&job.status),
+            format_elapsed(job.elapsed(time.elapsed()))
+        );
+        if label.0 != text {
+            label.0 = text;
+        }
+    }
+}
+
+fn status_text(status: &JobStatus) -> String {
+    match status {
+        JobStatus::Queued => "Queued, click its priority to change it".to_string(),
+        JobStatus::Previewing => "Generating preview".to_string(),
+        JobStatus::Refining => "Texturing".to_string(),
+        JobStatus::Modeling => "Modeling from concept art".to_string(),
+        JobStatus::Completed => "Done".to_string(),
+        JobStatus::Failed(reason) => format!("Failed: {reason}"),
+        JobStatus::Cancelled => "Cancelled".to_string(),
+    }
+}
+
+fn format_elapsed(elapsed: Duration) -> String {
+    let seconds = elapsed.as_secs();
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
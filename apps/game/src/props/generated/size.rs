@@ -0,0 +1,313 @@
+//! Real-world sizes of generated props. Meshy models come in arbitrary units, so a prop is scaled
+//! to the size the prompt asks for, e.g. "a 2 m tall statue", or to the typical size of the
+//! object it names.
+
+use std::sync::LazyLock;
+
+use bevy::prelude::*;
+use regex::Regex;
+
+/// The size of props whose prompt names no object we know, in meters.
+const DEFAULT_SIZE: f32 = 0.8;
+/// Explicit sizes outside this range are typos or jokes that would make the prop unusable.
+const EXPLICIT_SIZE_RANGE: (f32, f32) = (0.05, 20.0);
+
+/// Typical sizes of objects in meters, measured along the given axis.
+const SIZE_PRIORS: &[(&str, f32, SizeAxis)] = &[
+    // Tiny things that fit in a hand
+    ("coin", 0.03, SizeAxis::Longest),
+    ("ring", 0.03, SizeAxis::Longest),
+    ("key", 0.08, SizeAxis::Longest),
+    ("dice", 0.03, SizeAxis::Longest),
+    ("die", 0.03, SizeAxis::Longest),
+    ("egg", 0.06, SizeAxis::Longest),
+    ("apple", 0.08, SizeAxis::Longest),
+    ("orange", 0.08, SizeAxis::Longest),
+    ("potion", 0.15, SizeAxis::Height),
+    ("teacup", 0.08, SizeAxis::Height),
+    ("mug", 0.1, SizeAxis::Height),
+    ("cup", 0.1, SizeAxis::Height),
+    ("phone", 0.15, SizeAxis::Longest),
+    ("candle", 0.2, SizeAxis::Height),
+    ("bottle", 0.3, SizeAxis::Height),
+    ("book", 0.25, SizeAxis::Longest),
+    ("skull", 0.22, SizeAxis::Longest),
+    ("lantern", 0.35, SizeAxis::Height),
+    ("lamp", 0.5, SizeAxis::Height),
+    ("ball", 0.22, SizeAxis::Longest),
+    ("helmet", 0.3, SizeAxis::Longest),
+    ("hat", 0.3, SizeAxis::Longest),
+    ("shoe", 0.3, SizeAxis::Longest),
+    ("boot", 0.3, SizeAxis::Height),
+    ("plate", 0.25, SizeAxis::Longest),
+    ("bowl", 0.2, SizeAxis::Longest),
+    ("vase", 0.4, SizeAxis::Height),
+    ("pot", 0.3, SizeAxis::Height),
+    ("bucket", 0.35, SizeAxis::Height),
+    ("basket", 0.4, SizeAxis::Longest),
+    ("pumpkin", 0.4, SizeAxis::Longest),
+    ("mushroom", 0.2, SizeAxis::Height),
+    ("rock", 0.5, SizeAxis::Longest),
+    // Tools and weapons
+    ("dagger", 0.35, SizeAxis::Longest),
+    ("knife", 0.3, SizeAxis::Longest),
+    ("hammer", 0.4, SizeAxis::Longest),
+    ("axe", 0.8, SizeAxis::Longest),
+    ("sword", 1.0, SizeAxis::Longest),
+    ("shield", 0.8, SizeAxis::Longest),
+    ("bow", 1.3, SizeAxis::Longest),
+    ("guitar", 1.0, SizeAxis::Longest),
+    ("staff", 1.7, SizeAxis::Longest),
+    ("spear", 2.0, SizeAxis::Longest),
+    ("broom", 1.4, SizeAxis::Longest),
+    ("shovel", 1.2, SizeAxis::Longest),
+    // Containers
+    ("chest", 0.6, SizeAxis::Longest),
+    ("crate", 0.7, SizeAxis::Longest),
+    ("box", 0.5, SizeAxis::Longest),
+    ("barrel", 0.9, SizeAxis::Height),
+    ("suitcase", 0.6, SizeAxis::Longest),
+    // Furniture
+    ("stool", 0.5, SizeAxis::Height),
+    ("chair", 0.9, SizeAxis::Height),
+    ("throne", 1.6, SizeAxis::Height),
+    ("bench", 1.5, SizeAxis::Longest),
+    ("sofa", 2.0, SizeAxis::Longest),
+    ("couch", 2.0, SizeAxis::Longest),
+    ("desk", 1.4, SizeAxis::Longest),
+    ("table", 1.5, SizeAxis::Longest),
+    ("bed", 2.0, SizeAxis::Longest),
+    ("bookshelf", 1.9, SizeAxis::Height),
+    ("shelf", 1.6, SizeAxis::Height),
+    ("wardrobe", 2.0, SizeAxis::Height),
+    ("cabinet", 1.2, SizeAxis::Height),
+    ("fridge", 1.8, SizeAxis::Height),
+    ("television", 0.9, SizeAxis::Longest),
+    ("tv", 0.9, SizeAxis::Longest),
+    ("piano", 1.5, SizeAxis::Longest),
+    ("door", 2.1, SizeAxis::Height),
+    ("fence", 1.2, SizeAxis::Height),
+    ("sign", 1.5, SizeAxis::Height),
+    ("mailbox", 1.2, SizeAxis::Height),
+    ("anvil", 0.5, SizeAxis::Height),
+    ("cauldron", 0.7, SizeAxis::Height),
+    // Creatures
+    ("mouse", 0.1, SizeAxis::Longest),
+    ("frog", 0.1, SizeAxis::Longest),
+    ("bird", 0.25, SizeAxis::Longest),
+    ("duck", 0.4, SizeAxis::Longest),
+    ("chicken", 0.4, SizeAxis::Height),
+    ("cat", 0.45, SizeAxis::Longest),
+    ("fox", 0.7, SizeAxis::Longest),
+    ("dog", 0.8, SizeAxis::Longest),
+    ("pig", 1.1, SizeAxis::Longest),
+    ("deer", 1.8, SizeAxis::Longest),
+    ("cow", 2.2, SizeAxis::Longest),
+    ("horse", 2.4, SizeAxis::Longest),
+    ("bear", 2.0, SizeAxis::Longest),
+    ("dragon", 6.0, SizeAxis::Longest),
+    ("robot", 1.8, SizeAxis::Height),
+    ("statue", 2.0, SizeAxis::Height),
+    ("knight", 1.8, SizeAxis::Height),
+    ("person", 1.75, SizeAxis::Height),
+    ("man", 1.75, SizeAxis::Height),
+    ("woman", 1.7, SizeAxis::Height),
+    ("figurine", 0.15, SizeAxis::Height),
+    ("toy", 0.3, SizeAxis::Longest),
+    // Large props
+    ("bicycle", 1.7, SizeAxis::Longest),
+    ("motorcycle", 2.1, SizeAxis::Longest),
+    ("car", 4.3, SizeAxis::Longest),
+    ("boat", 4.0, SizeAxis::Longest),
+    ("cart", 2.0, SizeAxis::Longest),
+    ("wagon", 3.5, SizeAxis::Longest),
+    ("cannon", 2.0, SizeAxis::Longest),
+    ("well", 1.5, SizeAxis::Longest),
+    ("tent", 2.5, SizeAxis::Longest),
+    ("arch", 3.0, SizeAxis::Height),
+    ("pillar", 3.0, SizeAxis::Height),
+    ("column", 3.0, SizeAxis::Height),
+    ("fountain", 2.5, SizeAxis::Longest),
+    ("boulder", 1.5, SizeAxis::Longest),
+    ("bush", 1.0, SizeAxis::Height),
+    ("tree", 6.0, SizeAxis::Height),
+    ("lamppost", 3.5, SizeAxis::Height),
+    ("streetlight", 4.5, SizeAxis::Height),
+    ("tower", 12.0, SizeAxis::Height),
+    ("house", 7.0, SizeAxis::Longest),
+    ("hut", 4.0, SizeAxis::Longest),
+];
+
+/// Words that end the part of a prompt naming its object, as in "a hat with a feather".
+const PHRASE_ENDS: &[&str] = &[
+    "with", "on", "of", "in", "under", "over", "near", "beside", "behind", "holding", "for",
+    "from", "and",
+];
+
+/// Matches sizes like "2m", "30 cm", "1.5 meters tall" or "6 ft long". Inches have to be spelled
+/// out, as "in" is more often a word, like in "a 4 in 1 toolbox".
+static EXPLICIT_SIZE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?x)
+        \b(?P<value>\d+(?:\.\d+)?)\s*
+        (?P<unit>millimet(?:er|re)s?|mm|centimet(?:er|re)s?|cm|met(?:er|re)s?|m|feet|foot|ft|inch(?:es)?)\b
+        (?:\s+(?P<dimension>tall|high|long|wide|across|deep))?",
+    )
+    .expect("explicit size pattern is valid")
+});
+
+/// Along which axis of its bounding box a [`PropSize`] is measured.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SizeAxis {
+    /// How tall the prop stands, e.g. for chairs and people.
+    Height,
+    /// The largest extent in any direction, e.g. for tables lying on their longest side.
+    Longest,
+}
+
+/// How large a generated prop should be in the world.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct PropSize {
+    pub(crate) meters: f32,
+    pub(crate) axis: SizeAxis,
+}
+
+impl PropSize {
+    /// Uses the size written in the prompt if there is one, and otherwise the typical size of the
+    /// object the prompt is about, if [`SIZE_PRIORS`] knows it. That object is the last noun before
+    /// the first preposition, as in "a man's hat with a feather on it". Prompts without a known
+    /// object there fall back to the first known object after it.
+    pub(crate) fn from_prompt(prompt: &str) -> Self {
+        let prompt = prompt.to_lowercase();
+        if let Some(size) = explicit_size(&prompt) {
+            return size;
+        }
+
+        let words = prompt
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .collect::<Vec<_>>();
+        let phrase_end = words
+            .iter()
+            .position(|word| PHRASE_ENDS.contains(word))
+            .unwrap_or(words.len());
+        let (phrase, rest) = words.split_at(phrase_end);
+        phrase
+            .iter()
+            .rev()
+            .chain(rest)
+            .find_map(|word| {
+                // Allow plurals, but don't let "car" match "card".
+                let singular = word.strip_suffix('s').unwrap_or(word);
+                SIZE_PRIORS
+                    .iter()
+                    .find(|(keyword, ..)| keyword == word || *keyword == singular)
+            })
+            .map(|&(_, meters, axis)| Self { meters, axis })
+            .unwrap_or(Self {
+                meters: DEFAULT_SIZE,
+                axis: SizeAxis::Longest,
+            })
+    }
+
+    /// The uniform scale that gives a model with the given bounding box size this size.
+    pub(crate) fn scale_for(&self, model_size: Vec3) -> f32 {
+        let measured = match self.axis {
+            SizeAxis::Height => model_size.y,
+            SizeAxis::Longest => model_size.max_element(),
+        };
+        if measured <= f32::EPSILON {
+            // Flat models have no height, so fall back to their largest extent.
+            let longest = model_size.max_element();
+            return if longest > f32::EPSILON {
+                self.meters / longest
+            } else {
+                1.0
+            };
+        }
+        self.meters / measured
+    }
+}
+
+fn explicit_size(prompt: &str) -> Option<PropSize> {
+    let captures = EXPLICIT_SIZE.captures(prompt)?;
+    let value = captures["value"].parse::<f32>().ok()?;
+    let unit = &captures["unit"];
+    let meters_per_unit = if unit.starts_with("mm") || unit.starts_with("milli") {
+        0.001
+    } else if unit.starts_with('c') {
+        0.01
+    } else if unit.starts_with('m') {
+        1.0
+    } else if unit.starts_with('f') {
+        0.3048
+    } else {
+        0.0254
+    };
+    let meters = value * meters_per_unit;
+    if !(EXPLICIT_SIZE_RANGE.0..=EXPLICIT_SIZE_RANGE.1).contains(&meters) {
+        return None;
+    }
+
+    let axis = match captures
+        .name("dimension")
+        .map(|dimension| dimension.as_str())
+    {
+        Some("tall" | "high") => SizeAxis::Height,
+        _ => SizeAxis::Longest,
+    };
+    Some(PropSize { meters, axis })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn size(prompt: &str) -> (f32, SizeAxis) {
+        let size = PropSize::from_prompt(prompt);
+        (size.meters, size.axis)
+    }
+
+    #[test]
+    fn reads_explicit_sizes() {
+        assert_eq!(size("a 2 m tall statue"), (2.0, SizeAxis::Height));
+        assert_eq!(size("a 30cm vase"), (0.3, SizeAxis::Longest));
+        assert_eq!(size("a 12 inch pizza"), (12.0 * 0.0254, SizeAxis::Longest));
+        assert_eq!(size("a 6 ft long table"), (6.0 * 0.3048, SizeAxis::Longest));
+        // Sizes out of range are ignored in favour of the object.
+        assert_eq!(size("a 500 m tower"), (12.0, SizeAxis::Height));
+        // "in" is a word, not a unit.
+        assert_eq!(size("a 4 in 1 toolbox"), (DEFAULT_SIZE, SizeAxis::Longest));
+        assert_eq!(explicit_size("a 4 in 1 chair"), None);
+    }
+
+    #[test]
+    fn uses_the_object_the_prompt_is_about() {
+        assert_eq!(size("a man's hat"), (0.3, SizeAxis::Longest));
+        assert_eq!(
+            size("a chair with a cushion on it"),
+            (0.9, SizeAxis::Height)
+        );
+        assert_eq!(size("a statue of a man"), (2.0, SizeAxis::Height));
+        assert_eq!(size("two old cars"), (4.3, SizeAxis::Longest));
+        assert_eq!(size("a glowing thing on a table"), (1.5, SizeAxis::Longest));
+        assert_eq!(size("a playing card"), (DEFAULT_SIZE, SizeAxis::Longest));
+    }
+
+    #[test]
+    fn scales_along_the_size_axis() {
+        let height = PropSize {
+            meters: 1.0,
+            axis: SizeAxis::Height,
+        };
+        let longest = PropSize {
+            meters: 1.0,
+            axis: SizeAxis::Longest,
+        };
+        assert_eq!(height.scale_for(Vec3::new(4.0, 2.0, 1.0)), 0.5);
+        assert_eq!(longest.scale_for(Vec3::new(4.0, 2.0, 1.0)), 0.25);
+        // Flat models fall back to their longest side, and empty ones aren't scaled.
+        assert_eq!(height.scale_for(Vec3::new(4.0, 0.0, 2.0)), 0.25);
+        assert_eq!(height.scale_for(Vec3::ZERO), 1.0);
+    }
+}
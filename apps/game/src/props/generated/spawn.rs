@@ -0,0 +1,179 @@
+//! Spawning generated props. A prop is a physical root entity whose model is spawned as separate
+//! children, so that the model can be replaced while the prop keeps its physics state.
+
+use avian_pickup::prop::PreferredPickupRotation;
+use avian3d::prelude::*;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    gameplay::procedural_navmesh::NavmeshObstacle,
+    generate::{generate_collider::ColliderQuality, generate_model::GeneratedModel},
+    props::generated::{
+        lod::{PropLod, lod_visibility_ranges},
+        material::PropMaterial,
+        size::PropSize,
+    },
+    screens::Screen,
+    third_party::avian3d::CollisionLayer,
+};
+
+/// A prop generated from a prompt during procedural gameplay.
+#[derive(Component, Debug, Clone)]
+pub(crate) struct GeneratedProp {
+    /// The prompt the prop was generated from, which doubles as its description.
+    pub(crate) prompt: String,
+    /// How closely the prop's collider follows its shape.
+    pub(crate) collider_quality: ColliderQuality,
+}
+
+/// Identifies a generated prop across despawning and respawning it, e.g. when its deletion is
+/// undone.
+#[derive(Component, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) struct PropId(u64);
+
+impl PropId {
+    pub(crate) fn new() -> Self {
+        Self(Uuid::new_v4().as_u64_pair().0)
+    }
+}
+
+/// The asset path of the model a generated prop currently shows.
+#[derive(Component, Debug, Clone)]
+pub(crate) struct PropModelPath(pub(crate) String);
+
+/// A child of a generated prop that belongs to its current model, i.e. a level of detail or the
+/// collider.
+#[derive(Component)]
+pub(crate) struct GeneratedPropModel;
+
+#[derive(Debug, Clone)]
+pub(crate) struct SpawnLocation {
+    /// Stands on the surface the prop was placed on, and carries the rotation and scale it was
+    /// placed with.
+    pub(crate) transform: Transform,
+}
+
+/// Spawns the physical prop, without a model. The model is spawned by [`spawn_prop_model`], so
+/// that it can be replaced while the prop keeps its physics state.
+pub(crate) fn spawn_generated_prop(
+    commands: &mut Commands,
+    id: PropId,
+    spawn_location: SpawnLocation,
+    prompt: &str,
+    collider_quality: ColliderQuality,
+) -> Entity {
+    let material = PropMaterial::from_prompt(prompt);
+    info!(prompt, ?material, "inferred material of generated prop");
+
+    commands
+        .spawn((
+            Name::new(format!("Generated Prop {prompt}")),
+            spawn_location.transform,
+            GlobalTransform::default(),
+            // Add physics components to make the model collidable and pickupable
+            RigidBody::Dynamic, // Dynamic so it can be picked up and moved
+            // Enable pickup interaction
+            PreferredPickupRotation(Quat::IDENTITY), // Keep upright when picked up
+            // Let NPCs walk around the prop once it has settled
+            NavmeshObstacle,
+            // Sets friction and restitution, and gives the prop its sounds
+            material,
+            StateScoped(Screen::ProceduralGameplay),
+            GeneratedProp {
+                prompt: prompt.to_string(),
+                collider_quality,
+            },
+            id,
+        ))
+        .id()
+}
+
+/// The size of a model in meters once it is scaled to the real-world size its prompt asks for.
+/// `None` if the model could not be measured.
+pub(crate) fn prop_dimensions(model: &GeneratedModel, prompt: &str) -> Option<Vec3> {
+    let bounds = model.bounds?;
+    Some(bounds.size() * PropSize::from_prompt(prompt).scale_for(bounds.size()))
+}
+
+/// The transform that scales a model to its real-world size and moves its base to the origin of
+/// the prop, which stands on the ground, along with the largest extent of the scaled model.
+pub(crate) fn fit_model(model: &GeneratedModel, prompt: &str) -> (Transform, f32) {
+    match model.bounds {
+        Some(bounds) => {
+            let size = PropSize::from_prompt(prompt);
+            let scale = size.scale_for(bounds.size());
+            info!(prompt, ?size, scale, "inferred size of generated prop");
+            (
+                Transform::from_translation(-bounds.base() * scale).with_scale(Vec3::splat(scale)),
+                bounds.size().max_element() * scale,
+            )
+        }
+        None => {
+            warn!(
+                prompt,
+                "generated prop has unknown bounds; spawning it unscaled"
+            );
+            (Transform::default(), 1.0)
+        }
+    }
+}
+
+/// Spawns the levels of detail and the collider of a model as children of a generated prop.
+pub(crate) fn spawn_prop_model(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    prop: Entity,
+    model: &GeneratedModel,
+    prompt: &str,
+) {
+    let material = PropMaterial::from_prompt(prompt);
+    let (model_transform, world_size) = fit_model(model, prompt);
+    commands
+        .entity(prop)
+        .insert(PropModelPath(model.path.clone()));
+
+    // The collider was decomposed from the model's own mesh, so it shares the model's transform.
+    let collider = model
+        .collider
+        .as_ref()
+        .and_then(|decomposition| decomposition.to_collider());
+    let has_collider = collider.is_some();
+    if let Some(collider) = collider {
+        commands.spawn((
+            Name::new("Collider"),
+            GeneratedPropModel,
+            collider,
+            CollisionLayers::new(CollisionLayer::Prop, LayerMask::ALL),
+            ColliderDensity(material.density()),
+            model_transform,
+            ChildOf(prop),
+        ));
+    }
+
+    let ranges = lod_visibility_ranges(model.lod_count, world_size);
+    let coarsest_lod = ranges.len().saturating_sub(1);
+    for (lod, range) in ranges.into_iter().enumerate() {
+        let scene_handle: Handle<Scene> = asset_server.load(format!("{}#Scene{lod}", model.path));
+        let mut part = commands.spawn((
+            Name::new(format!("Model LOD{lod}")),
+            GeneratedPropModel,
+            SceneRoot(scene_handle),
+            model_transform,
+            ChildOf(prop),
+        ));
+        if let Some(range) = range {
+            part.insert(PropLod(range));
+        }
+        // Without a cached collider, fall back to a hull of the coarsest level of detail, which is
+        // the cheapest to compute.
+        if !has_collider && lod == coarsest_lod {
+            part.insert(
+                ColliderConstructorHierarchy::new(ColliderConstructor::ConvexHullFromMesh)
+                    .with_default_layers(CollisionLayers::new(CollisionLayer::Prop, LayerMask::ALL))
+                    .with_default_density(material.density()),
+            );
+        }
+    }
+}
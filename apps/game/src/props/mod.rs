@@ -5,6 +5,7 @@ use bevy::prelude::*;
 
 mod brush_entity;
 mod effects;
+pub(crate) mod generated;
 mod generic;
 mod setup;
 mod specific;
@@ -15,6 +16,7 @@ pub(super) fn plugin(app: &mut App) {
         specific::plugin,
         effects::plugin,
         generic::plugin,
+        generated::plugin,
         brush_entity::plugin,
     ));
 }
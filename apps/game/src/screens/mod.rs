@@ -2,7 +2,7 @@
 
 mod gameplay;
 pub(crate) mod loading;
-mod procedural_gameplay;
+pub(crate) mod procedural_gameplay;
 mod procedural_loading;
 mod splash;
 mod title;
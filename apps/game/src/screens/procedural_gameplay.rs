@@ -1,35 +1,55 @@
 //! The screen state for procedural gameplay.
 
 use anyhow::Result as AnyhowResult;
-use avian_pickup::prop::PreferredPickupRotation;
-use avian3d::prelude::*;
 use bevy::{
+    ecs::spawn::SpawnWith,
     input::common_conditions::input_just_pressed,
     prelude::*,
-    render::{
-        mesh::{Mesh, MeshAabb},
-        view::RenderLayers,
-    },
+    render::view::RenderLayers,
     tasks::{IoTaskPool, Task},
     ui::Val::*,
 };
 use bevy_hanabi::prelude::*;
-use bevy_simple_text_input::{TextInput, TextInputSubmitEvent, TextInputSystem};
+use bevy_simple_text_input::{
+    TextInput, TextInputInactive, TextInputSubmitEvent, TextInputSystem, TextInputValue,
+};
 use futures_lite::future;
-use std::{any::TypeId, collections::VecDeque};
+use std::{any::TypeId, time::Duration};
 
 use crate::{
     Pause, RenderLayer,
     gameplay::{
         crosshair::CrosshairState,
-        player::{Player, default_input::BlocksInput},
-        procedural_level::sample_terrain_height,
+        player::{default_input::BlocksInput, dialogue::conversation::is_conversation_open},
+        world_settings::WorldSettings,
+    },
+    generate::{
+        generate_collider::ColliderQuality,
+        generate_concept::{ConceptArt, generate_concept_art},
+        generate_model::{
+            GeneratedModel, MeshyTaskFailed, ModelPreview, RefinedModel, finish_image_to_3d,
+            finish_preview, finish_refine, load_model, start_image_to_3d, start_preview,
+            start_refine,
+        },
+        model_options::{ModelOption, ModelOptions},
+        validate_model::ModelValidationError,
+    },
+    menus::{Menu, generate::GenerationPrompt},
+    props::generated::{
+        edit::{EditHistory, PropRecord, WorldEdit},
+        journal::{GenerationJournal, RemoteJob},
+        library::{LibraryEntry, PropLibrary},
+        library_browser::prop_library_closed,
+        placement::{BeginPlacement, PlacementTarget, PropPlaced, placement_inactive},
+        queue::{GenerationQueue, JobId, JobPriority, JobStatus},
+        queue_panel::generation_queue_closed,
+        spawn::{
+            GeneratedProp, GeneratedPropModel, PropId, SpawnLocation, prop_dimensions,
+            spawn_generated_prop, spawn_prop_model,
+        },
     },
-    generate::generate_model::{GeneratedModelPaths, generate_prop},
-    menus::Menu,
     screens::Screen,
     theme::{palette::SCREEN_BACKGROUND, widget},
-    third_party::avian3d::CollisionLayer,
 };
 
 pub(super) fn plugin(app: &mut App) {
@@ -43,6 +63,10 @@ pub(super) fn plugin(app: &mut App) {
                 in_state(Screen::ProceduralGameplay)
                     .and(in_state(Menu::None))
                     .and(model_prompt_closed)
+                    .and(prop_library_closed)
+                    .and(generation_queue_closed)
+                    .and(placement_inactive)
+                    .and(not(is_conversation_open))
                     .and(input_just_pressed(KeyCode::KeyP).or(input_just_pressed(KeyCode::Escape))),
             ),
             close_menu.run_if(
@@ -56,14 +80,34 @@ pub(super) fn plugin(app: &mut App) {
     app.add_systems(
         Update,
         (
-            toggle_model_prompt,
+            // Typing to an NPC or searching the library shouldn't open the prompt.
+            toggle_model_prompt.run_if(
+                not(is_conversation_open)
+                    .and(prop_library_closed)
+                    .and(generation_queue_closed),
+            ),
+            cycle_collider_quality.run_if(input_just_pressed(KeyCode::Tab)),
+            (
+                focus_clicked_prompt_input,
+                update_model_option_labels,
+                update_concept_art_label,
+            ),
             submit_model_prompt.after(TextInputSystem),
-            monitor_model_generation_tasks,
-            adjust_generated_prop_height.after(monitor_model_generation_tasks),
+            show_concept_art,
+            (
+                stop_cancelled_generations,
+                start_queued_generations,
+                monitor_model_generation_tasks,
+            )
+                .chain(),
+            expire_generation_notices,
         )
             .run_if(in_state(Screen::ProceduralGameplay)),
     );
 
+    app.add_observer(start_model_generation);
+    app.add_systems(OnEnter(Screen::ProceduralGameplay), reset_model_options);
+
     app.add_systems(
         OnExit(Screen::ProceduralGameplay),
         (close_menu, unpause, cleanup_model_prompt),
@@ -74,7 +118,7 @@ pub(super) fn plugin(app: &mut App) {
     );
 }
 
-fn model_prompt_closed(state: Res<ModelPromptUiState>) -> bool {
+pub(crate) fn model_prompt_closed(state: Res<ModelPromptUiState>) -> bool {
     !state.is_open()
 }
 
@@ -139,15 +183,105 @@ fn toggle_model_prompt(
     }
 }
 
+fn cycle_collider_quality(
+    mut ui_state: ResMut<ModelPromptUiState>,
+    mut labels: Query<&mut Text, With<ColliderQualityLabel>>,
+) {
+    if !ui_state.is_open() {
+        return;
+    }
+    ui_state.collider_quality = ui_state.collider_quality.next();
+    for mut label in &mut labels {
+        label.0 = collider_quality_text(ui_state.collider_quality);
+    }
+}
+
+fn collider_quality_text(quality: ColliderQuality) -> String {
+    format!("Collider quality: {} (Tab to change)", quality.name())
+}
+
+fn reset_model_options(mut ui_state: ResMut<ModelPromptUiState>, settings: Res<WorldSettings>) {
+    ui_state.options = settings.prop_options.clone();
+}
+
+fn model_option_bar(option: ModelOption) -> impl Bundle {
+    let step = move |up| {
+        move |_trigger: Trigger<Pointer<Click>>, mut ui_state: ResMut<ModelPromptUiState>| {
+            ui_state.options.step(option, up);
+        }
+    };
+    widget::plus_minus_bar(ModelOptionLabel(option), step(false), step(true))
+}
+
+fn update_model_option_labels(
+    ui_state: Res<ModelPromptUiState>,
+    mut labels: Query<(&mut Text, &ModelOptionLabel)>,
+) {
+    for (mut label, ModelOptionLabel(option)) in &mut labels {
+        let text = ui_state.options.value_text(*option);
+        if label.0 != text {
+            label.0 = text;
+        }
+    }
+}
+
+fn toggle_concept_art(_: Trigger<Pointer<Click>>, mut ui_state: ResMut<ModelPromptUiState>) {
+    ui_state.concept_art = !ui_state.concept_art;
+}
+
+fn concept_art_text(concept_art: bool) -> String {
+    let value = if concept_art { "on" } else { "off" };
+    format!("Paint concept art first: {value}")
+}
+
+fn update_concept_art_label(
+    ui_state: Res<ModelPromptUiState>,
+    mut labels: Query<&mut Text, With<ConceptArtLabel>>,
+) {
+    for mut label in &mut labels {
+        let text = concept_art_text(ui_state.concept_art);
+        if label.0 != text {
+            label.0 = text;
+        }
+    }
+}
+
+/// Types into whichever prompt input was clicked last.
+fn focus_clicked_prompt_input(
+    clicked: Query<
+        (Entity, &Interaction),
+        (
+            Changed<Interaction>,
+            Or<(With<ModelPromptInput>, With<TexturePromptInput>)>,
+        ),
+    >,
+    mut inputs: Query<
+        (Entity, &mut TextInputInactive),
+        Or<(With<ModelPromptInput>, With<TexturePromptInput>)>,
+    >,
+) {
+    let Some((focused, _)) = clicked
+        .iter()
+        .find(|(_, interaction)| **interaction == Interaction::Pressed)
+    else {
+        return;
+    };
+    for (entity, mut inactive) in &mut inputs {
+        inactive.0 = entity != focused;
+    }
+}
+
 fn submit_model_prompt(
     mut events: EventReader<TextInputSubmitEvent>,
     mut commands: Commands,
     mut ui_state: ResMut<ModelPromptUiState>,
+    prompt_inputs: Query<(Entity, &TextInputValue), With<ModelPromptInput>>,
+    texture_inputs: Query<(Entity, &TextInputValue), With<TexturePromptInput>>,
+    concept_panels: Query<Entity, With<ConceptArtPanel>>,
+    world: Res<GenerationPrompt>,
     crosshair: Option<Single<&mut CrosshairState>>,
     mut next_pause: ResMut<NextState<Pause>>,
     mut blocks_input: ResMut<BlocksInput>,
-    mut effects: ResMut<Assets<EffectAsset>>,
-    players: Query<&GlobalTransform, With<Player>>,
 ) {
     if !ui_state.is_open() {
         for _ in events.read() {}
@@ -157,32 +291,43 @@ fn submit_model_prompt(
     let mut crosshair = crosshair.map(Single::into_inner);
 
     for event in events.read() {
-        let prompt = event.value.trim();
+        // Enter submits both inputs, whichever of them has focus. The focused one has been
+        // cleared by now, so its value comes from the event.
+        let value = |input: Result<(Entity, &TextInputValue), _>| match input {
+            Ok((entity, _)) if entity == event.entity => event.value.trim().to_string(),
+            Ok((_, value)) => value.0.trim().to_string(),
+            Err(_) => String::new(),
+        };
+        let prompt = value(prompt_inputs.single());
         if prompt.is_empty() {
             continue;
         }
+        let texture_prompt = value(texture_inputs.single());
+        ui_state.options.texture_prompt = (!texture_prompt.is_empty()).then_some(texture_prompt);
 
-        info!(prompt, "Starting in-game Meshy generation task");
-
-        let task_prompt = prompt.to_string();
-        let spawn_location = predicted_spawn_location(&players);
-        let placeholder = spawn_placeholder(
-            &mut commands,
-            &mut effects,
-            spawn_location.transform.clone(),
-        );
+        // The prompt stays open until the player approves the concept art.
+        if ui_state.concept_art {
+            let Ok(panel) = concept_panels.single() else {
+                break;
+            };
+            paint_concept_art(&mut commands, panel, prompt.clone(), world.0.clone());
+            ui_state.reject_pending_concept();
+            ui_state.pending_concept = Some(PendingConcept {
+                prompt,
+                collider_quality: ui_state.collider_quality,
+                options: ui_state.options.clone(),
+                concept: None,
+            });
+            break;
+        }
 
-        let task_location = spawn_location.clone();
-        let task = IoTaskPool::get().spawn({
-            let prompt = task_prompt.clone();
-            async move { generate_prop(prompt) }
-        });
-        commands.spawn(ModelGenerationTask {
-            prompt: task_prompt,
-            task,
-            placeholder,
-            spawn_location: task_location,
-        });
+        // The generation only starts once the player has chosen where the prop goes.
+        commands.trigger(BeginPlacement(PlacementTarget::Prompt {
+            prompt,
+            collider_quality: ui_state.collider_quality,
+            options: ui_state.options.clone(),
+            concept: None,
+        }));
 
         close_model_prompt_ui(
             &mut commands,
@@ -195,70 +340,702 @@ fn submit_model_prompt(
     }
 }
 
+/// Paints concept art in the panel of the prompt, replacing what the panel showed before.
+fn paint_concept_art(commands: &mut Commands, panel: Entity, prompt: String, world_prompt: String) {
+    commands.entity(panel).despawn_related::<Children>();
+    let label = format!("Painting concept art of \"{prompt}\"...");
+    let task = IoTaskPool::get().spawn(async move { generate_concept_art(prompt, world_prompt) });
+    commands.spawn((
+        widget::label_small(label),
+        ConceptArtTask(task),
+        ChildOf(panel),
+    ));
+}
+
+/// Shows the concept art once it is painted, for the player to approve or re-roll.
+fn show_concept_art(
+    mut commands: Commands,
+    mut tasks: Query<&mut ConceptArtTask>,
+    panels: Query<Entity, With<ConceptArtPanel>>,
+    mut ui_state: ResMut<ModelPromptUiState>,
+    asset_server: Res<AssetServer>,
+) {
+    let Ok(panel) = panels.single() else {
+        return;
+    };
+    for mut task in &mut tasks {
+        let Some(result) = future::block_on(future::poll_once(&mut task.0)) else {
+            continue;
+        };
+        commands.entity(panel).despawn_related::<Children>();
+        let concept = match result {
+            Ok(concept) => concept,
+            Err(err) => {
+                error!(?err, "Failed to generate concept art");
+                commands.spawn((
+                    widget::label_small("Couldn't paint the concept art."),
+                    ChildOf(panel),
+                ));
+                commands.spawn((
+                    widget::button_medium("Try Again", reroll_concept_art),
+                    ChildOf(panel),
+                ));
+                continue;
+            }
+        };
+        commands.spawn((
+            Name::new("Concept Art Image"),
+            ImageNode::new(asset_server.load(concept.path.clone())),
+            Node {
+                width: Px(256.0),
+                height: Px(256.0),
+                ..default()
+            },
+            ChildOf(panel),
+        ));
+        commands.spawn((
+            Name::new("Concept Art Buttons"),
+            Node {
+                column_gap: Px(16.0),
+                ..default()
+            },
+            ChildOf(panel),
+            children![
+                widget::button_medium("Approve", approve_concept_art),
+                widget::button_medium("Re-roll", reroll_concept_art),
+            ],
+        ));
+        if let Some(pending) = &mut ui_state.pending_concept {
+            pending.concept = Some(concept);
+        }
+    }
+}
+
+/// Places the prop, which is then modeled from the approved concept art.
+fn approve_concept_art(
+    _: Trigger<Pointer<Click>>,
+    mut commands: Commands,
+    mut ui_state: ResMut<ModelPromptUiState>,
+    crosshair: Option<Single<&mut CrosshairState>>,
+    mut next_pause: ResMut<NextState<Pause>>,
+    mut blocks_input: ResMut<BlocksInput>,
+) {
+    let Some(PendingConcept {
+        prompt,
+        collider_quality,
+        options,
+        concept: Some(concept),
+    }) = ui_state.pending_concept.take()
+    else {
+        return;
+    };
+    commands.trigger(BeginPlacement(PlacementTarget::Prompt {
+        prompt,
+        collider_quality,
+        options,
+        concept: Some(concept),
+    }));
+
+    let mut crosshair = crosshair.map(Single::into_inner);
+    close_model_prompt_ui(
+        &mut commands,
+        &mut ui_state,
+        crosshair.as_deref_mut(),
+        &mut next_pause,
+        &mut blocks_input,
+    );
+}
+
+fn reroll_concept_art(
+    _: Trigger<Pointer<Click>>,
+    mut commands: Commands,
+    mut ui_state: ResMut<ModelPromptUiState>,
+    panel: Single<Entity, With<ConceptArtPanel>>,
+    world: Res<GenerationPrompt>,
+) {
+    if let Some(pending) = &mut ui_state.pending_concept {
+        if let Some(concept) = pending.concept.take() {
+            concept.delete();
+        }
+        paint_concept_art(
+            &mut commands,
+            *panel,
+            pending.prompt.clone(),
+            world.0.clone(),
+        );
+    }
+}
+
+/// Queues a prop for generation once the player has placed it.
+fn start_model_generation(
+    trigger: Trigger<PropPlaced>,
+    mut queue: ResMut<GenerationQueue>,
+    time: Res<Time<Real>>,
+) {
+    let PropPlaced {
+        target:
+            PlacementTarget::Prompt {
+                prompt,
+                collider_quality,
+                options,
+                concept,
+            },
+        transform,
+    } = trigger.event()
+    else {
+        return;
+    };
+    queue.push(
+        prompt.clone(),
+        *collider_quality,
+        options.clone(),
+        concept.clone(),
+        SpawnLocation {
+            transform: *transform,
+        },
+        JobPriority::default(),
+        time.elapsed(),
+    );
+}
+
+/// Starts the queued jobs that fit within the queue's concurrency limit.
+fn start_queued_generations(
+    mut commands: Commands,
+    mut queue: ResMut<GenerationQueue>,
+    mut effects: ResMut<Assets<EffectAsset>>,
+    time: Res<Time<Real>>,
+) {
+    // Only flag the queue as changed if a job actually started, so that its panel isn't rebuilt
+    // every frame.
+    while let Some(job) = queue.bypass_change_detection().start_next(time.elapsed()) {
+        queue.set_changed();
+        info!(
+            prompt = job.prompt,
+            "Starting in-game Meshy generation task"
+        );
+
+        // The placeholder marks the spot without taking on the rotation and scale of the prop.
+        let placeholder = spawn_placeholder(
+            &mut commands,
+            &mut effects,
+            Transform::from_translation(job.spawn_location.transform.translation),
+        );
+        let (first_task_id, stage) = match (job.remote, job.concept.clone()) {
+            (None, None) => {
+                let (prompt, options) = (job.prompt.clone(), job.options.clone());
+                let task = IoTaskPool::get().spawn(async move { start_preview(prompt, options) });
+                (None, GenerationStage::SubmitPreview { task, placeholder })
+            }
+            (None, Some(concept)) => {
+                let options = job.options.clone();
+                let task =
+                    IoTaskPool::get().spawn(async move { start_image_to_3d(concept, options) });
+                (None, GenerationStage::SubmitImage { task, placeholder })
+            }
+            (Some(RemoteJob::Preview { task_id }), _) => {
+                let task = IoTaskPool::get().spawn({
+                    let task_id = task_id.clone();
+                    async move { finish_preview(task_id) }
+                });
+                let stage = GenerationStage::Preview {
+                    task,
+                    placeholder,
+                    refine_task_id: None,
+                };
+                (Some(task_id), stage)
+            }
+            // The preview was downloaded before, so it only has to be loaded to spawn the prop.
+            (
+                Some(RemoteJob::Refine {
+                    generation_id,
+                    preview_task_id,
+                    preview_model,
+                    task_id,
+                }),
+                _,
+            ) => {
+                let collider_quality = job.collider_quality;
+                let task = IoTaskPool::get().spawn({
+                    let preview_task_id = preview_task_id.clone();
+                    async move {
+                        let model = load_model(&preview_model, collider_quality)?;
+                        Ok(ModelPreview {
+                            generation_id,
+                            preview_task_id,
+                            model,
+                        })
+                    }
+                });
+                let stage = GenerationStage::Preview {
+                    task,
+                    placeholder,
+                    refine_task_id: Some(task_id),
+                };
+                (Some(preview_task_id), stage)
+            }
+            (Some(RemoteJob::ImageTo3d { task_id }), _) => {
+                let collider_quality = job.collider_quality;
+                let task = IoTaskPool::get().spawn({
+                    let task_id = task_id.clone();
+                    async move { finish_image_to_3d(task_id, collider_quality) }
+                });
+                (Some(task_id), GenerationStage::Image { task, placeholder })
+            }
+        };
+        commands.spawn((
+            ModelGenerationTask {
+                job: job.id,
+                prompt: job.prompt,
+                collider_quality: job.collider_quality,
+                options: job.options,
+                concept: job.concept,
+                spawn_location: job.spawn_location,
+                first_task_id,
+                stage,
+            },
+            // The journal keeps the job, so it is resumed when the player returns to the world.
+            StateScoped(Screen::ProceduralGameplay),
+        ));
+    }
+}
+
+/// Stops waiting for jobs that were cancelled. Meshy may still finish them, but nobody will look.
+fn stop_cancelled_generations(
+    mut commands: Commands,
+    queue: Res<GenerationQueue>,
+    mut journal: ResMut<GenerationJournal>,
+    mut history: ResMut<EditHistory>,
+    tasks: Query<(Entity, &ModelGenerationTask)>,
+    props: Query<(&PropId, &Transform, &Children), With<GeneratedProp>>,
+) {
+    if !queue.is_changed() {
+        return;
+    }
+    for (entity, task) in &tasks {
+        if queue
+            .get(task.job)
+            .is_some_and(|job| job.status.is_running())
+        {
+            continue;
+        }
+        if let Some(first_task_id) = &task.first_task_id {
+            journal.remove(first_task_id);
+        }
+        keep_preview(task, &props, &mut history);
+        if let GenerationStage::SubmitPreview { placeholder, .. }
+        | GenerationStage::Preview { placeholder, .. }
+        | GenerationStage::SubmitImage { placeholder, .. }
+        | GenerationStage::Image { placeholder, .. } = &task.stage
+        {
+            commands.entity(*placeholder).despawn();
+        }
+        commands.entity(entity).despawn();
+    }
+}
+
+/// Spawns a prop with its preview as soon as that arrives, then swaps in the refined model. Props
+/// modeled from concept art have no preview and spawn with their final model. Every Meshy task is
+/// written to the journal before it is waited for, and every completed prop to the edit history.
 fn monitor_model_generation_tasks(
     mut commands: Commands,
     mut tasks: Query<(Entity, &mut ModelGenerationTask)>,
+    notices: Query<Entity, With<GenerationNotice>>,
+    props: Query<(&PropId, &Transform, &Children), With<GeneratedProp>>,
+    model_parts: Query<(), With<GeneratedPropModel>>,
     asset_server: Res<AssetServer>,
+    mut library: ResMut<PropLibrary>,
+    mut queue: ResMut<GenerationQueue>,
+    mut journal: ResMut<GenerationJournal>,
+    mut history: ResMut<EditHistory>,
+    world: Res<GenerationPrompt>,
+    time: Res<Time<Real>>,
 ) {
+    let now = time.elapsed();
     for (entity, mut task) in tasks.iter_mut() {
-        if let Some(result) = future::block_on(future::poll_once(&mut task.task)) {
-            let prompt = task.prompt.clone();
-            let spawn_location = task.spawn_location.clone();
-            commands.entity(task.placeholder).despawn();
-            commands.entity(entity).despawn();
+        let ModelGenerationTask {
+            job,
+            prompt,
+            collider_quality,
+            options,
+            concept,
+            spawn_location,
+            first_task_id,
+            stage,
+        } = &mut *task;
+        let (job, prompt, collider_quality) = (*job, prompt.as_str(), *collider_quality);
+        let result = match stage {
+            GenerationStage::SubmitPreview {
+                task: submit_task,
+                placeholder,
+            } => {
+                let Some(result) = future::block_on(future::poll_once(submit_task)) else {
+                    continue;
+                };
+                let placeholder = *placeholder;
+                match result {
+                    Ok(task_id) => {
+                        let remote = RemoteJob::Preview {
+                            task_id: task_id.clone(),
+                        };
+                        journal.record(
+                            &world.0,
+                            prompt,
+                            collider_quality,
+                            options,
+                            concept.as_ref(),
+                            spawn_location,
+                            remote.clone(),
+                        );
+                        queue.set_remote(job, Some(remote));
+                        *first_task_id = Some(task_id.clone());
+                        *stage = GenerationStage::Preview {
+                            task: IoTaskPool::get().spawn(async move { finish_preview(task_id) }),
+                            placeholder,
+                            refine_task_id: None,
+                        };
+                        Ok(())
+                    }
+                    Err(err) => {
+                        commands.entity(placeholder).despawn();
+                        Err(err)
+                    }
+                }
+            }
+            GenerationStage::Preview {
+                task: preview_task,
+                placeholder,
+                refine_task_id,
+            } => {
+                let Some(result) = future::block_on(future::poll_once(preview_task)) else {
+                    continue;
+                };
+                commands.entity(*placeholder).despawn();
+                let refine_task_id = refine_task_id.take();
+                result.map(|preview| {
+                    let prop = spawn_generated_prop(
+                        &mut commands,
+                        PropId::new(),
+                        spawn_location.clone(),
+                        prompt,
+                        collider_quality,
+                    );
+                    spawn_prop_model(&mut commands, &asset_server, prop, &preview.model, prompt);
+                    queue.set_prop(job, prop);
 
-            match result {
-                Ok(paths) => spawn_generated_model(
-                    &mut commands,
-                    &asset_server,
-                    spawn_location,
-                    &paths,
-                    &prompt,
-                ),
-                Err(err) => error!(prompt, ?err, "Failed to generate Meshy model"),
+                    *stage = match refine_task_id {
+                        Some(refine_task_id) => GenerationStage::Refine {
+                            preview: preview.model.clone(),
+                            task: IoTaskPool::get().spawn(async move {
+                                finish_refine(preview, refine_task_id, collider_quality)
+                            }),
+                            prop,
+                        },
+                        None => GenerationStage::SubmitRefine {
+                            task: IoTaskPool::get().spawn({
+                                let preview_task_id = preview.preview_task_id.clone();
+                                let options = options.clone();
+                                async move { start_refine(preview_task_id, options) }
+                            }),
+                            prop,
+                            preview,
+                        },
+                    };
+                    queue.set_status(job, JobStatus::Refining, now);
+                })
+            }
+            GenerationStage::SubmitRefine {
+                task: submit_task,
+                prop,
+                preview,
+            } => {
+                let Some(result) = future::block_on(future::poll_once(submit_task)) else {
+                    continue;
+                };
+                let (prop, preview) = (*prop, preview.clone());
+                result.map(|task_id| {
+                    let remote = RemoteJob::Refine {
+                        generation_id: preview.generation_id.clone(),
+                        preview_task_id: preview.preview_task_id.clone(),
+                        preview_model: preview.model.path.clone(),
+                        task_id: task_id.clone(),
+                    };
+                    journal.record(
+                        &world.0,
+                        prompt,
+                        collider_quality,
+                        options,
+                        concept.as_ref(),
+                        spawn_location,
+                        remote.clone(),
+                    );
+                    queue.set_remote(job, Some(remote));
+                    *stage = GenerationStage::Refine {
+                        preview: preview.model.clone(),
+                        task: IoTaskPool::get().spawn(async move {
+                            finish_refine(preview, task_id, collider_quality)
+                        }),
+                        prop,
+                    };
+                })
+            }
+            GenerationStage::Refine {
+                task: refine_task,
+                prop,
+                ..
+            } => {
+                let Some(result) = future::block_on(future::poll_once(refine_task)) else {
+                    continue;
+                };
+                let prop = *prop;
+                result.map(|refined| {
+                    commands.entity(entity).despawn();
+                    if let Some(first_task_id) = first_task_id {
+                        journal.remove(first_task_id);
+                    }
+                    if let Some(err) = &refined.refined_error {
+                        show_generation_notice(
+                            &mut commands,
+                            &notices,
+                            format!("The textured model was broken ({err}); keeping the preview."),
+                        );
+                    }
+                    queue.set_status(job, JobStatus::Completed, now);
+                    record_in_library(&mut library, &refined, prompt, collider_quality);
+                    // The prop may have left the world while it was being refined.
+                    let Ok((id, transform, children)) = props.get(prop) else {
+                        return;
+                    };
+                    // Undoing this removes the prop, and redoing it brings it back refined.
+                    history.push(WorldEdit::Spawn(PropRecord::new(
+                        *id,
+                        prompt,
+                        &refined.model,
+                        collider_quality,
+                        *transform,
+                    )));
+
+                    // Only the model is replaced. The prop itself keeps its transform and
+                    // velocity, even while the player holds it.
+                    for child in children.iter().filter(|child| model_parts.contains(*child)) {
+                        commands.entity(child).despawn();
+                    }
+                    spawn_prop_model(&mut commands, &asset_server, prop, &refined.model, prompt);
+                    info!(prompt, model = %refined.model.path, "Swapped in refined Meshy model");
+                })
             }
+            GenerationStage::SubmitImage {
+                task: submit_task,
+                placeholder,
+            } => {
+                let Some(result) = future::block_on(future::poll_once(submit_task)) else {
+                    continue;
+                };
+                let placeholder = *placeholder;
+                match result {
+                    Ok(task_id) => {
+                        let remote = RemoteJob::ImageTo3d {
+                            task_id: task_id.clone(),
+                        };
+                        journal.record(
+                            &world.0,
+                            prompt,
+                            collider_quality,
+                            options,
+                            concept.as_ref(),
+                            spawn_location,
+                            remote.clone(),
+                        );
+                        queue.set_remote(job, Some(remote));
+                        *first_task_id = Some(task_id.clone());
+                        *stage = GenerationStage::Image {
+                            task: IoTaskPool::get().spawn(async move {
+                                finish_image_to_3d(task_id, collider_quality)
+                            }),
+                            placeholder,
+                        };
+                        Ok(())
+                    }
+                    Err(err) => {
+                        commands.entity(placeholder).despawn();
+                        Err(err)
+                    }
+                }
+            }
+            GenerationStage::Image {
+                task: image_task,
+                placeholder,
+            } => {
+                let Some(result) = future::block_on(future::poll_once(image_task)) else {
+                    continue;
+                };
+                commands.entity(*placeholder).despawn();
+                result.map(|refined| {
+                    commands.entity(entity).despawn();
+                    if let Some(first_task_id) = first_task_id {
+                        journal.remove(first_task_id);
+                    }
+                    queue.set_status(job, JobStatus::Completed, now);
+                    record_in_library(&mut library, &refined, prompt, collider_quality);
+                    // Only retrying a failed job would model the concept art again.
+                    if let Some(concept) = concept.take() {
+                        concept.delete();
+                    }
+                    let id = PropId::new();
+                    let prop = spawn_generated_prop(
+                        &mut commands,
+                        id,
+                        spawn_location.clone(),
+                        prompt,
+                        collider_quality,
+                    );
+                    spawn_prop_model(&mut commands, &asset_server, prop, &refined.model, prompt);
+                    history.push(WorldEdit::Spawn(PropRecord::new(
+                        id,
+                        prompt,
+                        &refined.model,
+                        collider_quality,
+                        spawn_location.transform,
+                    )));
+                    info!(prompt, model = %refined.model.path, "Spawned Meshy model of concept art");
+                })
+            }
+        };
+
+        // A prop that failed while being refined keeps its preview, so it is still usable.
+        let Err(err) = result else {
+            continue;
+        };
+        error!(prompt, ?err, "Failed to generate Meshy model");
+        show_generation_failure(&mut commands, &notices, prompt, &err);
+        queue.set_status(job, JobStatus::Failed(failure_reason(&err)), now);
+        // The credits for a Meshy task are spent, so unless Meshy gave up on it or made a broken
+        // model, the journal keeps it for the next visit, and retrying continues from it.
+        if meshy_task_failed(&err) {
+            if let Some(first_task_id) = first_task_id {
+                journal.remove(first_task_id);
+            }
+            queue.set_remote(job, None);
         }
+        keep_preview(&task, &props, &mut history);
+        commands.entity(entity).despawn();
     }
 }
 
-fn spawn_generated_model(
+/// Records a prop that stays with its preview because refining it failed or was cancelled, so
+/// that undoing removes it like any other spawned prop.
+fn keep_preview(
+    task: &ModelGenerationTask,
+    props: &Query<(&PropId, &Transform, &Children), With<GeneratedProp>>,
+    history: &mut EditHistory,
+) {
+    let (prop, preview) = match &task.stage {
+        GenerationStage::SubmitRefine { prop, preview, .. } => (*prop, &preview.model),
+        GenerationStage::Refine { prop, preview, .. } => (*prop, preview),
+        _ => return,
+    };
+    // The prop may have left the world while it was being refined.
+    let Ok((id, transform, _)) = props.get(prop) else {
+        return;
+    };
+    history.push(WorldEdit::Spawn(PropRecord::new(
+        *id,
+        &task.prompt,
+        preview,
+        task.collider_quality,
+        *transform,
+    )));
+}
+
+fn record_in_library(
+    library: &mut PropLibrary,
+    refined: &RefinedModel,
+    prompt: &str,
+    collider_quality: ColliderQuality,
+) {
+    library.record(LibraryEntry::new(
+        refined.generation_id.clone(),
+        prompt.to_string(),
+        &refined.model,
+        prop_dimensions(&refined.model, prompt),
+        refined.thumbnail.clone(),
+        collider_quality,
+    ));
+}
+
+fn show_generation_failure(
     commands: &mut Commands,
-    asset_server: &AssetServer,
-    spawn_location: SpawnLocation,
-    paths: &GeneratedModelPaths,
+    notices: &Query<Entity, With<GenerationNotice>>,
     prompt: &str,
+    err: &anyhow::Error,
 ) {
-    let scene_path = format!("{}#Scene0", paths.refined_path);
-    let scene_handle: Handle<Scene> = asset_server.load(scene_path);
+    show_generation_notice(
+        commands,
+        notices,
+        format!("Couldn't generate \"{prompt}\": {}.", failure_reason(err)),
+    );
+}
 
-    let generation_folder = paths.refined_path.split('/').nth(2).unwrap_or("generated");
+/// Whether a generation failed because of its Meshy task itself, instead of e.g. the connection.
+fn meshy_task_failed(err: &anyhow::Error) -> bool {
+    err.chain()
+        .any(|cause| cause.is::<MeshyTaskFailed>() || cause.is::<ModelValidationError>())
+}
 
+/// Why a generation failed, in words the player understands.
+fn failure_reason(err: &anyhow::Error) -> String {
+    err.chain()
+        .find_map(|cause| {
+            cause
+                .downcast_ref::<ModelValidationError>()
+                .map(ToString::to_string)
+                .or_else(|| {
+                    cause
+                        .downcast_ref::<MeshyTaskFailed>()
+                        .map(ToString::to_string)
+                })
+        })
+        .unwrap_or_else(|| "something went wrong, see the log for details".to_string())
+}
+
+/// Tells the player about a generation that didn't go as planned. Replaces the previous notice.
+fn show_generation_notice(
+    commands: &mut Commands,
+    notices: &Query<Entity, With<GenerationNotice>>,
+    text: String,
+) {
+    for notice in notices {
+        commands.entity(notice).despawn();
+    }
     commands.spawn((
-        Name::new(format!("Generated Prop {generation_folder}")),
-        SceneRoot(scene_handle),
-        spawn_location.transform,
-        GlobalTransform::default(),
-        // Add physics components to make the model collidable and pickupable
-        RigidBody::Dynamic, // Dynamic so it can be picked up and moved
-        // Use convex hull collision with proper layers for pickup interaction
-        ColliderConstructorHierarchy::new(ColliderConstructor::ConvexHullFromMesh)
-            .with_default_layers(CollisionLayers::new(CollisionLayer::Prop, LayerMask::ALL))
-            .with_default_density(500.0), // Reasonable weight for generated objects
-        // Enable pickup interaction
-        PreferredPickupRotation(Quat::IDENTITY), // Keep upright when picked up
-        StateScoped(Screen::ProceduralGameplay),
-        GeneratedPropRoot {
-            ground_height: spawn_location.ground_height,
-            adjusted: false,
+        Name::new("Generation Notice"),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Px(20.0),
+            left: Percent(20.0),
+            width: Percent(60.0),
+            justify_content: JustifyContent::Center,
+            padding: UiRect::all(Px(10.0)),
+            ..default()
         },
+        BackgroundColor(SCREEN_BACKGROUND.with_alpha(0.8)),
+        GlobalZIndex(2),
+        Pickable::IGNORE,
+        StateScoped(Screen::ProceduralGameplay),
+        GenerationNotice(Timer::new(NOTICE_DURATION, TimerMode::Once)),
+        children![widget::label_small(text)],
     ));
+}
 
-    info!(
-        prompt,
-        refined = %paths.refined_path,
-        preview = %paths.preview_path,
-        "Spawned Meshy-generated prop in procedural gameplay"
-    );
+fn expire_generation_notices(
+    mut commands: Commands,
+    mut notices: Query<(Entity, &mut GenerationNotice)>,
+    time: Res<Time<Real>>,
+) {
+    for (entity, mut notice) in &mut notices {
+        if notice.0.tick(time.delta()).finished() {
+            commands.entity(entity).despawn();
+        }
+    }
 }
 
 fn cleanup_model_prompt(
@@ -306,7 +1083,78 @@ fn open_model_prompt_ui(
                         width: Px(520.0),
                         ..default()
                     },
-                    children![(TextInput, ModelPromptInput)],
+                    children![(
+                        TextInput,
+                        TextInputInactive(false),
+                        Interaction::None,
+                        ModelPromptInput
+                    )],
+                ),
+                (
+                    Name::new("Model Options Grid"),
+                    Node {
+                        display: Display::Grid,
+                        row_gap: Px(6.0),
+                        column_gap: Px(20.0),
+                        grid_template_columns: RepeatedGridTrack::px(2, 260.0),
+                        ..default()
+                    },
+                    Children::spawn(SpawnWith(|parent: &mut ChildSpawner| {
+                        for option in ModelOption::ALL {
+                            parent.spawn((
+                                widget::label_small(option.name()),
+                                Node {
+                                    justify_self: JustifySelf::End,
+                                    ..default()
+                                },
+                            ));
+                            parent.spawn(model_option_bar(option));
+                        }
+                    })),
+                ),
+                widget::label_small("Texture (optional, click to type)"),
+                (
+                    Name::new("Texture Prompt Input"),
+                    Node {
+                        width: Px(520.0),
+                        ..default()
+                    },
+                    children![(
+                        TextInput,
+                        TextInputValue(ui_state.options.texture_prompt.clone().unwrap_or_default()),
+                        TextInputInactive(true),
+                        Interaction::None,
+                        TexturePromptInput
+                    )],
+                ),
+                (
+                    widget::label_small(collider_quality_text(ui_state.collider_quality)),
+                    ColliderQualityLabel,
+                ),
+                (
+                    Name::new("Concept Art Toggle"),
+                    Node {
+                        align_items: AlignItems::Center,
+                        column_gap: Px(16.0),
+                        ..default()
+                    },
+                    children![
+                        (
+                            widget::label_small(concept_art_text(ui_state.concept_art)),
+                            ConceptArtLabel,
+                        ),
+                        widget::button_small("Toggle", toggle_concept_art),
+                    ],
+                ),
+                (
+                    Name::new("Concept Art"),
+                    Node {
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::Center,
+                        row_gap: Px(10.0),
+                        ..default()
+                    },
+                    ConceptArtPanel,
                 ),
                 widget::label_small("Press Enter to submit. Press M or Esc to close."),
             ],
@@ -343,6 +1191,8 @@ fn close_model_prompt_ui(
     if let Some(root) = ui_state.root.take() {
         commands.entity(root).despawn();
     }
+    // Closing the prompt rejects the concept art.
+    ui_state.reject_pending_concept();
 
     if ui_state.paused_game {
         next_pause.set(Pause(false));
@@ -366,12 +1216,41 @@ struct ModelPromptUiState {
     root: Option<Entity>,
     paused_game: bool,
     blocked_input: bool,
+    /// The collider quality of the next prop, which is kept between prompts.
+    collider_quality: ColliderQuality,
+    /// The options of the next prop. They start out as the world's defaults and are kept between
+    /// prompts.
+    options: ModelOptions,
+    /// Whether props are modeled from concept art the player approves, instead of their prompt.
+    concept_art: bool,
+    /// The prop whose concept art is being painted or waits for approval.
+    pending_concept: Option<PendingConcept>,
+}
+
+/// A prop that is placed once the player approves its concept art.
+struct PendingConcept {
+    prompt: String,
+    collider_quality: ColliderQuality,
+    options: ModelOptions,
+    /// The concept art, once it is painted.
+    concept: Option<ConceptArt>,
 }
 
 impl ModelPromptUiState {
     fn is_open(&self) -> bool {
         self.root.is_some()
     }
+
+    /// Forgets the pending prop, and deletes its concept art, which nothing will use.
+    fn reject_pending_concept(&mut self) {
+        if let Some(concept) = self
+            .pending_concept
+            .take()
+            .and_then(|pending| pending.concept)
+        {
+            concept.delete();
+        }
+    }
 }
 
 #[derive(Component)]
@@ -381,100 +1260,82 @@ struct ModelPromptOverlay;
 struct ModelPromptInput;
 
 #[derive(Component)]
-struct ModelGenerationTask {
-    prompt: String,
-    task: Task<AnyhowResult<GeneratedModelPaths>>,
-    placeholder: Entity,
-    spawn_location: SpawnLocation,
-}
+struct TexturePromptInput;
 
-#[derive(Clone)]
-struct SpawnLocation {
-    transform: Transform,
-    ground_height: f32,
-}
+#[derive(Component)]
+struct ModelOptionLabel(ModelOption);
 
-fn predicted_spawn_location(players: &Query<&GlobalTransform, With<Player>>) -> SpawnLocation {
-    let target_position = players
-        .iter()
-        .next()
-        .map(|player_transform| {
-            let origin = player_transform.translation();
-            let forward = player_transform.forward();
-            Vec3::new(origin.x + forward.x * 2.0, 0.0, origin.z + forward.z * 2.0)
-        })
-        .unwrap_or(Vec3::ZERO);
-
-    let ground_height = sample_terrain_height(target_position.x, target_position.z);
-    SpawnLocation {
-        transform: Transform::from_translation(Vec3::new(
-            target_position.x,
-            ground_height,
-            target_position.z,
-        )),
-        ground_height,
-    }
-}
+#[derive(Component)]
+struct ColliderQualityLabel;
 
 #[derive(Component)]
-struct GeneratedPropRoot {
-    ground_height: f32,
-    adjusted: bool,
-}
+struct ConceptArtLabel;
 
-fn adjust_generated_prop_height(
-    mut roots: Query<(&mut Transform, &Children, &mut GeneratedPropRoot)>,
-    children_query: Query<&Children>,
-    mesh_query: Query<(&GlobalTransform, &Mesh3d)>,
-    meshes: Res<Assets<Mesh>>,
-) {
-    for (mut transform, children, mut prop) in roots.iter_mut() {
-        if prop.adjusted {
-            continue;
-        }
+/// Shows the concept art of the prompted prop.
+#[derive(Component)]
+struct ConceptArtPanel;
 
-        let mut stack: VecDeque<Entity> = VecDeque::new();
-        for child in children.iter() {
-            stack.push_back(child);
-        }
-        let mut min_y: Option<f32> = None;
-
-        while let Some(entity) = stack.pop_front() {
-            if let Ok((global_transform, mesh)) = mesh_query.get(entity) {
-                if let Some(mesh_asset) = meshes.get(&mesh.0) {
-                    if let Some(aabb) = mesh_asset.compute_aabb() {
-                        let center: Vec3 = aabb.center.into();
-                        let half: Vec3 = aabb.half_extents.into();
-                        for sx in [-1.0, 1.0] {
-                            for sy in [-1.0, 1.0] {
-                                for sz in [-1.0, 1.0] {
-                                    let local =
-                                        center + Vec3::new(sx * half.x, sy * half.y, sz * half.z);
-                                    let world = global_transform.transform_point(local);
-                                    min_y = Some(match min_y {
-                                        Some(current) => current.min(world.y),
-                                        None => world.y,
-                                    });
-                                }
-                            }
-                        }
-                    }
-                }
-            }
+/// Paints concept art, in the label that says so.
+#[derive(Component)]
+struct ConceptArtTask(Task<AnyhowResult<ConceptArt>>);
 
-            if let Ok(children) = children_query.get(entity) {
-                for child in children.iter() {
-                    stack.push_back(child);
-                }
-            }
-        }
+/// How long notices about generations stay on screen.
+const NOTICE_DURATION: Duration = Duration::from_secs(8);
 
-        if let Some(min_y) = min_y {
-            let delta = prop.ground_height - min_y;
-            transform.translation.y += delta;
-            prop.adjusted = true;
-        }
-    }
+#[derive(Component)]
+struct GenerationNotice(Timer);
+
+/// A running job of the [`GenerationQueue`].
+#[derive(Component)]
+struct ModelGenerationTask {
+    job: JobId,
+    prompt: String,
+    collider_quality: ColliderQuality,
+    options: ModelOptions,
+    concept: Option<ConceptArt>,
+    spawn_location: SpawnLocation,
+    /// Identifies the job in the [`GenerationJournal`] once Meshy has accepted it.
+    first_task_id: Option<String>,
+    stage: GenerationStage,
+}
+
+enum GenerationStage {
+    /// Asking Meshy for a preview, while a placeholder effect marks where the prop will appear.
+    SubmitPreview {
+        task: Task<AnyhowResult<String>>,
+        placeholder: Entity,
+    },
+    /// Waiting for the preview. A job resumed while it was being refined loads its preview from
+    /// disk instead, and continues with the refine task it had.
+    Preview {
+        task: Task<AnyhowResult<ModelPreview>>,
+        placeholder: Entity,
+        refine_task_id: Option<String>,
+    },
+    /// The prop is in the world with its preview model, asking Meshy to refine it.
+    SubmitRefine {
+        task: Task<AnyhowResult<String>>,
+        prop: Entity,
+        preview: ModelPreview,
+    },
+    /// Waiting for the refined model.
+    Refine {
+        task: Task<AnyhowResult<RefinedModel>>,
+        prop: Entity,
+        /// The model the prop keeps if refining it fails.
+        preview: GeneratedModel,
+    },
+    /// Asking Meshy to model the approved concept art, while a placeholder effect marks where the
+    /// prop will appear.
+    SubmitImage {
+        task: Task<AnyhowResult<String>>,
+        placeholder: Entity,
+    },
+    /// Waiting for the model of the concept art. The prop spawns once it arrives.
+    Image {
+        task: Task<AnyhowResult<RefinedModel>>,
+        placeholder: Entity,
+    },
 }
 
 fn spawn_placeholder(
@@ -544,7 +1405,3 @@ fn spawn_placeholder(
         ))
         .id()
 }
-
-/// Marker component for generated models that need collision setup
-#[derive(Component)]
-struct GeneratedModel;
@@ -2,14 +2,25 @@
 
 use bevy::{
     asset::LoadState,
+    platform::collections::HashMap,
     prelude::*,
     tasks::{IoTaskPool, Task},
 };
 use futures_lite::future;
 
 use crate::{
-    gameplay::procedural_level::{ProceduralLevelAssets, spawn_procedural_level},
-    generate::{generate_ground::generate_ground_texture, generate_sky::generate_sky_texture},
+    gameplay::{
+        procedural_level::{ProceduralLevelAssets, spawn_procedural_level},
+        sky_lighting::SkyLighting,
+        terrain_material::{TerrainMaterial, terrain_material},
+        vegetation::VegetationSettings,
+        world_plan::WorldPlan,
+    },
+    generate::{
+        generate_audio::{WorldAudio, generate_world_audio},
+        generate_ground::{GeneratedGroundTexture, TerrainLayer, generate_ground_texture},
+        generate_sky::{GeneratedSkyTexture, generate_sky_texture},
+    },
     menus::{Menu, generate::GenerationPrompt},
     screens::Screen,
     theme::{palette::SCREEN_BACKGROUND, prelude::*},
@@ -47,6 +58,8 @@ fn start_generation_tasks(
     prompt: Res<GenerationPrompt>,
     mut progress: ResMut<GenerationProgress>,
     existing_tasks: Query<Entity, With<GenerationTask>>,
+    mut materials: ResMut<Assets<TerrainMaterial>>,
+    mut procedural_assets: ResMut<ProceduralLevelAssets>,
 ) {
     for entity in &existing_tasks {
         commands.entity(entity).despawn();
@@ -54,22 +67,47 @@ fn start_generation_tasks(
 
     let base_prompt = prompt.0.clone();
 
-    progress.ground = GenerationStatus::InProgress;
+    // The layer textures are filled in one by one as their generation finishes.
+    procedural_assets.ground_material = materials.add(terrain_material());
     progress.sky = GenerationStatus::InProgress;
+    progress.audio = GenerationStatus::InProgress;
 
-    info!("starting ground generation for prompt: {}", base_prompt);
-    let ground_task = IoTaskPool::get().spawn({
-        let prompt = base_prompt.clone();
-        async move { generate_ground_texture(prompt) }
-    });
-    commands.spawn(GenerationTask::new(GenerationKind::Ground, ground_task));
+    for layer in TerrainLayer::ALL {
+        progress
+            .terrain_layers
+            .insert(layer, GenerationStatus::InProgress);
+
+        info!(
+            ?layer,
+            "starting ground generation for prompt: {}", base_prompt
+        );
+        let ground_task = IoTaskPool::get().spawn({
+            let prompt = base_prompt.clone();
+            async move {
+                generate_ground_texture(prompt, layer)
+                    .map(|ground| GenerationResult::Terrain(layer, ground))
+            }
+        });
+        commands.spawn(GenerationTask::new(
+            GenerationKind::Terrain(layer),
+            ground_task,
+        ));
+    }
 
     info!("starting sky generation for prompt: {}", base_prompt);
     let sky_task = IoTaskPool::get().spawn({
         let prompt = base_prompt.clone();
-        async move { generate_sky_texture(prompt) }
+        async move { generate_sky_texture(prompt).map(GenerationResult::Sky) }
     });
     commands.spawn(GenerationTask::new(GenerationKind::Sky, sky_task));
+
+    info!("starting audio generation for prompt: {}", base_prompt);
+    let audio_task = IoTaskPool::get().spawn({
+        let prompt = base_prompt.clone();
+        // Audio generation falls back to bundled tracks on its own, so it never fails.
+        async move { Ok(GenerationResult::Audio(generate_world_audio(prompt))) }
+    });
+    commands.spawn(GenerationTask::new(GenerationKind::Audio, audio_task));
 }
 
 fn monitor_generation_tasks(
@@ -77,7 +115,7 @@ fn monitor_generation_tasks(
     mut tasks: Query<(Entity, &mut GenerationTask)>,
     mut progress: ResMut<GenerationProgress>,
     asset_server: Res<AssetServer>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut materials: ResMut<Assets<TerrainMaterial>>,
     mut procedural_assets: ResMut<ProceduralLevelAssets>,
 ) {
     for (entity, mut task) in tasks.iter_mut() {
@@ -86,21 +124,20 @@ fn monitor_generation_tasks(
             commands.entity(entity).despawn();
 
             match (kind, result) {
-                (GenerationKind::Ground, Ok(path)) => {
-                    let texture: Handle<Image> = asset_server.load(path.clone());
-                    let material = materials.add(StandardMaterial {
-                        base_color_texture: Some(texture.clone()),
-                        perceptual_roughness: 0.9,
-                        metallic: 0.0,
-                        ..default()
-                    });
-
-                    procedural_assets.ground_material = material.clone();
-                    progress.ground =
-                        GenerationStatus::Succeeded(GeneratedGround { material, texture });
+                (_, Ok(GenerationResult::Terrain(layer, ground))) => {
+                    let texture: Handle<Image> = asset_server.load(ground.path.clone());
+                    if let Some(material) = materials.get_mut(&procedural_assets.ground_material) {
+                        *material.extension.layer_texture_mut(layer) = Some(texture.clone());
+                    }
+                    if layer == TerrainLayer::Ground {
+                        progress.ground_color = Some(ground.average_color);
+                    }
+                    progress
+                        .terrain_layers
+                        .insert(layer, GenerationStatus::Succeeded(texture));
                 }
-                (GenerationKind::Sky, Ok(path)) => {
-                    let texture: Handle<Image> = asset_server.load(path.clone());
+                (_, Ok(GenerationResult::Sky(sky))) => {
+                    let texture: Handle<Image> = asset_server.load(sky.path.clone());
 
                     procedural_assets.env_map_specular = texture.clone();
                     procedural_assets.env_map_diffuse = texture.clone();
@@ -108,16 +145,40 @@ fn monitor_generation_tasks(
                         "Sky generation succeeded; updated env map handles (specular: {:?})",
                         texture
                     );
-                    progress.sky = GenerationStatus::Succeeded(GeneratedSky { texture });
+                    progress.sky = GenerationStatus::Succeeded(GeneratedSky {
+                        texture,
+                        lighting: sky.analysis.into(),
+                    });
+                }
+                (_, Ok(GenerationResult::Audio(audio))) => {
+                    procedural_assets.music = audio
+                        .music
+                        .as_ref()
+                        .map(|path| asset_server.load(path.clone()));
+                    procedural_assets.ambience = asset_server.load(audio.ambience.clone());
+                    info!(?audio, "world audio is ready");
+                    progress.audio = GenerationStatus::Succeeded(());
                 }
-                (GenerationKind::Ground, Err(err)) => {
-                    error!("failed to generate ground texture: {err:?}");
-                    progress.ground = GenerationStatus::Failed(err.to_string());
+                (GenerationKind::Terrain(layer), Err(err)) => {
+                    if layer == TerrainLayer::Ground {
+                        error!("failed to generate ground texture: {err:?}");
+                    } else {
+                        // The terrain can fall back to the ground texture for this layer.
+                        warn!(?layer, "failed to generate terrain layer texture: {err:?}");
+                    }
+                    progress
+                        .terrain_layers
+                        .insert(layer, GenerationStatus::Failed(err.to_string()));
                 }
                 (GenerationKind::Sky, Err(err)) => {
                     error!("failed to generate sky texture: {err:?}");
                     progress.sky = GenerationStatus::Failed(err.to_string());
                 }
+                (GenerationKind::Audio, Err(err)) => {
+                    // Keep the default ambience.
+                    warn!("failed to generate world audio: {err:?}");
+                    progress.audio = GenerationStatus::Failed(err.to_string());
+                }
             }
         }
     }
@@ -129,23 +190,44 @@ fn advance_to_procedural_gameplay_screen(
     progress: Res<GenerationProgress>,
     procedural_assets: Option<Res<ProceduralLevelAssets>>,
     asset_server: Res<AssetServer>,
+    mut materials: ResMut<Assets<TerrainMaterial>>,
+    prompt: Res<GenerationPrompt>,
+    mut commands: Commands,
 ) {
-    match (&progress.ground, &progress.sky) {
+    match (progress.terrain_layer(TerrainLayer::Ground), &progress.sky) {
         (GenerationStatus::Failed(reason), _) | (_, GenerationStatus::Failed(reason)) => {
             warn!("generation failed: {reason}");
             next_screen.set(Screen::Title);
             next_menu.set(Menu::Main);
         }
-        (GenerationStatus::Succeeded(ground), GenerationStatus::Succeeded(sky)) => {
+        (GenerationStatus::Succeeded(_), GenerationStatus::Succeeded(sky))
+            if progress.all_terrain_layers_finished() && progress.audio_finished() =>
+        {
             if let Some(assets) = procedural_assets {
+                let layer_textures =
+                    progress
+                        .terrain_layers
+                        .values()
+                        .filter_map(|status| match status {
+                            GenerationStatus::Succeeded(texture) => Some(texture.id()),
+                            _ => None,
+                        });
                 let states = [
-                    asset_server.get_load_state(assets.music.id()),
+                    asset_server.get_load_state(assets.ambience.id()),
                     asset_server.get_load_state(assets.env_map_specular.id()),
                     asset_server.get_load_state(assets.env_map_diffuse.id()),
-                    asset_server.get_load_state(ground.material.id()),
-                    asset_server.get_load_state(ground.texture.id()),
+                    asset_server.get_load_state(assets.ground_material.id()),
                     asset_server.get_load_state(sky.texture.id()),
-                ];
+                ]
+                .into_iter()
+                .chain(layer_textures.map(|id| asset_server.get_load_state(id)))
+                .chain(
+                    assets
+                        .music
+                        .as_ref()
+                        .map(|music| asset_server.get_load_state(music.id())),
+                )
+                .collect::<Vec<_>>();
 
                 if states
                     .iter()
@@ -162,6 +244,17 @@ fn advance_to_procedural_gameplay_screen(
                     .all(|state| matches!(state, Some(LoadState::Loaded)) || state.is_none());
 
                 if all_loaded {
+                    if let Some(material) = materials.get_mut(&assets.ground_material) {
+                        material.extension.fill_missing_layers();
+                    }
+                    commands.insert_resource(sky.lighting.clone());
+                    commands.insert_resource(WorldPlan::from_prompt(&prompt.0));
+                    if let Some(ground_color) = progress.ground_color {
+                        commands.insert_resource(VegetationSettings::from_prompt(
+                            &prompt.0,
+                            ground_color,
+                        ));
+                    }
                     next_screen.set(Screen::ProceduralGameplay);
                 }
             }
@@ -177,45 +270,76 @@ fn reset_generation_progress(mut progress: ResMut<GenerationProgress>) {
 #[derive(Component)]
 struct GenerationTask {
     kind: GenerationKind,
-    task: Task<anyhow::Result<String>>,
+    task: Task<anyhow::Result<GenerationResult>>,
 }
 
 impl GenerationTask {
-    fn new(kind: GenerationKind, task: Task<anyhow::Result<String>>) -> Self {
+    fn new(kind: GenerationKind, task: Task<anyhow::Result<GenerationResult>>) -> Self {
         Self { kind, task }
     }
 }
 
+enum GenerationResult {
+    Terrain(TerrainLayer, GeneratedGroundTexture),
+    Sky(GeneratedSkyTexture),
+    Audio(WorldAudio),
+}
+
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
 enum GenerationKind {
-    Ground,
+    Terrain(TerrainLayer),
     Sky,
+    Audio,
 }
 
 #[derive(Resource, Debug, Clone)]
 struct GenerationProgress {
-    ground: GenerationStatus<GeneratedGround>,
+    terrain_layers: HashMap<TerrainLayer, GenerationStatus<Handle<Image>>>,
+    /// The average color of the generated ground layer.
+    ground_color: Option<Color>,
     sky: GenerationStatus<GeneratedSky>,
+    audio: GenerationStatus<()>,
 }
 
 impl Default for GenerationProgress {
     fn default() -> Self {
         Self {
-            ground: GenerationStatus::Pending,
+            terrain_layers: HashMap::default(),
+            ground_color: None,
             sky: GenerationStatus::Pending,
+            audio: GenerationStatus::Pending,
         }
     }
 }
 
-#[derive(Debug, Clone)]
-struct GeneratedGround {
-    material: Handle<StandardMaterial>,
-    texture: Handle<Image>,
+impl GenerationProgress {
+    fn terrain_layer(&self, layer: TerrainLayer) -> &GenerationStatus<Handle<Image>> {
+        self.terrain_layers
+            .get(&layer)
+            .unwrap_or(&GenerationStatus::Pending)
+    }
+
+    fn audio_finished(&self) -> bool {
+        matches!(
+            self.audio,
+            GenerationStatus::Succeeded(_) | GenerationStatus::Failed(_)
+        )
+    }
+
+    fn all_terrain_layers_finished(&self) -> bool {
+        TerrainLayer::ALL.iter().all(|layer| {
+            matches!(
+                self.terrain_layer(*layer),
+                GenerationStatus::Succeeded(_) | GenerationStatus::Failed(_)
+            )
+        })
+    }
 }
 
 #[derive(Debug, Clone)]
 struct GeneratedSky {
     texture: Handle<Image>,
+    lighting: SkyLighting,
 }
 
 #[derive(Debug, Clone)]
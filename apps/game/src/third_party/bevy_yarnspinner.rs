@@ -7,28 +7,40 @@ use bevy_trenchbroom::prelude::*;
 use bevy_yarnspinner::{events::DialogueCompleteEvent, prelude::*};
 use bevy_yarnspinner_example_dialogue_view::prelude::*;
 
-use crate::screens::Screen;
+use crate::{gameplay::npc::dialogue::PROCEDURAL_DIALOGUE_PATH, screens::Screen};
 
 pub(super) fn plugin(app: &mut App) {
     app.register_type::<YarnNode>();
 
     app.add_plugins((
         // In Wasm, we need to load the dialogue file manually. If we're not targeting Wasm, we can just use `YarnSpinnerPlugin::default()` instead.
-        YarnSpinnerPlugin::with_yarn_sources(vec![YarnFileSource::file("dialogue/npc.yarn")]),
+        YarnSpinnerPlugin::with_yarn_sources(vec![
+            YarnFileSource::file("dialogue/npc.yarn"),
+            YarnFileSource::file(PROCEDURAL_DIALOGUE_PATH),
+        ]),
         ExampleYarnSpinnerDialogueViewPlugin::default(),
     ));
     app.add_systems(OnEnter(Screen::Gameplay), setup_dialogue_runner);
+    app.add_systems(OnEnter(Screen::ProceduralGameplay), setup_dialogue_runner);
     app.add_systems(
         OnExit(Screen::Gameplay),
         abort_all_dialogues_when_leaving_gameplay,
     );
+    app.add_systems(
+        OnExit(Screen::ProceduralGameplay),
+        abort_all_dialogues_when_leaving_gameplay,
+    );
 }
 
 #[cfg_attr(feature = "hot_patch", hot)]
-fn setup_dialogue_runner(mut commands: Commands, yarn_project: Res<YarnProject>) {
+fn setup_dialogue_runner(
+    mut commands: Commands,
+    yarn_project: Res<YarnProject>,
+    screen: Res<State<Screen>>,
+) {
     let dialogue_runner = yarn_project.create_dialogue_runner(&mut commands);
     commands.spawn((
-        StateScoped(Screen::Gameplay),
+        StateScoped(*screen.get()),
         Name::new("Dialogue Runner"),
         dialogue_runner,
     ));
@@ -0,0 +1,86 @@
+use async_trait::async_trait;
+use reqwest::header::CONTENT_TYPE;
+use serde_json::json;
+
+use crate::error::{GenerativeError, GenerativeResult};
+
+use super::{AudioFormat, AudioGenerationRequest, AudioGenerator, GeneratedAudio};
+
+const DEFAULT_BASE_URL: &str = "http://127.0.0.1:8765";
+
+/// Generates audio through a locally hosted model server, e.g. a MusicGen or Stable Audio wrapper.
+///
+/// The server is expected to answer `POST {base_url}/generate` with a JSON body of the form
+/// `{"prompt": "...", "kind": "music" | "ambience", "duration": 30.0, "loop": true}`
+/// by returning the raw audio file, with its `Content-Type` set to the audio format.
+#[derive(Clone)]
+pub struct LocalAudioGenerator {
+    client: reqwest::Client,
+    base_url: String,
+}
+
+impl LocalAudioGenerator {
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+        }
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+}
+
+impl Default for LocalAudioGenerator {
+    fn default() -> Self {
+        Self::new(DEFAULT_BASE_URL)
+    }
+}
+
+#[async_trait]
+impl AudioGenerator for LocalAudioGenerator {
+    async fn generate_audio(
+        &self,
+        request: &AudioGenerationRequest,
+    ) -> GenerativeResult<GeneratedAudio> {
+        let response = self
+            .client
+            .post(format!("{}/generate", self.base_url))
+            .json(&json!({
+                "prompt": request.prompt,
+                "kind": request.kind.name(),
+                "duration": request.duration_seconds,
+                "loop": request.loopable,
+            }))
+            .send()
+            .await?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(GenerativeError::LocalServer {
+                status: status.as_u16(),
+                body,
+            });
+        }
+
+        // Servers that don't say otherwise are assumed to send WAV, the most common output of
+        // audio models.
+        let format = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(AudioFormat::from_mime_type)
+            .unwrap_or(AudioFormat::Wav);
+        let bytes = response.bytes().await?;
+        if bytes.is_empty() {
+            return Err(GenerativeError::MissingAudioData);
+        }
+
+        Ok(GeneratedAudio {
+            bytes: bytes.to_vec(),
+            format,
+        })
+    }
+}
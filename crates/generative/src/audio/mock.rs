@@ -0,0 +1,63 @@
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+
+use crate::{error::GenerativeResult, wav::encode_wav};
+
+use super::{AudioFormat, AudioGenerationRequest, AudioGenerator, AudioKind, GeneratedAudio};
+
+const SAMPLE_RATE: u32 = 8_000;
+
+/// An audio generator that works offline, for tests and development without a model server.
+///
+/// It returns a quiet drone of the requested length that loops seamlessly, and records every
+/// request it receives.
+#[derive(Default)]
+pub struct MockAudioGenerator {
+    requests: Mutex<Vec<AudioGenerationRequest>>,
+}
+
+impl MockAudioGenerator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The requests received so far, oldest first.
+    pub fn requests(&self) -> Vec<AudioGenerationRequest> {
+        self.requests
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .clone()
+    }
+}
+
+#[async_trait]
+impl AudioGenerator for MockAudioGenerator {
+    async fn generate_audio(
+        &self,
+        request: &AudioGenerationRequest,
+    ) -> GenerativeResult<GeneratedAudio> {
+        self.requests
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .push(request.clone());
+
+        // Whole seconds of a whole-numbered frequency always end where they started.
+        let seconds = request.duration_seconds.round().max(1.0) as u32;
+        let frequency = match request.kind {
+            AudioKind::Music => 220.0,
+            AudioKind::Ambience => 110.0,
+        };
+        let samples = (0..seconds * SAMPLE_RATE)
+            .map(|n| {
+                let t = n as f32 / SAMPLE_RATE as f32;
+                ((std::f32::consts::TAU * frequency * t).sin() * 0.1 * i16::MAX as f32) as i16
+            })
+            .collect::<Vec<_>>();
+
+        Ok(GeneratedAudio {
+            bytes: encode_wav(&samples, SAMPLE_RATE),
+            format: AudioFormat::Wav,
+        })
+    }
+}
@@ -0,0 +1,99 @@
+mod local;
+mod mock;
+
+pub use local::LocalAudioGenerator;
+pub use mock::MockAudioGenerator;
+
+use async_trait::async_trait;
+
+use crate::error::GenerativeResult;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AudioKind {
+    /// A musical score.
+    Music,
+    /// Environmental background sound, like wind, rain or machinery.
+    Ambience,
+}
+
+impl AudioKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            AudioKind::Music => "music",
+            AudioKind::Ambience => "ambience",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AudioFormat {
+    Wav,
+    Ogg,
+    Mp3,
+    Flac,
+}
+
+impl AudioFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            AudioFormat::Wav => "wav",
+            AudioFormat::Ogg => "ogg",
+            AudioFormat::Mp3 => "mp3",
+            AudioFormat::Flac => "flac",
+        }
+    }
+
+    pub fn from_mime_type(mime_type: &str) -> Option<Self> {
+        match mime_type.split(';').next()?.trim() {
+            "audio/wav" | "audio/wave" | "audio/x-wav" => Some(AudioFormat::Wav),
+            "audio/ogg" | "audio/vorbis" => Some(AudioFormat::Ogg),
+            "audio/mpeg" | "audio/mp3" => Some(AudioFormat::Mp3),
+            "audio/flac" | "audio/x-flac" => Some(AudioFormat::Flac),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AudioGenerationRequest {
+    pub prompt: String,
+    pub kind: AudioKind,
+    pub duration_seconds: f32,
+    /// Whether the end of the audio should flow seamlessly into its start.
+    pub loopable: bool,
+}
+
+impl AudioGenerationRequest {
+    pub fn new(prompt: impl Into<String>, kind: AudioKind) -> Self {
+        Self {
+            prompt: prompt.into(),
+            kind,
+            duration_seconds: 30.0,
+            loopable: true,
+        }
+    }
+
+    pub fn with_duration(mut self, seconds: f32) -> Self {
+        self.duration_seconds = seconds.clamp(1.0, 300.0);
+        self
+    }
+
+    pub fn with_loopable(mut self, loopable: bool) -> Self {
+        self.loopable = loopable;
+        self
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct GeneratedAudio {
+    pub bytes: Vec<u8>,
+    pub format: AudioFormat,
+}
+
+#[async_trait]
+pub trait AudioGenerator: Send + Sync {
+    async fn generate_audio(
+        &self,
+        request: &AudioGenerationRequest,
+    ) -> GenerativeResult<GeneratedAudio>;
+}
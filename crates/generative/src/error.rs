@@ -8,6 +8,18 @@ pub enum GenerativeError {
     #[error("OpenAI request failed: {0}")]
     OpenAi(#[from] OpenAIError),
 
+    #[error("HTTP request failed: {0}")]
+    Http(#[from] reqwest::Error),
+
+    #[error("Local model server responded with status {status}: {body}")]
+    LocalServer { status: u16, body: String },
+
     #[error("Image response did not include expected data")]
     MissingImageData,
+
+    #[error("Text response did not include any content")]
+    MissingTextData,
+
+    #[error("Speech response did not include any audio")]
+    MissingAudioData,
 }
@@ -1,8 +1,24 @@
+pub mod audio;
 pub mod error;
 pub mod image;
+pub mod speech;
+pub mod text;
+mod wav;
 
+pub use audio::{
+    AudioFormat, AudioGenerationRequest, AudioGenerator, AudioKind, GeneratedAudio,
+    LocalAudioGenerator, MockAudioGenerator,
+};
 pub use error::{GenerativeError, GenerativeResult};
 pub use image::{
     GeneratedImage, ImageData, ImageGenerationRequest, ImageGenerationResult, ImageGenerator,
     ImageOutputFormat, ImageSize, OpenAiImageGenerator,
 };
+pub use speech::{
+    GeneratedSpeech, OpenAiSpeechGenerator, SpeechGenerationRequest, SpeechGenerator, SpeechVoice,
+    StubSpeechGenerator,
+};
+pub use text::{
+    ChatMessage, ChatRole, MockTextGenerator, OpenAiTextGenerator, TextGenerationRequest,
+    TextGenerationResult, TextGenerator,
+};
@@ -0,0 +1,103 @@
+mod openai;
+mod stub;
+
+pub use openai::OpenAiSpeechGenerator;
+pub use stub::StubSpeechGenerator;
+
+use async_openai::types::Voice as OpenAiVoice;
+use async_trait::async_trait;
+
+use crate::error::GenerativeResult;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum SpeechVoice {
+    #[default]
+    Alloy,
+    Ash,
+    Coral,
+    Echo,
+    Fable,
+    Onyx,
+    Nova,
+    Sage,
+    Shimmer,
+}
+
+impl SpeechVoice {
+    pub const ALL: [SpeechVoice; 9] = [
+        SpeechVoice::Alloy,
+        SpeechVoice::Ash,
+        SpeechVoice::Coral,
+        SpeechVoice::Echo,
+        SpeechVoice::Fable,
+        SpeechVoice::Onyx,
+        SpeechVoice::Nova,
+        SpeechVoice::Sage,
+        SpeechVoice::Shimmer,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SpeechVoice::Alloy => "alloy",
+            SpeechVoice::Ash => "ash",
+            SpeechVoice::Coral => "coral",
+            SpeechVoice::Echo => "echo",
+            SpeechVoice::Fable => "fable",
+            SpeechVoice::Onyx => "onyx",
+            SpeechVoice::Nova => "nova",
+            SpeechVoice::Sage => "sage",
+            SpeechVoice::Shimmer => "shimmer",
+        }
+    }
+
+    pub fn as_openai_voice(&self) -> OpenAiVoice {
+        match self {
+            SpeechVoice::Alloy => OpenAiVoice::Alloy,
+            SpeechVoice::Ash => OpenAiVoice::Ash,
+            SpeechVoice::Coral => OpenAiVoice::Coral,
+            SpeechVoice::Echo => OpenAiVoice::Echo,
+            SpeechVoice::Fable => OpenAiVoice::Fable,
+            SpeechVoice::Onyx => OpenAiVoice::Onyx,
+            SpeechVoice::Nova => OpenAiVoice::Nova,
+            SpeechVoice::Sage => OpenAiVoice::Sage,
+            SpeechVoice::Shimmer => OpenAiVoice::Shimmer,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SpeechGenerationRequest {
+    pub text: String,
+    pub voice: SpeechVoice,
+    /// Playback speed from 0.25 to 4.0, where 1.0 is normal speed.
+    pub speed: Option<f32>,
+}
+
+impl SpeechGenerationRequest {
+    pub fn new(text: impl Into<String>, voice: SpeechVoice) -> Self {
+        Self {
+            text: text.into(),
+            voice,
+            speed: None,
+        }
+    }
+
+    pub fn with_speed(mut self, speed: f32) -> Self {
+        self.speed = Some(speed.clamp(0.25, 4.0));
+        self
+    }
+}
+
+/// Synthesised speech as the bytes of a WAV file.
+#[derive(Debug, Clone)]
+pub struct GeneratedSpeech {
+    pub wav: Vec<u8>,
+}
+
+#[async_trait]
+pub trait SpeechGenerator: Send + Sync {
+    async fn generate_speech(
+        &self,
+        request: &SpeechGenerationRequest,
+    ) -> GenerativeResult<GeneratedSpeech>;
+}
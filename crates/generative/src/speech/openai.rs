@@ -0,0 +1,89 @@
+use async_openai::Client;
+use async_openai::config::OpenAIConfig;
+use async_openai::types::{
+    CreateSpeechRequest, CreateSpeechRequestArgs, SpeechModel, SpeechResponseFormat,
+};
+
+use crate::error::{GenerativeError, GenerativeResult};
+
+use super::{GeneratedSpeech, SpeechGenerationRequest, SpeechGenerator};
+
+#[derive(Clone)]
+pub struct OpenAiSpeechGenerator<C = OpenAIConfig>
+where
+    C: async_openai::config::Config,
+{
+    client: Client<C>,
+    model: SpeechModel,
+}
+
+impl<C> OpenAiSpeechGenerator<C>
+where
+    C: async_openai::config::Config + Clone + Send + Sync + 'static,
+{
+    pub fn new(client: Client<C>) -> Self {
+        Self {
+            client,
+            model: SpeechModel::Tts1,
+        }
+    }
+
+    pub fn with_model(client: Client<C>, model: SpeechModel) -> Self {
+        Self { client, model }
+    }
+
+    pub fn client(&self) -> &Client<C> {
+        &self.client
+    }
+
+    pub fn model(&self) -> &SpeechModel {
+        &self.model
+    }
+}
+
+impl Default for OpenAiSpeechGenerator<OpenAIConfig> {
+    fn default() -> Self {
+        Self::new(Client::new())
+    }
+}
+
+#[async_trait::async_trait]
+impl<C> SpeechGenerator for OpenAiSpeechGenerator<C>
+where
+    C: async_openai::config::Config + Clone + Send + Sync + 'static,
+{
+    async fn generate_speech(
+        &self,
+        request: &SpeechGenerationRequest,
+    ) -> GenerativeResult<GeneratedSpeech> {
+        let response = self
+            .client
+            .audio()
+            .speech(build_request(request, &self.model)?)
+            .await?;
+
+        if response.bytes.is_empty() {
+            return Err(GenerativeError::MissingAudioData);
+        }
+        Ok(GeneratedSpeech {
+            wav: response.bytes.to_vec(),
+        })
+    }
+}
+
+fn build_request(
+    request: &SpeechGenerationRequest,
+    model: &SpeechModel,
+) -> GenerativeResult<CreateSpeechRequest> {
+    let mut builder = CreateSpeechRequestArgs::default();
+    builder
+        .input(request.text.clone())
+        .model(model.clone())
+        .voice(request.voice.as_openai_voice())
+        .response_format(SpeechResponseFormat::Wav);
+    if let Some(speed) = request.speed {
+        builder.speed(speed);
+    }
+
+    builder.build().map_err(GenerativeError::from)
+}
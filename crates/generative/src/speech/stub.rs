@@ -0,0 +1,68 @@
+use async_trait::async_trait;
+
+use crate::{error::GenerativeResult, wav::encode_wav};
+
+use super::{GeneratedSpeech, SpeechGenerationRequest, SpeechGenerator, SpeechVoice};
+
+const SAMPLE_RATE: u32 = 22_050;
+const SYLLABLE_SECONDS: f32 = 0.09;
+const PAUSE_SECONDS: f32 = 0.04;
+const AMPLITUDE: f32 = 0.25;
+
+/// A speech generator that works offline, for tests and development without an API key.
+///
+/// Instead of words it "speaks" a short beep per syllable-ish chunk of the text, pitched by voice,
+/// so that lines still get audio with a plausible length.
+#[derive(Debug, Clone, Default)]
+pub struct StubSpeechGenerator;
+
+impl StubSpeechGenerator {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+#[async_trait]
+impl SpeechGenerator for StubSpeechGenerator {
+    async fn generate_speech(
+        &self,
+        request: &SpeechGenerationRequest,
+    ) -> GenerativeResult<GeneratedSpeech> {
+        let speed = request.speed.unwrap_or(1.0);
+        let base_pitch = base_pitch(request.voice);
+        let syllable_len = (SAMPLE_RATE as f32 * SYLLABLE_SECONDS / speed) as usize;
+        let pause_len = (SAMPLE_RATE as f32 * PAUSE_SECONDS / speed) as usize;
+
+        let mut samples = Vec::new();
+        for (i, word) in request.text.split_whitespace().enumerate() {
+            let syllables = word
+                .chars()
+                .filter(|c| c.is_alphanumeric())
+                .count()
+                .div_ceil(3);
+            for syllable in 0..syllables.max(1) {
+                // Wobble the pitch a little so the babble doesn't sound like a test tone.
+                let pitch = base_pitch * (1.0 + 0.08 * (((i + syllable) % 3) as f32 - 1.0));
+                for n in 0..syllable_len {
+                    let t = n as f32 / SAMPLE_RATE as f32;
+                    let envelope = (std::f32::consts::PI * n as f32 / syllable_len as f32).sin();
+                    let sample = (std::f32::consts::TAU * pitch * t).sin() * envelope * AMPLITUDE;
+                    samples.push((sample * i16::MAX as f32) as i16);
+                }
+            }
+            samples.extend(std::iter::repeat_n(0, pause_len));
+        }
+
+        Ok(GeneratedSpeech {
+            wav: encode_wav(&samples, SAMPLE_RATE),
+        })
+    }
+}
+
+fn base_pitch(voice: SpeechVoice) -> f32 {
+    let index = SpeechVoice::ALL
+        .iter()
+        .position(|candidate| *candidate == voice)
+        .unwrap_or_default();
+    140.0 + 30.0 * index as f32
+}
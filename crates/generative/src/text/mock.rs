@@ -0,0 +1,62 @@
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+
+use crate::error::GenerativeResult;
+
+use super::{ChatRole, TextGenerationRequest, TextGenerationResult, TextGenerator};
+
+/// A text generator that answers without any network access, for tests and offline development.
+///
+/// It replies with the given canned replies in order, cycling through them. Without replies, it
+/// echoes the last user message. Every request it receives is recorded.
+#[derive(Default)]
+pub struct MockTextGenerator {
+    replies: Vec<String>,
+    requests: Mutex<Vec<TextGenerationRequest>>,
+}
+
+impl MockTextGenerator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_replies(replies: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        Self {
+            replies: replies.into_iter().map(Into::into).collect(),
+            requests: Mutex::default(),
+        }
+    }
+
+    /// The requests received so far, oldest first.
+    pub fn requests(&self) -> Vec<TextGenerationRequest> {
+        self.requests
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .clone()
+    }
+}
+
+#[async_trait]
+impl TextGenerator for MockTextGenerator {
+    async fn generate_text(
+        &self,
+        request: &TextGenerationRequest,
+    ) -> GenerativeResult<TextGenerationResult> {
+        let mut requests = self.requests.lock().unwrap_or_else(|err| err.into_inner());
+        let text = if self.replies.is_empty() {
+            let last_user_message = request
+                .messages
+                .iter()
+                .rev()
+                .find(|message| message.role == ChatRole::User)
+                .map(|message| message.content.as_str())
+                .unwrap_or_default();
+            format!("You said: {last_user_message}")
+        } else {
+            self.replies[requests.len() % self.replies.len()].clone()
+        };
+        requests.push(request.clone());
+        Ok(TextGenerationResult { text })
+    }
+}
@@ -0,0 +1,120 @@
+use async_openai::Client;
+use async_openai::config::OpenAIConfig;
+use async_openai::types::{
+    ChatCompletionRequestAssistantMessage, ChatCompletionRequestMessage,
+    ChatCompletionRequestSystemMessage, ChatCompletionRequestUserMessage,
+    CreateChatCompletionRequest, CreateChatCompletionRequestArgs, CreateChatCompletionResponse,
+};
+
+use crate::error::{GenerativeError, GenerativeResult};
+
+use super::{ChatMessage, ChatRole, TextGenerationRequest, TextGenerationResult, TextGenerator};
+
+const DEFAULT_TEXT_MODEL: &str = "gpt-4o-mini";
+
+#[derive(Clone)]
+pub struct OpenAiTextGenerator<C = OpenAIConfig>
+where
+    C: async_openai::config::Config,
+{
+    client: Client<C>,
+    model: String,
+}
+
+impl<C> OpenAiTextGenerator<C>
+where
+    C: async_openai::config::Config + Clone + Send + Sync + 'static,
+{
+    pub fn new(client: Client<C>) -> Self {
+        Self {
+            client,
+            model: DEFAULT_TEXT_MODEL.to_string(),
+        }
+    }
+
+    pub fn with_model_name(client: Client<C>, model: impl Into<String>) -> Self {
+        Self {
+            client,
+            model: model.into(),
+        }
+    }
+
+    pub fn client(&self) -> &Client<C> {
+        &self.client
+    }
+
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+}
+
+impl Default for OpenAiTextGenerator<OpenAIConfig> {
+    fn default() -> Self {
+        Self::new(Client::new())
+    }
+}
+
+#[async_trait::async_trait]
+impl<C> TextGenerator for OpenAiTextGenerator<C>
+where
+    C: async_openai::config::Config + Clone + Send + Sync + 'static,
+{
+    async fn generate_text(
+        &self,
+        request: &TextGenerationRequest,
+    ) -> GenerativeResult<TextGenerationResult> {
+        let openai_response = self
+            .client
+            .chat()
+            .create(build_request(request, &self.model)?)
+            .await?;
+
+        parse_response(openai_response)
+    }
+}
+
+fn build_request(
+    request: &TextGenerationRequest,
+    model: &str,
+) -> GenerativeResult<CreateChatCompletionRequest> {
+    let mut builder = CreateChatCompletionRequestArgs::default();
+    builder.model(model);
+    builder.messages(
+        request
+            .messages
+            .iter()
+            .map(to_openai_message)
+            .collect::<Vec<_>>(),
+    );
+    if let Some(temperature) = request.temperature {
+        builder.temperature(temperature);
+    }
+    if let Some(max_tokens) = request.max_tokens {
+        builder.max_completion_tokens(max_tokens);
+    }
+
+    builder.build().map_err(GenerativeError::from)
+}
+
+fn to_openai_message(message: &ChatMessage) -> ChatCompletionRequestMessage {
+    let content = message.content.clone();
+    match message.role {
+        ChatRole::System => ChatCompletionRequestSystemMessage::from(content).into(),
+        ChatRole::User => ChatCompletionRequestUserMessage::from(content).into(),
+        ChatRole::Assistant => ChatCompletionRequestAssistantMessage::from(content).into(),
+    }
+}
+
+fn parse_response(
+    response: CreateChatCompletionResponse,
+) -> GenerativeResult<TextGenerationResult> {
+    let text = response
+        .choices
+        .into_iter()
+        .next()
+        .and_then(|choice| choice.message.content)
+        .filter(|text| !text.trim().is_empty())
+        .ok_or(GenerativeError::MissingTextData)?;
+
+    Ok(TextGenerationResult { text })
+}